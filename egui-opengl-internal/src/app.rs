@@ -1,28 +1,40 @@
 use std::ffi::c_void;
-use crate::{input::InputCollector, painter, utils};
+use crate::{
+    input::InputCollector, load_gl, win32::ime, Error, FrameStats, GlLoader, Painter, Renderer, Result,
+    StateBackup, WglLoader,
+};
 use clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
-use egui::{Context, FontData, FontDefinitions, FontId};
+use egui::{Context, FontData, FontDefinitions, FontId, MouseWheelUnit};
 use once_cell::sync::OnceCell;
 use std::ops::DerefMut;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use egui::FontFamily::Proportional;
 use egui::TextStyle::{Body, Button, Heading, Monospace, Name, Small};
 use windows::Win32::{
-    Foundation::{HWND, LPARAM, RECT, WPARAM},
+    Foundation::{HWND, LPARAM, POINT, RECT, WPARAM},
     Graphics::{
         Gdi::{WindowFromDC, HDC},
-        OpenGL::{wglCreateContext, wglGetCurrentContext, wglMakeCurrent, HGLRC},
+        OpenGL::{wglCreateContext, wglDeleteContext, wglGetCurrentContext, wglMakeCurrent, HGLRC},
+    },
+    UI::Input::Ime::{
+        ImmGetContext, ImmReleaseContext, ImmSetCandidateWindow, ImmSetCompositionWindow,
+        CANDIDATEFORM, CFS_EXCLUDE, CFS_POINT, COMPOSITIONFORM,
     },
     UI::WindowsAndMessaging::{GetClientRect, WM_SIZE},
 };
 
 #[allow(clippy::type_complexity)]
-struct AppData<T> {
+struct AppData<T, R> {
     ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
     gl_context: HGLRC,
     window: HWND,
-    painter: painter::Painter,
+    renderer: R,
+    /// Size the renderer was last resized to.
+    renderer_size: Option<(u32, u32)>,
     input_collector: InputCollector,
+    /// Text cursor the IME windows were last moved to.
+    ime_area: Option<[i32; 4]>,
     ctx: Context,
     client_rect: (u32, u32),
     state: T,
@@ -35,17 +47,28 @@ use spin::lock_api::{Mutex, MutexGuard};
 
 use lock_api::MappedMutexGuard;
 
+/// Maps a failed WGL call to [`Error::Context`].
+fn wgl_error(call: &'static str) -> impl FnOnce(windows::core::Error) -> Error {
+    move |err| Error::Context {
+        call,
+        message: err.message(),
+    }
+}
+
 
 /// Heart and soul of this integration.
 /// Main methods you are going to use are:
 /// * [`Self::render`] - Should be called inside of wglSwapBuffers hook.
 /// * [`Self::wnd_proc`] - Should be called on each `WndProc`.
-pub struct OpenGLApp<T = ()> {
-    data: Mutex<Option<AppData<T>>>,
+///
+/// Frames are drawn by a [`Renderer`], the OpenGL [`Painter`] unless another one is passed to [`Self::init_with_renderer`].
+pub struct OpenGLApp<T = (), R = Painter> {
+    data: Mutex<Option<AppData<T, R>>>,
     hwnd: OnceCell<HWND>,
+    destroyed: AtomicBool,
 }
 
-impl<T> Default for OpenGLApp<T> {
+impl<T, R> Default for OpenGLApp<T, R> {
     fn default() -> Self {
         Self::new()
     }
@@ -53,30 +76,63 @@ impl<T> Default for OpenGLApp<T> {
 
 
 
-impl<T> OpenGLApp<T> {
+impl<T, R> OpenGLApp<T, R> {
     /// Creates new [`OpenGLApp`] in const context. You are supposed to create a single static item to store the application state.
     pub const fn new() -> Self {
         Self {
             data: Mutex::new(None),
             hwnd: OnceCell::new(),
+            destroyed: AtomicBool::new(false),
         }
     }
 
     /// Checks if the app is ready to draw and if it's safe to invoke `render`, `wndproc`, etc.
-    /// `true` means that you have already called an `init_*` on the application.
+    /// `true` means that you have already called an `init_*` on the application and not [`Self::destroy`].
     pub fn is_ready(&self) -> bool {
-        self.hwnd.get().is_some()
+        self.hwnd.get().is_some() && !self.destroyed.load(Ordering::Acquire)
     }
 
-    /// Initializes application and state. You should call this only once!
-    pub fn init_with_state_context(
+    #[cfg(feature = "parking-lot")]
+    pub fn lock_state(&self) -> MappedMutexGuard<'_, parking_lot::RawMutex, T> {
+        MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
+    }
+
+    #[cfg(feature = "spin-lock")]
+    pub fn lock_state(&self) -> MappedMutexGuard<'_, spin::mutex::Mutex<()>, T> {
+        MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
+    }
+
+    /// Unit of the mouse wheel events, see [`InputCollector::set_wheel_unit`]. Needs an `init_*` call first.
+    pub fn set_wheel_unit(&self, unit: MouseWheelUnit) {
+        self.lock_data().input_collector.set_wheel_unit(unit);
+    }
+
+    /// Reads input from `WM_INPUT`, see [`InputCollector::set_raw_input`]. Needs an `init_*` call first.
+    pub fn set_raw_input(&self, enabled: bool) {
+        self.lock_data().input_collector.set_raw_input(enabled);
+    }
+
+    fn lock_data(&self) -> impl DerefMut<Target = AppData<T, R>> + '_ {
+        MutexGuard::map(self.data.lock(), |app| {
+            expect!(app.as_mut(), "You need to call init first")
+        })
+    }
+}
+
+impl<T, R: Renderer> OpenGLApp<T, R> {
+    /// Initializes application and state with a custom renderer. You should call this only once!
+    /// `create_renderer` runs with the app's own OpenGL context current,
+    /// so a renderer that uses OpenGL has to load its functions before, e.g. with [`load_gl`].
+    /// On error nothing is kept and the app stays uninitialized.
+    pub fn init_with_renderer(
         &self,
         hdc: HDC,
         window: HWND,
         ui: impl FnMut(&Context, &mut T) + 'static,
         state: T,
         context: Context,
-    ) {
+        create_renderer: impl FnOnce() -> Result<R>,
+    ) -> Result<()> {
         unsafe {
             if self.hwnd.get().is_some() {
                 panic_msg!("You must call init only once");
@@ -86,30 +142,72 @@ impl<T> OpenGLApp<T> {
                 panic_msg!("Invalid output window descriptor");
             }
 
-            let _ = self.hwnd.set(window);
-
-            // loads gl with all the opengl functions using get_proc_address which is hardcoded to look in the opengl32.dll module
-            gl::load_with(|s| utils::get_proc_address(s) as *const _);
-
             let o_context = wglGetCurrentContext();
-            let gl_context = wglCreateContext(hdc).unwrap();
-            wglMakeCurrent(hdc, gl_context).unwrap();
+            let gl_context = wglCreateContext(hdc).map_err(wgl_error("wglCreateContext"))?;
+
+            let renderer = wglMakeCurrent(hdc, gl_context)
+                .map_err(wgl_error("wglMakeCurrent"))
+                .and_then(|()| create_renderer());
+            let restored = wglMakeCurrent(hdc, o_context).map_err(wgl_error("wglMakeCurrent"));
 
-            let painter = painter::Painter::new();
+            let renderer = match renderer.and_then(|renderer| restored.map(|()| renderer)) {
+                Ok(renderer) => renderer,
+                Err(err) => {
+                    let _ = wglDeleteContext(gl_context);
+                    return Err(err);
+                }
+            };
 
+            let _ = self.hwnd.set(window);
             *self.data.lock() = Some(AppData {
                 input_collector: InputCollector::new(window),
+                ime_area: None,
                 ui: Box::new(ui),
                 gl_context,
                 window,
                 ctx: context,
                 client_rect: (0, 0),
                 state,
-                painter,
+                renderer,
+                renderer_size: None,
             });
-
-            wglMakeCurrent(hdc, o_context).unwrap();
         }
+
+        Ok(())
+    }
+}
+
+impl<T> OpenGLApp<T> {
+    /// Initializes application and state. You should call this only once!
+    /// On error nothing is kept and the app stays uninitialized.
+    #[inline]
+    pub fn init_with_state_context(
+        &self,
+        hdc: HDC,
+        window: HWND,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+    ) -> Result<()> {
+        self.init_with_loader(hdc, window, ui, state, context, &WglLoader)
+    }
+
+    /// Same as [`Self::init_with_state_context`], but resolves OpenGL functions through `loader`
+    /// instead of `opengl32.dll`, e.g. for ANGLE's [`crate::EglLoader`].
+    pub fn init_with_loader(
+        &self,
+        hdc: HDC,
+        window: HWND,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+        loader: &impl GlLoader,
+    ) -> Result<()> {
+        load_gl(loader).into_result()?;
+
+        self.init_with_renderer(hdc, window, ui, state, context, || {
+            Painter::new(StateBackup::default())
+        })
     }
 
     /// Initializes application and state. Sets egui's context to default value. You should call this only once!
@@ -120,7 +218,7 @@ impl<T> OpenGLApp<T> {
         window: HWND,
         ui: impl FnMut(&Context, &mut T) + 'static,
         state: T,
-    ) {
+    ) -> Result<()> {
         self.init_with_state_context(hdc, window, ui, state, Context::default())
     }
 
@@ -133,34 +231,34 @@ impl<T> OpenGLApp<T> {
         ui: impl FnMut(&Context, &mut T) + 'static,
         mut state: T,
         mutate: impl FnOnce(&mut Context, &mut T),
-    ) {
+    ) -> Result<()> {
         let mut ctx = Context::default();
         mutate(&mut ctx, &mut state);
 
-        self.init_with_state_context(hdc, window, ui, state, ctx);
-    }
-
-    #[cfg(feature = "parking-lot")]
-    pub fn lock_state(&self) -> MappedMutexGuard<'_, parking_lot::RawMutex, T> {
-        MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
+        self.init_with_state_context(hdc, window, ui, state, ctx)
     }
 
-    #[cfg(feature = "spin-lock")]
-    pub fn lock_state(&self) -> MappedMutexGuard<'_, spin::mutex::Mutex<()>, T> {
-        MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
+    /// Selects how much of the host's OpenGL state is saved and restored around each [`Self::render`].
+    /// Defaults to [`StateBackup::Full`].
+    pub fn set_state_backup(&self, state_backup: StateBackup) {
+        self.lock_data().renderer.set_state_backup(state_backup);
     }
 
-    fn lock_data(&self) -> impl DerefMut<Target = AppData<T>> + '_ {
-        MutexGuard::map(self.data.lock(), |app| {
-            expect!(app.as_mut(), "You need to call init first")
-        })
+    /// Draw call and upload counters for the most recently rendered frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.lock_data().renderer.frame_stats()
     }
 }
 
 impl<T: Default> OpenGLApp<T> {
     /// Initializes application and sets the state to its default value. You should call this only once!
     #[inline]
-    pub fn init_default(&self, hdc: HDC, window: HWND, ui: impl FnMut(&Context, &mut T) + 'static) {
+    pub fn init_default(
+        &self,
+        hdc: HDC,
+        window: HWND,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+    ) -> Result<()> {
         let ctx = Context::default();
 
         let font_file = {
@@ -195,52 +293,37 @@ impl<T: Default> OpenGLApp<T> {
         ].into();
 
         ctx.set_style(style);
-        self.init_with_state_context(hdc, window, ui, T::default(), ctx);
+        self.init_with_state_context(hdc, window, ui, T::default(), ctx)
     }
 }
 
-impl<T> OpenGLApp<T> {
+impl<T, R: Renderer> OpenGLApp<T, R> {
     /// Present call. Should be called once per original present call, before or inside of hook.
+    /// An error means this frame was skipped, the host's context is current again and later frames may still succeed.
     #[allow(invalid_reference_casting)]
-    pub fn render(&self, hdc: HDC) {
+    pub fn render(&self, hdc: HDC) -> Result<()> {
         unsafe {
             let this = &mut *self.lock_data();
 
             let window = WindowFromDC(hdc);
             if !window.eq(&this.window) {
                 this.window = window;
+                let wheel_unit = this.input_collector.wheel_unit();
+                let raw_input = this.input_collector.raw_input();
                 this.input_collector = InputCollector::new(window);
+                this.input_collector.set_wheel_unit(wheel_unit);
+                this.input_collector.set_raw_input(raw_input);
+                this.ime_area = None;
                 this.client_rect = self.get_client_rect(this.window);
             }
 
             let o_context = wglGetCurrentContext();
-            wglMakeCurrent(hdc, this.gl_context).unwrap();
-
-            let output = this
-                .ctx
-                .run(this.input_collector.collect_input(&this.ctx), |ctx| {
-                    (this.ui)(ctx, &mut this.state);
-                });
+            wglMakeCurrent(hdc, this.gl_context).map_err(wgl_error("wglMakeCurrent"))?;
 
-            if !output.platform_output.copied_text.is_empty() {
-                let _ = WindowsClipboardContext.set_contents(output.platform_output.copied_text);
-            }
+            let result = self.paint_frame(this);
 
-            if output.shapes.is_empty() {
-                wglMakeCurrent(hdc, o_context).unwrap();
-                return;
-            }
-
-            let client_rect = self.poll_client_rect(this);
-            let clipped_shapes = this.ctx.tessellate(output.shapes, 1.);
-            this.painter.paint_and_update_textures(
-                1.0,
-                &clipped_shapes,
-                &output.textures_delta,
-                &client_rect,
-            );
-
-            wglMakeCurrent(hdc, o_context).unwrap();
+            wglMakeCurrent(hdc, o_context).map_err(wgl_error("wglMakeCurrent"))?;
+            result
         }
     }
 
@@ -268,11 +351,70 @@ impl<T> OpenGLApp<T> {
         let data = &mut *self.lock_data();
         data.window
     }
+
+    /// Destroys the renderer and deletes the overlay's context, e.g. before unhooking.
+    /// Must not run concurrently with [`Self::render`]. Afterwards the app is no longer ready and cannot be initialized again.
+    pub fn destroy(&self, hdc: HDC) -> Result<()> {
+        let Some(mut data) = self.data.lock().take() else {
+            return Ok(());
+        };
+        self.destroyed.store(true, Ordering::Release);
+
+        unsafe {
+            let o_context = wglGetCurrentContext();
+            // Deleting the context frees whatever the renderer could not, so that still happens on failure.
+            let current = wglMakeCurrent(hdc, data.gl_context).map_err(wgl_error("wglMakeCurrent"));
+            if current.is_ok() {
+                data.renderer.destroy();
+            }
+            let restored = wglMakeCurrent(hdc, o_context).map_err(wgl_error("wglMakeCurrent"));
+            let deleted = wglDeleteContext(data.gl_context).map_err(wgl_error("wglDeleteContext"));
+
+            current.and(restored).and(deleted)
+        }
+    }
 }
 
-impl<T> OpenGLApp<T> {
+impl<T, R: Renderer> OpenGLApp<T, R> {
+    /// Runs the ui and draws it with the overlay's context current.
+    fn paint_frame(&self, this: &mut AppData<T, R>) -> Result<()> {
+        let output = this
+            .ctx
+            .run(this.input_collector.collect_input(&this.ctx), |ctx| {
+                (this.ui)(ctx, &mut this.state);
+            });
+
+        if !output.platform_output.copied_text.is_empty() {
+            let _ = WindowsClipboardContext.set_contents(output.platform_output.copied_text);
+        }
+
+        // Only a focused text edit reports where its cursor is.
+        let ime_area = output
+            .platform_output
+            .ime
+            .map(|ime| ime::candidate_area(ime.cursor_rect, this.ctx.pixels_per_point()));
+        if let Some(area) = ime_area.filter(|area| this.ime_area != Some(*area)) {
+            self.set_ime_area(this.window, area);
+        }
+        this.ime_area = ime_area;
+
+        if output.shapes.is_empty() {
+            return Ok(());
+        }
+
+        let client_rect = self.poll_client_rect(this);
+        if this.renderer_size != Some(client_rect) {
+            this.renderer.resize(client_rect);
+            this.renderer_size = Some(client_rect);
+        }
+
+        let clipped_shapes = this.ctx.tessellate(output.shapes, 1.);
+        this.renderer
+            .render_frame(1.0, &clipped_shapes, &output.textures_delta)
+    }
+
     #[inline]
-    fn poll_client_rect(&self, data: &mut AppData<T>) -> (u32, u32) {
+    fn poll_client_rect(&self, data: &mut AppData<T, R>) -> (u32, u32) {
         static INIT: std::sync::Once = std::sync::Once::new();
         INIT.call_once(|| {
             data.client_rect = self.get_client_rect(data.window);
@@ -281,6 +423,33 @@ impl<T> OpenGLApp<T> {
         data.client_rect
     }
 
+    /// Moves the IME's composition window to the text cursor and keeps its candidate window from covering it.
+    fn set_ime_area(&self, window: HWND, [left, top, right, bottom]: [i32; 4]) {
+        unsafe {
+            let himc = ImmGetContext(window);
+            if himc.is_invalid() {
+                return;
+            }
+
+            let composition = COMPOSITIONFORM {
+                dwStyle: CFS_POINT,
+                ptCurrentPos: POINT { x: left, y: top },
+                ..Default::default()
+            };
+            let _ = ImmSetCompositionWindow(himc, &composition);
+
+            let candidate = CANDIDATEFORM {
+                dwIndex: 0,
+                dwStyle: CFS_EXCLUDE,
+                ptCurrentPos: POINT { x: left, y: bottom },
+                rcArea: RECT { left, top, right, bottom },
+            };
+            let _ = ImmSetCandidateWindow(himc, &candidate);
+
+            let _ = ImmReleaseContext(window, himc);
+        }
+    }
+
     #[inline]
     fn get_client_rect(&self, window: HWND) -> (u32, u32) {
         let mut rect = RECT::default();
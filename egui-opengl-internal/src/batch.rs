@@ -0,0 +1,242 @@
+use egui::{
+    epaint::{Mesh, Primitive, Vertex},
+    ClippedPrimitive, Rect, TextureId,
+};
+
+/// Counters for the last painted frame, to see how well meshes were batched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Non-empty meshes egui produced.
+    pub meshes: usize,
+    /// `glDrawElements*` calls issued for those meshes.
+    pub draw_calls: usize,
+    /// Paint callbacks invoked.
+    pub callbacks: usize,
+    /// Vertices uploaded.
+    pub vertices: usize,
+    /// Indices uploaded.
+    pub indices: usize,
+}
+
+/// A range of the frame's index buffer drawn with one texture and clip rect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MeshDraw {
+    pub texture_id: TextureId,
+    pub clip_rect: Rect,
+    /// Position of the first index in the frame's index buffer.
+    pub first_index: usize,
+    pub index_count: usize,
+    /// Added to every index by `glDrawElementsBaseVertex`.
+    /// Always `0` when base vertices are not supported and the indices are absolute instead.
+    pub base_vertex: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DrawCommand {
+    Mesh(MeshDraw),
+    /// Index into the frame's clipped primitives.
+    Callback(usize),
+}
+
+/// All vertices and indices of a frame packed into one buffer each, plus the draws that read them.
+/// Kept by the painter so the allocations are reused across frames.
+#[derive(Default)]
+pub(crate) struct FrameBatch {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub commands: Vec<DrawCommand>,
+    pub meshes: usize,
+}
+
+impl FrameBatch {
+    /// Packs `clipped_primitives`, merging consecutive meshes that share a texture and clip rect into one draw.
+    pub fn build(&mut self, clipped_primitives: &[ClippedPrimitive], base_vertex: bool) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.commands.clear();
+        self.meshes = 0;
+
+        for (
+            i,
+            ClippedPrimitive {
+                clip_rect,
+                primitive,
+            },
+        ) in clipped_primitives.iter().enumerate()
+        {
+            match primitive {
+                Primitive::Mesh(mesh) => self.push_mesh(mesh, *clip_rect, base_vertex),
+                Primitive::Callback(_) => self.commands.push(DrawCommand::Callback(i)),
+            }
+        }
+    }
+
+    fn push_mesh(&mut self, mesh: &Mesh, clip_rect: Rect, base_vertex: bool) {
+        debug_assert!(mesh.is_valid());
+
+        if mesh.is_empty() {
+            return;
+        }
+        self.meshes += 1;
+
+        let vertex_start = self.vertices.len();
+        let draw = match self.commands.last_mut() {
+            Some(DrawCommand::Mesh(draw))
+                if draw.texture_id == mesh.texture_id && draw.clip_rect == clip_rect =>
+            {
+                draw
+            }
+            _ => {
+                self.commands.push(DrawCommand::Mesh(MeshDraw {
+                    texture_id: mesh.texture_id,
+                    clip_rect,
+                    first_index: self.indices.len(),
+                    index_count: 0,
+                    base_vertex: if base_vertex { vertex_start } else { 0 },
+                }));
+                let Some(DrawCommand::Mesh(draw)) = self.commands.last_mut() else {
+                    unreachable!()
+                };
+                draw
+            }
+        };
+
+        let offset = (vertex_start - draw.base_vertex) as u32;
+        self.indices
+            .extend(mesh.indices.iter().map(|&idx| idx + offset));
+        self.vertices.extend_from_slice(&mesh.vertices);
+        draw.index_count += mesh.indices.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{epaint::PaintCallback, pos2, vec2, Color32};
+    use std::sync::Arc;
+
+    fn rect_mesh(x: f32, texture_id: TextureId) -> Mesh {
+        let mut mesh = Mesh::with_texture(texture_id);
+        mesh.add_rect_with_uv(
+            Rect::from_min_size(pos2(x, 0.0), vec2(1.0, 1.0)),
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        mesh
+    }
+
+    fn mesh(clip_rect: Rect, mesh: Mesh) -> ClippedPrimitive {
+        ClippedPrimitive {
+            clip_rect,
+            primitive: Primitive::Mesh(mesh),
+        }
+    }
+
+    fn callback(clip_rect: Rect) -> ClippedPrimitive {
+        ClippedPrimitive {
+            clip_rect,
+            primitive: Primitive::Callback(PaintCallback {
+                rect: clip_rect,
+                callback: Arc::new(()),
+            }),
+        }
+    }
+
+    /// Vertex positions of every triangle, as the GPU would resolve them.
+    fn resolve(batch: &FrameBatch) -> Vec<egui::Pos2> {
+        batch
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Mesh(draw) => Some(draw),
+                DrawCommand::Callback(_) => None,
+            })
+            .flat_map(|draw| {
+                batch.indices[draw.first_index..draw.first_index + draw.index_count]
+                    .iter()
+                    .map(|&idx| batch.vertices[draw.base_vertex + idx as usize].pos)
+            })
+            .collect()
+    }
+
+    fn primitives() -> Vec<ClippedPrimitive> {
+        let clip_a = Rect::from_min_size(pos2(0.0, 0.0), vec2(100.0, 100.0));
+        let clip_b = Rect::from_min_size(pos2(50.0, 0.0), vec2(10.0, 10.0));
+        let font = TextureId::Managed(0);
+        let image = TextureId::User(1);
+
+        vec![
+            mesh(clip_a, rect_mesh(0.0, font)),
+            mesh(clip_a, rect_mesh(1.0, font)),
+            mesh(clip_a, rect_mesh(2.0, font)),
+            mesh(clip_a, rect_mesh(3.0, image)),
+            mesh(clip_b, rect_mesh(4.0, image)),
+            mesh(clip_b, Mesh::with_texture(image)),
+            mesh(clip_b, rect_mesh(5.0, image)),
+            callback(clip_b),
+            mesh(clip_b, rect_mesh(6.0, image)),
+        ]
+    }
+
+    #[test]
+    fn test_merges_consecutive_meshes() {
+        let mut batch = FrameBatch::default();
+        batch.build(&primitives(), true);
+
+        assert_eq!(batch.meshes, 7);
+        assert_eq!(batch.vertices.len(), 7 * 4);
+        assert_eq!(batch.indices.len(), 7 * 6);
+
+        let counts: Vec<_> = batch
+            .commands
+            .iter()
+            .map(|command| match command {
+                DrawCommand::Mesh(draw) => draw.index_count,
+                DrawCommand::Callback(i) => 1000 + i,
+            })
+            .collect();
+        // The empty mesh is dropped and does not split the run around it, the callback does.
+        assert_eq!(counts, [18, 6, 12, 1007, 6]);
+    }
+
+    #[test]
+    fn test_base_vertex_and_absolute_indices_agree() {
+        let mut with_base = FrameBatch::default();
+        with_base.build(&primitives(), true);
+        let mut absolute = FrameBatch::default();
+        absolute.build(&primitives(), false);
+
+        assert!(absolute.commands.iter().all(|command| match command {
+            DrawCommand::Mesh(draw) => draw.base_vertex == 0,
+            DrawCommand::Callback(_) => true,
+        }));
+        assert_eq!(with_base.commands.len(), absolute.commands.len());
+        assert_eq!(resolve(&with_base), resolve(&absolute));
+
+        let expected: Vec<_> = primitives()
+            .into_iter()
+            .filter_map(|primitive| match primitive.primitive {
+                Primitive::Mesh(mesh) => Some(mesh),
+                Primitive::Callback(_) => None,
+            })
+            .flat_map(|mesh| {
+                mesh.indices
+                    .iter()
+                    .map(|&idx| mesh.vertices[idx as usize].pos)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(resolve(&with_base), expected);
+    }
+
+    #[test]
+    fn test_rebuild_clears_previous_frame() {
+        let mut batch = FrameBatch::default();
+        batch.build(&primitives(), true);
+        batch.build(&primitives()[..1], true);
+
+        assert_eq!(batch.meshes, 1);
+        assert_eq!(batch.vertices.len(), 4);
+        assert_eq!(batch.commands.len(), 1);
+    }
+}
@@ -0,0 +1,23 @@
+use egui::TextureId;
+
+/// Everything that can go wrong while setting up the overlay or drawing a frame.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Creating, switching to or deleting the overlay's own OpenGL context failed.
+    #[error("{call} failed: {message}")]
+    Context { call: &'static str, message: String },
+    /// OpenGL functions the painter needs could not be resolved.
+    #[error("failed to load OpenGL functions: {}", .missing.join(", "))]
+    GlLoad { missing: Vec<&'static str> },
+    /// The driver rejected one of the generated shaders.
+    #[error("failed to compile {stage} shader:\n{log}")]
+    ShaderCompile { stage: &'static str, log: String },
+    /// The shaders compiled but could not be linked into a usable program.
+    #[error("failed to link shader program:\n{log}")]
+    Link { log: String },
+    /// A texture could not be created or updated.
+    #[error("texture {id:?}: {reason}")]
+    Texture { id: TextureId, reason: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
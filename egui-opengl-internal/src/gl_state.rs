@@ -0,0 +1,687 @@
+use crate::shader::{gl_string, GlVersion};
+use gl::types::{GLboolean, GLenum, GLint, GLuint};
+
+/// Controls how much of the host's OpenGL state is saved before painting and restored afterwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StateBackup {
+    /// Nothing is saved. The painter's program, buffers and blend state are left bound after the frame.
+    None,
+    /// Every piece of state the painter touches is saved before painting and restored afterwards.
+    #[default]
+    Full,
+}
+
+/// Capabilities toggled by the painter, apart from `FRAMEBUFFER_SRGB`.
+const CAPABILITIES: [GLenum; 5] = [
+    gl::BLEND,
+    gl::SCISSOR_TEST,
+    gl::DEPTH_TEST,
+    gl::STENCIL_TEST,
+    gl::CULL_FACE,
+];
+
+/// Pixel unpack parameters that affect `glTexImage2D`/`glTexSubImage2D`, apart from `UNPACK_ALIGNMENT`.
+const UNPACK_SUBIMAGE_PARAMETERS: [GLenum; 3] = [
+    gl::UNPACK_ROW_LENGTH,
+    gl::UNPACK_SKIP_PIXELS,
+    gl::UNPACK_SKIP_ROWS,
+];
+
+/// The subset of OpenGL used to save and restore state.
+/// Exists so the snapshot logic can run against a recording mock in tests.
+pub(crate) trait GlFunctions {
+    unsafe fn get_integers(&self, pname: GLenum, out: &mut [GLint]);
+    unsafe fn is_enabled(&self, cap: GLenum) -> bool;
+    unsafe fn set_enabled(&self, cap: GLenum, enabled: bool);
+    unsafe fn blend_func_separate(
+        &self,
+        src_rgb: GLenum,
+        dst_rgb: GLenum,
+        src_alpha: GLenum,
+        dst_alpha: GLenum,
+    );
+    unsafe fn blend_equation_separate(&self, rgb: GLenum, alpha: GLenum);
+    unsafe fn viewport(&self, rect: [GLint; 4]);
+    unsafe fn scissor(&self, rect: [GLint; 4]);
+    unsafe fn use_program(&self, program: GLuint);
+    unsafe fn bind_vertex_array(&self, vao: GLuint);
+    unsafe fn bind_buffer(&self, target: GLenum, buffer: GLuint);
+    unsafe fn active_texture(&self, unit: GLenum);
+    unsafe fn bind_texture(&self, target: GLenum, texture: GLuint);
+    unsafe fn color_mask(&self, mask: [bool; 4]);
+    unsafe fn pixel_store(&self, pname: GLenum, param: GLint);
+    unsafe fn bind_sampler(&self, unit: GLuint, sampler: GLuint);
+    unsafe fn polygon_mode(&self, mode: GLenum);
+    /// Sampler objects only exist since GL 3.3.
+    fn has_samplers(&self) -> bool;
+    /// `glPolygonMode` does not exist on GLES.
+    fn has_polygon_mode(&self) -> bool;
+    /// `UNPACK_ROW_LENGTH` and `UNPACK_SKIP_*` need desktop GL, GLES 3.0 or `EXT_unpack_subimage`.
+    fn has_unpack_subimage(&self) -> bool;
+    /// Pixel unpack buffers need desktop GL, GLES 3.0 or `NV_pixel_buffer_object`.
+    fn has_unpack_buffer(&self) -> bool;
+    /// `FRAMEBUFFER_SRGB` is a desktop capability, GLES rejects it.
+    fn has_framebuffer_srgb(&self) -> bool;
+
+    unsafe fn get_integer(&self, pname: GLenum) -> GLint {
+        let mut value = 0;
+        self.get_integers(pname, std::slice::from_mut(&mut value));
+        value
+    }
+}
+
+/// Calls straight into the loaded `gl` function pointers of the context it was created on.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Gl {
+    version: GlVersion,
+    unpack_subimage: bool,
+    unpack_buffer: bool,
+}
+
+impl Gl {
+    /// Reads which optional state the current context has.
+    pub fn current(version: GlVersion) -> Self {
+        let full = !version.es || version.major >= 3;
+        // Core profiles reject `GL_EXTENSIONS`, and only GLES 2.0 needs it.
+        let extensions = if full {
+            String::new()
+        } else {
+            gl_string(gl::EXTENSIONS)
+        };
+        let has = |name| extensions.split_ascii_whitespace().any(|ext| ext == name);
+
+        Self {
+            version,
+            unpack_subimage: full || has("GL_EXT_unpack_subimage"),
+            unpack_buffer: full || has("GL_NV_pixel_buffer_object"),
+        }
+    }
+}
+
+impl GlFunctions for Gl {
+    unsafe fn get_integers(&self, pname: GLenum, out: &mut [GLint]) {
+        gl::GetIntegerv(pname, out.as_mut_ptr());
+    }
+
+    unsafe fn is_enabled(&self, cap: GLenum) -> bool {
+        gl::IsEnabled(cap) == gl::TRUE
+    }
+
+    unsafe fn set_enabled(&self, cap: GLenum, enabled: bool) {
+        if enabled {
+            gl::Enable(cap);
+        } else {
+            gl::Disable(cap);
+        }
+    }
+
+    unsafe fn blend_func_separate(
+        &self,
+        src_rgb: GLenum,
+        dst_rgb: GLenum,
+        src_alpha: GLenum,
+        dst_alpha: GLenum,
+    ) {
+        gl::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+    }
+
+    unsafe fn blend_equation_separate(&self, rgb: GLenum, alpha: GLenum) {
+        gl::BlendEquationSeparate(rgb, alpha);
+    }
+
+    unsafe fn viewport(&self, [x, y, w, h]: [GLint; 4]) {
+        gl::Viewport(x, y, w, h);
+    }
+
+    unsafe fn scissor(&self, [x, y, w, h]: [GLint; 4]) {
+        gl::Scissor(x, y, w, h);
+    }
+
+    unsafe fn use_program(&self, program: GLuint) {
+        gl::UseProgram(program);
+    }
+
+    unsafe fn bind_vertex_array(&self, vao: GLuint) {
+        gl::BindVertexArray(vao);
+    }
+
+    unsafe fn bind_buffer(&self, target: GLenum, buffer: GLuint) {
+        gl::BindBuffer(target, buffer);
+    }
+
+    unsafe fn active_texture(&self, unit: GLenum) {
+        gl::ActiveTexture(unit);
+    }
+
+    unsafe fn bind_texture(&self, target: GLenum, texture: GLuint) {
+        gl::BindTexture(target, texture);
+    }
+
+    unsafe fn color_mask(&self, [r, g, b, a]: [bool; 4]) {
+        gl::ColorMask(
+            r as GLboolean,
+            g as GLboolean,
+            b as GLboolean,
+            a as GLboolean,
+        );
+    }
+
+    unsafe fn pixel_store(&self, pname: GLenum, param: GLint) {
+        gl::PixelStorei(pname, param);
+    }
+
+    unsafe fn bind_sampler(&self, unit: GLuint, sampler: GLuint) {
+        gl::BindSampler(unit, sampler);
+    }
+
+    unsafe fn polygon_mode(&self, mode: GLenum) {
+        gl::PolygonMode(gl::FRONT_AND_BACK, mode);
+    }
+
+    // Drivers may resolve desktop functions on GLES contexts as well, so the version decides too.
+    fn has_samplers(&self) -> bool {
+        (!self.version.es || self.version.major >= 3) && gl::BindSampler::is_loaded()
+    }
+
+    fn has_polygon_mode(&self) -> bool {
+        !self.version.es && gl::PolygonMode::is_loaded()
+    }
+
+    fn has_unpack_subimage(&self) -> bool {
+        self.unpack_subimage
+    }
+
+    fn has_unpack_buffer(&self) -> bool {
+        self.unpack_buffer
+    }
+
+    fn has_framebuffer_srgb(&self) -> bool {
+        !self.version.es
+    }
+}
+
+/// Snapshot of every piece of OpenGL state [`crate::painter::Painter`] modifies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GlStateSnapshot {
+    capabilities: [bool; CAPABILITIES.len()],
+    framebuffer_srgb: Option<bool>,
+    blend_func: [GLint; 4],
+    blend_equation: [GLint; 2],
+    viewport: [GLint; 4],
+    scissor_box: [GLint; 4],
+    program: GLint,
+    vertex_array: GLint,
+    array_buffer: GLint,
+    element_array_buffer: GLint,
+    active_texture: GLint,
+    texture_2d: GLint,
+    sampler: Option<GLint>,
+    polygon_mode: Option<GLint>,
+    color_mask: [GLint; 4],
+    unpack_alignment: GLint,
+    unpack_subimage: Option<[GLint; UNPACK_SUBIMAGE_PARAMETERS.len()]>,
+    unpack_buffer: Option<GLint>,
+}
+
+impl GlStateSnapshot {
+    /// Records the current state. Texture and sampler bindings are read for `TEXTURE0`, the unit the painter draws with.
+    pub unsafe fn capture(gl: &impl GlFunctions) -> Self {
+        let mut capabilities = [false; CAPABILITIES.len()];
+        for (enabled, &cap) in capabilities.iter_mut().zip(&CAPABILITIES) {
+            *enabled = gl.is_enabled(cap);
+        }
+        let framebuffer_srgb = gl
+            .has_framebuffer_srgb()
+            .then(|| gl.is_enabled(gl::FRAMEBUFFER_SRGB));
+
+        let blend_func = [
+            gl.get_integer(gl::BLEND_SRC_RGB),
+            gl.get_integer(gl::BLEND_DST_RGB),
+            gl.get_integer(gl::BLEND_SRC_ALPHA),
+            gl.get_integer(gl::BLEND_DST_ALPHA),
+        ];
+        let blend_equation = [
+            gl.get_integer(gl::BLEND_EQUATION_RGB),
+            gl.get_integer(gl::BLEND_EQUATION_ALPHA),
+        ];
+
+        let mut viewport = [0; 4];
+        gl.get_integers(gl::VIEWPORT, &mut viewport);
+        let mut scissor_box = [0; 4];
+        gl.get_integers(gl::SCISSOR_BOX, &mut scissor_box);
+
+        let active_texture = gl.get_integer(gl::ACTIVE_TEXTURE);
+        gl.active_texture(gl::TEXTURE0);
+        let texture_2d = gl.get_integer(gl::TEXTURE_BINDING_2D);
+        let sampler = gl
+            .has_samplers()
+            .then(|| gl.get_integer(gl::SAMPLER_BINDING));
+        gl.active_texture(active_texture as GLenum);
+
+        let polygon_mode = gl.has_polygon_mode().then(|| {
+            // Returns front and back modes, but only FRONT_AND_BACK can be set on core contexts.
+            let mut modes = [0; 2];
+            gl.get_integers(gl::POLYGON_MODE, &mut modes);
+            modes[0]
+        });
+
+        let mut color_mask = [0; 4];
+        gl.get_integers(gl::COLOR_WRITEMASK, &mut color_mask);
+
+        let unpack_subimage = gl
+            .has_unpack_subimage()
+            .then(|| UNPACK_SUBIMAGE_PARAMETERS.map(|pname| gl.get_integer(pname)));
+
+        Self {
+            capabilities,
+            framebuffer_srgb,
+            blend_func,
+            blend_equation,
+            viewport,
+            scissor_box,
+            program: gl.get_integer(gl::CURRENT_PROGRAM),
+            vertex_array: gl.get_integer(gl::VERTEX_ARRAY_BINDING),
+            array_buffer: gl.get_integer(gl::ARRAY_BUFFER_BINDING),
+            element_array_buffer: gl.get_integer(gl::ELEMENT_ARRAY_BUFFER_BINDING),
+            active_texture,
+            texture_2d,
+            sampler,
+            polygon_mode,
+            color_mask,
+            unpack_alignment: gl.get_integer(gl::UNPACK_ALIGNMENT),
+            unpack_subimage,
+            unpack_buffer: gl
+                .has_unpack_buffer()
+                .then(|| gl.get_integer(gl::PIXEL_UNPACK_BUFFER_BINDING)),
+        }
+    }
+
+    /// Puts every recorded value back.
+    pub unsafe fn restore(&self, gl: &impl GlFunctions) {
+        for (&enabled, &cap) in self.capabilities.iter().zip(&CAPABILITIES) {
+            gl.set_enabled(cap, enabled);
+        }
+        if let Some(enabled) = self.framebuffer_srgb {
+            gl.set_enabled(gl::FRAMEBUFFER_SRGB, enabled);
+        }
+
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_func;
+        gl.blend_func_separate(src_rgb as _, dst_rgb as _, src_alpha as _, dst_alpha as _);
+        let [rgb, alpha] = self.blend_equation;
+        gl.blend_equation_separate(rgb as _, alpha as _);
+
+        gl.viewport(self.viewport);
+        gl.scissor(self.scissor_box);
+        gl.use_program(self.program as _);
+
+        // The element array binding is part of the VAO, so it has to go back after the VAO itself.
+        gl.bind_vertex_array(self.vertex_array as _);
+        gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.element_array_buffer as _);
+        gl.bind_buffer(gl::ARRAY_BUFFER, self.array_buffer as _);
+
+        gl.active_texture(gl::TEXTURE0);
+        gl.bind_texture(gl::TEXTURE_2D, self.texture_2d as _);
+        if let Some(sampler) = self.sampler {
+            gl.bind_sampler(0, sampler as _);
+        }
+        gl.active_texture(self.active_texture as _);
+
+        if let Some(mode) = self.polygon_mode {
+            gl.polygon_mode(mode as _);
+        }
+
+        let [r, g, b, a] = self.color_mask;
+        gl.color_mask([r != 0, g != 0, b != 0, a != 0]);
+
+        gl.pixel_store(gl::UNPACK_ALIGNMENT, self.unpack_alignment);
+        if let Some(unpack) = self.unpack_subimage {
+            for (value, pname) in unpack.into_iter().zip(UNPACK_SUBIMAGE_PARAMETERS) {
+                gl.pixel_store(pname, value);
+            }
+        }
+        if let Some(buffer) = self.unpack_buffer {
+            gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, buffer as _);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::painter::{reset_unpack_state, set_paint_state};
+    use std::{cell::RefCell, collections::HashMap};
+
+    /// Optional parts of the API a fake context has.
+    #[derive(Debug, Clone, Copy, Default)]
+    struct Features {
+        samplers: bool,
+        polygon_mode: bool,
+        unpack_subimage: bool,
+        unpack_buffer: bool,
+        framebuffer_srgb: bool,
+    }
+
+    const GL_33: Features = Features {
+        samplers: true,
+        polygon_mode: true,
+        unpack_subimage: true,
+        unpack_buffer: true,
+        framebuffer_srgb: true,
+    };
+    const GL_21: Features = Features {
+        samplers: false,
+        ..GL_33
+    };
+    const GLES_3: Features = Features {
+        samplers: true,
+        polygon_mode: false,
+        unpack_subimage: true,
+        unpack_buffer: true,
+        framebuffer_srgb: false,
+    };
+    const GLES_2: Features = Features {
+        samplers: false,
+        polygon_mode: false,
+        unpack_subimage: false,
+        unpack_buffer: false,
+        framebuffer_srgb: false,
+    };
+
+    /// Fake GL context that keeps state in a map and logs every call made through it.
+    #[derive(Default)]
+    struct RecordingGl {
+        state: RefCell<HashMap<(GLenum, GLint), Vec<GLint>>>,
+        calls: RefCell<Vec<&'static str>>,
+        features: Features,
+    }
+
+    impl RecordingGl {
+        fn new(features: Features) -> Self {
+            let gl = Self {
+                features,
+                ..Default::default()
+            };
+            gl.put(gl::ACTIVE_TEXTURE, &[gl::TEXTURE0 as _]);
+            gl.put(gl::COLOR_WRITEMASK, &[1, 1, 1, 1]);
+            gl.put(gl::POLYGON_MODE, &[gl::FILL as _, gl::FILL as _]);
+            gl
+        }
+
+        /// Texture unit bindings are stored per unit, everything else is global.
+        fn key(&self, pname: GLenum) -> (GLenum, GLint) {
+            match pname {
+                gl::TEXTURE_BINDING_2D | gl::SAMPLER_BINDING => {
+                    (pname, self.get(gl::ACTIVE_TEXTURE)[0])
+                }
+                _ => (pname, 0),
+            }
+        }
+
+        fn get(&self, pname: GLenum) -> Vec<GLint> {
+            let key = if pname == gl::ACTIVE_TEXTURE {
+                (pname, 0)
+            } else {
+                self.key(pname)
+            };
+            self.state
+                .borrow()
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| vec![0; 4])
+        }
+
+        fn put(&self, pname: GLenum, values: &[GLint]) {
+            let key = if pname == gl::ACTIVE_TEXTURE {
+                (pname, 0)
+            } else {
+                self.key(pname)
+            };
+            self.state.borrow_mut().insert(key, values.to_vec());
+        }
+
+        /// Current state with unset and zeroed entries dropped, so both compare equal.
+        fn current(&self) -> HashMap<(GLenum, GLint), Vec<GLint>> {
+            let mut state = self.state.borrow().clone();
+            state.retain(|_, values| values.iter().any(|&v| v != 0));
+            state
+        }
+
+        fn log(&self, call: &'static str) {
+            self.calls.borrow_mut().push(call);
+        }
+
+        /// Fails like `GL_INVALID_ENUM` would for names the context does not have.
+        fn check(&self, name: GLenum) {
+            let supported = match name {
+                gl::FRAMEBUFFER_SRGB => self.features.framebuffer_srgb,
+                gl::POLYGON_MODE => self.features.polygon_mode,
+                gl::UNPACK_ROW_LENGTH | gl::UNPACK_SKIP_PIXELS | gl::UNPACK_SKIP_ROWS => {
+                    self.features.unpack_subimage
+                }
+                gl::PIXEL_UNPACK_BUFFER | gl::PIXEL_UNPACK_BUFFER_BINDING => {
+                    self.features.unpack_buffer
+                }
+                _ => true,
+            };
+            assert!(
+                supported,
+                "{name:#06x} is not supported by {:?}",
+                self.features
+            );
+        }
+    }
+
+    impl GlFunctions for RecordingGl {
+        unsafe fn get_integers(&self, pname: GLenum, out: &mut [GLint]) {
+            self.log("GetIntegerv");
+            self.check(pname);
+            let values = self.get(pname);
+            out.copy_from_slice(&values[..out.len()]);
+        }
+
+        unsafe fn is_enabled(&self, cap: GLenum) -> bool {
+            self.log("IsEnabled");
+            self.check(cap);
+            self.get(cap)[0] != 0
+        }
+
+        unsafe fn set_enabled(&self, cap: GLenum, enabled: bool) {
+            self.log(if enabled { "Enable" } else { "Disable" });
+            self.check(cap);
+            self.put(cap, &[enabled as _]);
+        }
+
+        unsafe fn blend_func_separate(
+            &self,
+            src_rgb: GLenum,
+            dst_rgb: GLenum,
+            src_alpha: GLenum,
+            dst_alpha: GLenum,
+        ) {
+            self.log("BlendFuncSeparate");
+            self.put(gl::BLEND_SRC_RGB, &[src_rgb as _]);
+            self.put(gl::BLEND_DST_RGB, &[dst_rgb as _]);
+            self.put(gl::BLEND_SRC_ALPHA, &[src_alpha as _]);
+            self.put(gl::BLEND_DST_ALPHA, &[dst_alpha as _]);
+        }
+
+        unsafe fn blend_equation_separate(&self, rgb: GLenum, alpha: GLenum) {
+            self.log("BlendEquationSeparate");
+            self.put(gl::BLEND_EQUATION_RGB, &[rgb as _]);
+            self.put(gl::BLEND_EQUATION_ALPHA, &[alpha as _]);
+        }
+
+        unsafe fn viewport(&self, rect: [GLint; 4]) {
+            self.log("Viewport");
+            self.put(gl::VIEWPORT, &rect);
+        }
+
+        unsafe fn scissor(&self, rect: [GLint; 4]) {
+            self.log("Scissor");
+            self.put(gl::SCISSOR_BOX, &rect);
+        }
+
+        unsafe fn use_program(&self, program: GLuint) {
+            self.log("UseProgram");
+            self.put(gl::CURRENT_PROGRAM, &[program as _]);
+        }
+
+        unsafe fn bind_vertex_array(&self, vao: GLuint) {
+            self.log("BindVertexArray");
+            self.put(gl::VERTEX_ARRAY_BINDING, &[vao as _]);
+        }
+
+        unsafe fn bind_buffer(&self, target: GLenum, buffer: GLuint) {
+            self.log("BindBuffer");
+            self.check(target);
+            let pname = match target {
+                gl::ARRAY_BUFFER => gl::ARRAY_BUFFER_BINDING,
+                gl::ELEMENT_ARRAY_BUFFER => gl::ELEMENT_ARRAY_BUFFER_BINDING,
+                gl::PIXEL_UNPACK_BUFFER => gl::PIXEL_UNPACK_BUFFER_BINDING,
+                _ => unreachable!(),
+            };
+            self.put(pname, &[buffer as _]);
+        }
+
+        unsafe fn active_texture(&self, unit: GLenum) {
+            self.log("ActiveTexture");
+            self.put(gl::ACTIVE_TEXTURE, &[unit as _]);
+        }
+
+        unsafe fn bind_texture(&self, _target: GLenum, texture: GLuint) {
+            self.log("BindTexture");
+            self.put(gl::TEXTURE_BINDING_2D, &[texture as _]);
+        }
+
+        unsafe fn color_mask(&self, mask: [bool; 4]) {
+            self.log("ColorMask");
+            self.put(gl::COLOR_WRITEMASK, &mask.map(|m| m as GLint));
+        }
+
+        unsafe fn pixel_store(&self, pname: GLenum, param: GLint) {
+            self.log("PixelStorei");
+            self.check(pname);
+            self.put(pname, &[param]);
+        }
+
+        unsafe fn bind_sampler(&self, unit: GLuint, sampler: GLuint) {
+            assert!(
+                self.features.samplers,
+                "BindSampler called without sampler support"
+            );
+            self.log("BindSampler");
+            assert_eq!(
+                self.get(gl::ACTIVE_TEXTURE)[0],
+                (gl::TEXTURE0 + unit) as GLint
+            );
+            self.put(gl::SAMPLER_BINDING, &[sampler as _]);
+        }
+
+        unsafe fn polygon_mode(&self, mode: GLenum) {
+            assert!(self.features.polygon_mode, "PolygonMode called on GLES");
+            self.log("PolygonMode");
+            self.put(gl::POLYGON_MODE, &[mode as _, mode as _]);
+        }
+
+        fn has_samplers(&self) -> bool {
+            self.features.samplers
+        }
+
+        fn has_polygon_mode(&self) -> bool {
+            self.features.polygon_mode
+        }
+
+        fn has_unpack_subimage(&self) -> bool {
+            self.features.unpack_subimage
+        }
+
+        fn has_unpack_buffer(&self) -> bool {
+            self.features.unpack_buffer
+        }
+
+        fn has_framebuffer_srgb(&self) -> bool {
+            self.features.framebuffer_srgb
+        }
+    }
+
+    /// Host state that differs from everything the painter sets.
+    fn host_state(gl: &RecordingGl) {
+        unsafe {
+            gl.set_enabled(gl::DEPTH_TEST, true);
+            gl.set_enabled(gl::CULL_FACE, true);
+            gl.blend_func_separate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ZERO);
+            gl.blend_equation_separate(gl::FUNC_ADD, gl::MAX);
+            gl.viewport([10, 20, 300, 400]);
+            gl.scissor([1, 2, 3, 4]);
+            gl.use_program(7);
+            gl.bind_vertex_array(3);
+            gl.bind_buffer(gl::ARRAY_BUFFER, 11);
+            gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 12);
+            gl.bind_texture(gl::TEXTURE_2D, 21);
+            if gl.has_samplers() {
+                gl.bind_sampler(0, 5);
+            }
+            gl.active_texture(gl::TEXTURE3);
+            gl.bind_texture(gl::TEXTURE_2D, 33);
+            if gl.has_polygon_mode() {
+                gl.polygon_mode(gl::LINE);
+            }
+            gl.color_mask([true, false, true, false]);
+            gl.pixel_store(gl::UNPACK_ALIGNMENT, 4);
+            if gl.has_unpack_subimage() {
+                gl.pixel_store(gl::UNPACK_ROW_LENGTH, 64);
+            }
+            if gl.has_unpack_buffer() {
+                gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, 13);
+            }
+        }
+    }
+
+    /// The painter's own state setup and unpack reset, then the bindings its draws make.
+    fn painter_state(gl: &RecordingGl) {
+        unsafe {
+            set_paint_state(gl, 1, true);
+            reset_unpack_state(gl);
+            gl.viewport([0, 0, 1920, 1080]);
+            gl.scissor([5, 5, 100, 100]);
+            gl.bind_vertex_array(2);
+            gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 4);
+            gl.bind_buffer(gl::ARRAY_BUFFER, 5);
+            gl.bind_texture(gl::TEXTURE_2D, 9);
+        }
+    }
+
+    #[test]
+    fn test_restore_round_trip() {
+        for features in [GL_33, GL_21, GLES_3, GLES_2] {
+            let gl = RecordingGl::new(features);
+            host_state(&gl);
+            let before = gl.current();
+
+            let snapshot = unsafe { GlStateSnapshot::capture(&gl) };
+            assert_eq!(
+                gl.current(),
+                before,
+                "capture must not change state on {features:?}"
+            );
+
+            painter_state(&gl);
+            assert_ne!(gl.current(), before);
+
+            unsafe { snapshot.restore(&gl) };
+            assert_eq!(gl.current(), before, "{features:?}");
+            assert_eq!(unsafe { GlStateSnapshot::capture(&gl) }, snapshot);
+        }
+    }
+
+    #[test]
+    fn test_restore_without_samplers() {
+        let gl = RecordingGl::new(GL_21);
+        let snapshot = unsafe { GlStateSnapshot::capture(&gl) };
+        assert_eq!(snapshot.sampler, None);
+
+        gl.calls.borrow_mut().clear();
+        unsafe { snapshot.restore(&gl) };
+        assert!(!gl.calls.borrow().contains(&"BindSampler"));
+    }
+}
@@ -0,0 +1,519 @@
+//! Input from GLFW games, for hooks of the callbacks they register with `glfwSetKeyCallback` and friends.
+//! Each method takes the arguments of one callback.
+
+use egui::{
+    Context, Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect, Vec2,
+};
+use std::ffi::{c_int, c_uint};
+
+pub const GLFW_RELEASE: c_int = 0;
+pub const GLFW_PRESS: c_int = 1;
+pub const GLFW_REPEAT: c_int = 2;
+
+pub const GLFW_MOD_SHIFT: c_int = 0x0001;
+pub const GLFW_MOD_CONTROL: c_int = 0x0002;
+pub const GLFW_MOD_ALT: c_int = 0x0004;
+
+/// Collects input of a single GLFW window, like [`crate::sdl::SdlInputCollector`] does for SDL.
+pub struct GlfwInputCollector {
+    events: Vec<Event>,
+    modifiers: Modifiers,
+    /// Button events carry no position, so the last one from the cursor callback is used.
+    cursor_pos: Pos2,
+    /// Keys currently down, released when the window loses focus.
+    held_keys: Vec<Key>,
+    focused: bool,
+    /// In screen coordinates like the cursor, egui's points.
+    window_size: Option<(u32, u32)>,
+    /// In pixels, which differ from screen coordinates on scaled Wayland and macOS outputs.
+    framebuffer_size: Option<(u32, u32)>,
+}
+
+/// What a callback was about, and so which of egui's wishes decides if the game should see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlfwInputResult {
+    Unknown,
+    Pointer,
+    Key,
+    Window,
+}
+
+impl GlfwInputResult {
+    /// Whether egui wants the input the callback carries, in which case the hook should not call the game's callback.
+    pub fn swallow(self, ctx: &Context) -> bool {
+        match self {
+            Self::Pointer => ctx.wants_pointer_input(),
+            Self::Key => ctx.wants_keyboard_input(),
+            Self::Unknown | Self::Window => false,
+        }
+    }
+}
+
+impl GlfwInputCollector {
+    /// `screen_size` is the window's and the framebuffer's size until the size callbacks say otherwise.
+    pub fn new(screen_size: Option<(u32, u32)>) -> Self {
+        Self {
+            events: vec![],
+            modifiers: Modifiers::NONE,
+            cursor_pos: Pos2::ZERO,
+            held_keys: vec![],
+            focused: true,
+            window_size: screen_size,
+            framebuffer_size: screen_size,
+        }
+    }
+
+    /// `GLFWkeyfun`. GLFW names keys after the US layout, so they are physical keys and the logical key is the same.
+    pub fn key(
+        &mut self,
+        key: c_int,
+        _scancode: c_int,
+        action: c_int,
+        mods: c_int,
+    ) -> GlfwInputResult {
+        let modifiers = get_modifiers(mods);
+        self.modifiers = modifiers;
+
+        let Some(key) = get_key(key) else {
+            return GlfwInputResult::Key;
+        };
+        let pressed = action != GLFW_RELEASE;
+        if pressed && !self.held_keys.contains(&key) {
+            self.held_keys.push(key);
+        } else if !pressed {
+            self.held_keys.retain(|&held| held != key);
+        }
+
+        if action == GLFW_PRESS && modifiers.ctrl {
+            match key {
+                Key::C => self.events.push(Event::Copy),
+                Key::X => self.events.push(Event::Cut),
+                _ => {}
+            }
+        }
+
+        self.events.push(Event::Key {
+            key,
+            physical_key: Some(key),
+            pressed,
+            repeat: action == GLFW_REPEAT,
+            modifiers,
+        });
+        GlfwInputResult::Key
+    }
+
+    /// `GLFWcharfun`.
+    pub fn char(&mut self, codepoint: c_uint) -> GlfwInputResult {
+        if let Some(c) = char::from_u32(codepoint).filter(|c| !c.is_control()) {
+            self.events.push(Event::Text(c.into()));
+        }
+        GlfwInputResult::Key
+    }
+
+    /// `GLFWcursorposfun`.
+    pub fn cursor_pos(&mut self, x: f64, y: f64) -> GlfwInputResult {
+        self.cursor_pos = Pos2::new(x as f32, y as f32);
+        self.events.push(Event::PointerMoved(self.cursor_pos));
+        GlfwInputResult::Pointer
+    }
+
+    /// `GLFWcursorenterfun`.
+    pub fn cursor_enter(&mut self, entered: c_int) -> GlfwInputResult {
+        if entered == 0 {
+            self.events.push(Event::PointerGone);
+        }
+        GlfwInputResult::Pointer
+    }
+
+    /// `GLFWmousebuttonfun`.
+    pub fn mouse_button(&mut self, button: c_int, action: c_int, mods: c_int) -> GlfwInputResult {
+        let modifiers = get_modifiers(mods);
+        self.modifiers = modifiers;
+
+        if let Some(button) = get_button(button) {
+            self.events.push(Event::PointerButton {
+                pos: self.cursor_pos,
+                button,
+                pressed: action == GLFW_PRESS,
+                modifiers,
+            });
+        }
+        GlfwInputResult::Pointer
+    }
+
+    /// `GLFWscrollfun`. GLFW already counts scrolling left and up as positive, like egui.
+    pub fn scroll(&mut self, x_offset: f64, y_offset: f64) -> GlfwInputResult {
+        self.events.push(Event::MouseWheel {
+            unit: MouseWheelUnit::Line,
+            delta: Vec2::new(x_offset as f32, y_offset as f32),
+            modifiers: self.modifiers,
+        });
+        GlfwInputResult::Pointer
+    }
+
+    /// `GLFWwindowfocusfun`.
+    pub fn window_focus(&mut self, focused: c_int) -> GlfwInputResult {
+        self.focused = focused != 0;
+        if !self.focused {
+            // Their releases go to whichever window has the focus now.
+            for key in self.held_keys.drain(..) {
+                self.events.push(Event::Key {
+                    key,
+                    physical_key: Some(key),
+                    pressed: false,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                });
+            }
+            self.modifiers = Modifiers::NONE;
+        }
+        self.events.push(Event::WindowFocused(self.focused));
+        GlfwInputResult::Window
+    }
+
+    /// `GLFWwindowsizefun`, the size in screen coordinates the ui is laid out in.
+    pub fn window_size(&mut self, width: c_int, height: c_int) -> GlfwInputResult {
+        self.window_size = Some((width.max(0) as u32, height.max(0) as u32));
+        GlfwInputResult::Window
+    }
+
+    /// `GLFWframebuffersizefun`, the size in pixels the renderer draws at.
+    pub fn framebuffer_size(&mut self, width: c_int, height: c_int) -> GlfwInputResult {
+        self.framebuffer_size = Some((width.max(0) as u32, height.max(0) as u32));
+        GlfwInputResult::Window
+    }
+
+    /// Size of the framebuffer from the last size callback, or the one it was created with.
+    pub fn screen_size(&self) -> Option<(u32, u32)> {
+        self.framebuffer_size
+    }
+
+    /// Pixels per screen coordinate, `None` until both sizes are known.
+    pub fn pixels_per_point(&self) -> Option<f32> {
+        match (self.window_size, self.framebuffer_size) {
+            (Some((window_width, _)), Some((framebuffer_width, _))) if window_width > 0 => {
+                Some(framebuffer_width as f32 / window_width as f32)
+            }
+            _ => None,
+        }
+    }
+
+    /// The screen rect is in screen coordinates, and the framebuffer's scale is passed on as the native pixels per point.
+    pub fn collect_input(&mut self, ctx: &Context, time: f64) -> RawInput {
+        let mut viewports = ctx.input(|i| i.raw.viewports.clone());
+        if let Some(pixels_per_point) = self.pixels_per_point() {
+            viewports
+                .entry(ctx.viewport_id())
+                .or_default()
+                .native_pixels_per_point = Some(pixels_per_point);
+        }
+
+        RawInput {
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            screen_rect: self.window_size.map(|(width, height)| {
+                Rect::from_min_size(Pos2::ZERO, Vec2::new(width as f32, height as f32))
+            }),
+            time: Some(time),
+            focused: self.focused,
+            viewport_id: ctx.viewport_id(),
+            viewports,
+            ..Default::default()
+        }
+    }
+}
+
+fn get_modifiers(mods: c_int) -> Modifiers {
+    let ctrl = mods & GLFW_MOD_CONTROL != 0;
+
+    Modifiers {
+        alt: mods & GLFW_MOD_ALT != 0,
+        ctrl,
+        shift: mods & GLFW_MOD_SHIFT != 0,
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+fn get_button(button: c_int) -> Option<PointerButton> {
+    match button {
+        0 => Some(PointerButton::Primary),
+        1 => Some(PointerButton::Secondary),
+        2 => Some(PointerButton::Middle),
+        3 => Some(PointerButton::Extra1),
+        4 => Some(PointerButton::Extra2),
+        _ => None,
+    }
+}
+
+/// Keys by `GLFW_KEY_*` token. Printable keys are their ASCII character.
+const KEYS: &[(c_int, Key)] = &[
+    (32, Key::Space),
+    (39, Key::Quote),
+    (44, Key::Comma),
+    (45, Key::Minus),
+    (46, Key::Period),
+    (47, Key::Slash),
+    (48, Key::Num0),
+    (49, Key::Num1),
+    (50, Key::Num2),
+    (51, Key::Num3),
+    (52, Key::Num4),
+    (53, Key::Num5),
+    (54, Key::Num6),
+    (55, Key::Num7),
+    (56, Key::Num8),
+    (57, Key::Num9),
+    (59, Key::Semicolon),
+    (61, Key::Equals),
+    (65, Key::A),
+    (66, Key::B),
+    (67, Key::C),
+    (68, Key::D),
+    (69, Key::E),
+    (70, Key::F),
+    (71, Key::G),
+    (72, Key::H),
+    (73, Key::I),
+    (74, Key::J),
+    (75, Key::K),
+    (76, Key::L),
+    (77, Key::M),
+    (78, Key::N),
+    (79, Key::O),
+    (80, Key::P),
+    (81, Key::Q),
+    (82, Key::R),
+    (83, Key::S),
+    (84, Key::T),
+    (85, Key::U),
+    (86, Key::V),
+    (87, Key::W),
+    (88, Key::X),
+    (89, Key::Y),
+    (90, Key::Z),
+    (91, Key::OpenBracket),
+    (92, Key::Backslash),
+    (93, Key::CloseBracket),
+    (96, Key::Backtick),
+    (256, Key::Escape),
+    (257, Key::Enter),
+    (258, Key::Tab),
+    (259, Key::Backspace),
+    (260, Key::Insert),
+    (261, Key::Delete),
+    (262, Key::ArrowRight),
+    (263, Key::ArrowLeft),
+    (264, Key::ArrowDown),
+    (265, Key::ArrowUp),
+    (266, Key::PageUp),
+    (267, Key::PageDown),
+    (268, Key::Home),
+    (269, Key::End),
+    (290, Key::F1),
+    (291, Key::F2),
+    (292, Key::F3),
+    (293, Key::F4),
+    (294, Key::F5),
+    (295, Key::F6),
+    (296, Key::F7),
+    (297, Key::F8),
+    (298, Key::F9),
+    (299, Key::F10),
+    (300, Key::F11),
+    (301, Key::F12),
+    (302, Key::F13),
+    (303, Key::F14),
+    (304, Key::F15),
+    (305, Key::F16),
+    (306, Key::F17),
+    (307, Key::F18),
+    (308, Key::F19),
+    (309, Key::F20),
+    (310, Key::F21),
+    (311, Key::F22),
+    (312, Key::F23),
+    (313, Key::F24),
+    (314, Key::F25),
+    (320, Key::Num0),
+    (321, Key::Num1),
+    (322, Key::Num2),
+    (323, Key::Num3),
+    (324, Key::Num4),
+    (325, Key::Num5),
+    (326, Key::Num6),
+    (327, Key::Num7),
+    (328, Key::Num8),
+    (329, Key::Num9),
+    (330, Key::Period),
+    (331, Key::Slash),
+    (333, Key::Minus),
+    (334, Key::Plus),
+    (335, Key::Enter),
+    (336, Key::Equals),
+];
+
+fn get_key(key: c_int) -> Option<Key> {
+    KEYS.iter()
+        .find(|&&(token, _)| token == key)
+        .map(|&(_, key)| key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLFW_KEY_A: c_int = 65;
+    const GLFW_KEY_C: c_int = 67;
+
+    #[test]
+    fn test_keys_and_text() {
+        let mut collector = GlfwInputCollector::new(None);
+
+        assert_eq!(
+            collector.key(GLFW_KEY_A, 38, GLFW_PRESS, 0),
+            GlfwInputResult::Key
+        );
+        collector.char('a' as c_uint);
+        collector.key(GLFW_KEY_A, 38, GLFW_REPEAT, 0);
+        collector.key(GLFW_KEY_A, 38, GLFW_RELEASE, 0);
+        collector.key(GLFW_KEY_C, 54, GLFW_PRESS, GLFW_MOD_CONTROL);
+        // Control characters are never text.
+        collector.char(0x7f);
+
+        let key = |key, pressed, repeat, modifiers| Event::Key {
+            key,
+            physical_key: Some(key),
+            pressed,
+            repeat,
+            modifiers,
+        };
+        let ctrl = Modifiers {
+            ctrl: true,
+            command: true,
+            ..Modifiers::NONE
+        };
+        assert_eq!(
+            collector.events,
+            [
+                key(Key::A, true, false, Modifiers::NONE),
+                Event::Text("a".into()),
+                key(Key::A, true, true, Modifiers::NONE),
+                key(Key::A, false, false, Modifiers::NONE),
+                Event::Copy,
+                key(Key::C, true, false, ctrl),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mouse() {
+        let mut collector = GlfwInputCollector::new(None);
+
+        collector.cursor_pos(1.5, 2.5);
+        collector.mouse_button(1, GLFW_PRESS, GLFW_MOD_SHIFT);
+        collector.mouse_button(3, GLFW_RELEASE, 0);
+        assert_eq!(collector.scroll(0.0, -2.0), GlfwInputResult::Pointer);
+        collector.cursor_enter(0);
+
+        let pos = Pos2::new(1.5, 2.5);
+        assert_eq!(
+            collector.events,
+            [
+                Event::PointerMoved(pos),
+                Event::PointerButton {
+                    pos,
+                    button: PointerButton::Secondary,
+                    pressed: true,
+                    modifiers: Modifiers::SHIFT,
+                },
+                Event::PointerButton {
+                    pos,
+                    button: PointerButton::Extra1,
+                    pressed: false,
+                    modifiers: Modifiers::NONE,
+                },
+                Event::MouseWheel {
+                    unit: MouseWheelUnit::Line,
+                    delta: Vec2::new(0.0, -2.0),
+                    modifiers: Modifiers::NONE,
+                },
+                Event::PointerGone,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_callbacks() {
+        let mut collector = GlfwInputCollector::new(Some((640, 480)));
+
+        collector.key(GLFW_KEY_A, 38, GLFW_PRESS, 0);
+        collector.events.clear();
+
+        assert_eq!(collector.window_size(1280, 720), GlfwInputResult::Window);
+        assert_eq!(
+            collector.framebuffer_size(1280, 720),
+            GlfwInputResult::Window
+        );
+        collector.window_focus(0);
+        assert_eq!(
+            collector.events,
+            [
+                Event::Key {
+                    key: Key::A,
+                    physical_key: Some(Key::A),
+                    pressed: false,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                },
+                Event::WindowFocused(false),
+            ]
+        );
+
+        let input = collector.collect_input(&Context::default(), 1.0);
+        assert_eq!(
+            input.screen_rect,
+            Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(1280.0, 720.0)))
+        );
+        assert!(!input.focused);
+    }
+
+    #[test]
+    fn test_scaled_framebuffer() {
+        let mut collector = GlfwInputCollector::new(Some((640, 480)));
+        collector.framebuffer_size(1280, 960);
+        collector.cursor_pos(320.0, 240.0);
+        assert_eq!(collector.screen_size(), Some((1280, 960)));
+        assert_eq!(collector.pixels_per_point(), Some(2.0));
+
+        // The ui is laid out in the cursor's screen coordinates and drawn at twice the size.
+        let ctx = Context::default();
+        let input = collector.collect_input(&ctx, 1.0);
+        let screen_rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(640.0, 480.0));
+        assert_eq!(input.screen_rect, Some(screen_rect));
+
+        let output = ctx.run(input, |_| {});
+        assert_eq!(output.pixels_per_point, 2.0);
+        assert_eq!(ctx.screen_rect(), screen_rect);
+        assert_eq!(
+            ctx.input(|i| i.pointer.latest_pos()),
+            Some(screen_rect.center())
+        );
+    }
+
+    #[test]
+    fn test_key_map() {
+        assert_eq!(get_key(32), Some(Key::Space));
+        assert_eq!(get_key(90), Some(Key::Z));
+        assert_eq!(get_key(257), Some(Key::Enter));
+        assert_eq!(get_key(314), Some(Key::F25));
+        assert_eq!(get_key(329), Some(Key::Num9));
+        // Modifier keys and unknown keys.
+        assert_eq!(get_key(340), None);
+        assert_eq!(get_key(-1), None);
+
+        for (i, (token, _)) in KEYS.iter().enumerate() {
+            assert!(KEYS[..i].iter().all(|(other, _)| other != token));
+        }
+    }
+}
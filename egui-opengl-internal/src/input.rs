@@ -1,29 +1,64 @@
+use crate::win32::{
+    focus::FocusState,
+    ime::ImeState,
+    keys,
+    modifiers::ModifierState,
+    pointer::{self, PointerInfo, PointerKind, TouchState},
+    raw_input::{self, RawInputData, RawKeys},
+    text::{self, TextInput},
+    wheel,
+};
 use clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
-use egui::{Context, Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect, Vec2};
+use egui::{pos2, vec2, Context, Event, Key, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect};
 use windows::Wdk::System::SystemInformation::NtQuerySystemTime;
 use windows::Win32::{
-    Foundation::{HWND, RECT},
-    System::SystemServices::{MK_CONTROL, MK_SHIFT},
+    Foundation::{HWND, POINT, RECT},
+    Graphics::Gdi::{ClientToScreen, ScreenToClient},
     UI::{
+        Input::Ime::{
+            ImmGetCompositionStringW, ImmGetContext, ImmReleaseContext, IME_COMPOSITION_STRING,
+        },
         Input::KeyboardAndMouse::{
-            GetAsyncKeyState, VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
-            VK_ESCAPE, VK_HOME, VK_INSERT, VK_LEFT, VK_LSHIFT, VK_NEXT, VK_PRIOR, VK_RETURN,
-            VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
+            GetKeyState, GetKeyboardLayout, MapVirtualKeyW, ToUnicodeEx, MAPVK_VK_TO_CHAR,
+            VK_CAPITAL,
         },
+        Input::KeyboardAndMouse::{TrackMouseEvent, TME_LEAVE, TRACKMOUSEEVENT},
+        Input::Pointer::{GetPointerPenInfo, GetPointerTouchInfo, GetPointerType, POINTER_INFO},
+        Input::{GetRawInputData, HRAWINPUT, RAWINPUTHEADER, RID_INPUT},
         WindowsAndMessaging::{
-            GetClientRect, KF_REPEAT, WHEEL_DELTA, WM_CHAR, WM_UNICHAR, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK,
-            WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP,
-            WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN,
-            WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN,
-            WM_XBUTTONUP, XBUTTON1, XBUTTON2,
+            GetClientRect, GetCursorPos, GetMessageExtraInfo, GetSystemMetrics, FAPPCOMMAND_MASK,
+            KF_REPEAT, PEN_MASK_PRESSURE, PT_PEN, PT_TOUCH, SM_CXSCREEN, SM_CXVIRTUALSCREEN,
+            SM_CYSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+            TOUCH_MASK_PRESSURE, WA_INACTIVE, WM_ACTIVATE, WM_APPCOMMAND, WM_CHAR, WM_DEADCHAR,
+            WM_IME_COMPOSITION, WM_IME_ENDCOMPOSITION, WM_IME_STARTCOMPOSITION, WM_INPUT,
+            WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP,
+            WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEFIRST, WM_MOUSEHWHEEL,
+            WM_MOUSELAST, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_POINTERDOWN, WM_POINTERUP,
+            WM_POINTERUPDATE, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETFOCUS,
+            WM_SYSDEADCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_UNICHAR, WM_XBUTTONDBLCLK,
+            WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
         },
     },
 };
 
+/// Lives in `Win32::UI::Controls`, which is not worth the feature for one constant.
+const WM_MOUSELEAVE: u32 = 0x02A3;
+
 pub struct InputCollector {
     hwnd: HWND,
     events: Vec<Event>,
-    modifiers: Option<Modifiers>,
+    modifiers: ModifierState,
+    wheel_unit: MouseWheelUnit,
+    ime: ImeState,
+    text: TextInput,
+    raw_input: bool,
+    /// Pointer position in raw input mode.
+    cursor: Pos2,
+    raw_keys: RawKeys,
+    focus: FocusState,
+    /// `TrackMouseEvent` is armed to send a `WM_MOUSELEAVE`.
+    tracking_leave: bool,
+    touch: TouchState,
 }
 
 /// High-level overview of recognized `WndProc` messages.
@@ -38,6 +73,7 @@ pub enum InputResult {
     Scroll,
     Zoom,
     Key,
+    Touch,
 }
 
 impl InputCollector {
@@ -45,21 +81,92 @@ impl InputCollector {
         Self {
             hwnd,
             events: vec![],
-            modifiers: None,
+            modifiers: ModifierState::sync(is_key_down),
+            wheel_unit: MouseWheelUnit::Line,
+            ime: ImeState::default(),
+            text: TextInput::default(),
+            raw_input: false,
+            cursor: Pos2::ZERO,
+            raw_keys: RawKeys::default(),
+            focus: FocusState::default(),
+            tracking_leave: false,
+            touch: TouchState::default(),
         }
     }
 
+    /// Reads the pointer and keys from `WM_INPUT` instead of the legacy messages, for games that register raw input
+    /// with `RIDEV_NOLEGACY` or lock the cursor. Egui then follows a cursor of its own, starting where the system cursor is.
+    /// Text is typed with `ToUnicodeEx`, which leaves dead keys to the game, so they do not compose in this mode.
+    pub fn set_raw_input(&mut self, enabled: bool) {
+        if enabled && !self.raw_input {
+            self.cursor = self.get_cursor_pos();
+            self.raw_keys = RawKeys::default();
+        }
+        self.raw_input = enabled;
+    }
+
+    pub fn raw_input(&self) -> bool {
+        self.raw_input
+    }
+
+    /// Unit of the wheel events, [`MouseWheelUnit::Line`] by default.
+    /// [`MouseWheelUnit::Point`] scrolls the same distance but lets the platform speed be tuned in egui terms.
+    pub fn set_wheel_unit(&mut self, unit: MouseWheelUnit) {
+        self.wheel_unit = unit;
+    }
+
+    pub fn wheel_unit(&self) -> MouseWheelUnit {
+        self.wheel_unit
+    }
+
     pub fn process(&mut self, umsg: u32, wparam: usize, lparam: isize) -> InputResult {
+        let start = self.events.len();
+        let result = self.process_message(umsg, wparam, lparam);
+        for event in &self.events[start..] {
+            self.focus.track(event);
+        }
+        result
+    }
+
+    fn process_message(&mut self, umsg: u32, wparam: usize, lparam: isize) -> InputResult {
+        if self.raw_input
+            && matches!(umsg, WM_MOUSEFIRST..=WM_MOUSELAST | WM_KEYDOWN..=WM_SYSDEADCHAR)
+        {
+            return InputResult::Unknown;
+        }
+
+        // Touch and pen input reached egui as pointer messages already.
+        if self.touch.active()
+            && matches!(umsg, WM_MOUSEFIRST..=WM_MOUSELAST)
+            && pointer::is_promoted(unsafe { GetMessageExtraInfo() }.0)
+        {
+            return InputResult::Unknown;
+        }
+
         match umsg {
             WM_MOUSEMOVE => {
-                self.alter_modifiers(get_mouse_modifiers(wparam));
-
+                if !self.tracking_leave {
+                    self.tracking_leave = track_mouse_leave(self.hwnd);
+                }
                 self.events.push(Event::PointerMoved(get_pos(lparam)));
                 InputResult::MouseMove
             }
+            WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP => {
+                let Some(info) = get_pointer_info(self.hwnd, wparam) else {
+                    return InputResult::Unknown;
+                };
+                let modifiers = self.modifiers.modifiers();
+                self.touch
+                    .process(umsg, wparam, &info, modifiers, &mut self.events);
+                InputResult::Touch
+            }
+            WM_MOUSELEAVE => {
+                self.tracking_leave = false;
+                self.events.push(Event::PointerGone);
+                InputResult::MouseMove
+            }
             WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -70,8 +177,7 @@ impl InputCollector {
                 InputResult::MouseLeft
             }
             WM_LBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -82,8 +188,7 @@ impl InputCollector {
                 InputResult::MouseLeft
             }
             WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -94,8 +199,7 @@ impl InputCollector {
                 InputResult::MouseRight
             }
             WM_RBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -106,8 +210,7 @@ impl InputCollector {
                 InputResult::MouseRight
             }
             WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -118,8 +221,7 @@ impl InputCollector {
                 InputResult::MouseMiddle
             }
             WM_MBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -130,8 +232,7 @@ impl InputCollector {
                 InputResult::MouseMiddle
             }
             WM_XBUTTONDOWN | WM_XBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -148,8 +249,7 @@ impl InputCollector {
                 InputResult::MouseMiddle
             }
             WM_XBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.alter_modifiers(modifiers);
+                let modifiers = self.modifiers.modifiers();
 
                 self.events.push(Event::PointerButton {
                     pos: get_pos(lparam),
@@ -165,134 +265,193 @@ impl InputCollector {
                 });
                 InputResult::MouseMiddle
             }
-            WM_UNICHAR => {
-                // Handle Unicode characters from WM_UNICHAR
-                let unicode_char = wparam as u32; // wparam is the Unicode character
-
-                // Debugging output
-                println!("WM_UNICHAR received: unicode_char = {}", unicode_char);
-
-                if unicode_char != 0xFFFF { // 0xFFFF indicates no character
-                    if let Some(ch) = char::from_u32(unicode_char) {
-                        // Print the character representation
-                        println!("Character from WM_UNICHAR: '{}'", ch);
-                        if !ch.is_control() {
-                            self.events.push(Event::Text(ch.into())); // Add the character to events
-                        }
-                    } else {
-                        println!("Invalid character for unicode_char: {}", unicode_char);
-                    }
+            WM_CHAR | WM_UNICHAR | WM_DEADCHAR | WM_SYSDEADCHAR => {
+                if let Some(ch) = self.text.process(umsg, wparam) {
+                    self.events.push(Event::Text(ch.into()));
                 }
                 InputResult::Character
             }
-
-            WM_CHAR => {
-                // Handle characters from WM_CHAR
-                let unicode_char = wparam as u32; // wparam is the character code
-                if unicode_char != 0xFFFF { // 0xFFFF indicates no character
-                    if let Some(ch) = char::from_u32(unicode_char) {
-                        if !ch.is_control() {
-                            self.events.push(Event::Text(ch.into())); // Add the character to events
-                        }
-                    } else {
-                        println!("Invalid character for unicode_char: {}", unicode_char);
-                    }
-                }
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                let event = wheel::wheel_event(
+                    (wparam >> 16) as i16,
+                    umsg == WM_MOUSEHWHEEL,
+                    self.wheel_unit,
+                    self.modifiers.modifiers(),
+                );
+                let result = match event {
+                    Event::Zoom(_) => InputResult::Zoom,
+                    _ => InputResult::Scroll,
+                };
+                self.events.push(event);
+                result
+            }
+            WM_IME_STARTCOMPOSITION | WM_IME_COMPOSITION | WM_IME_ENDCOMPOSITION => {
+                // Swallowing these keeps `DefWindowProc` from typing the result again with `WM_CHAR`.
+                let hwnd = self.hwnd;
+                self.ime.process(
+                    umsg,
+                    lparam,
+                    |flag| get_composition_string(hwnd, flag),
+                    &mut self.events,
+                );
                 InputResult::Character
             }
-            WM_MOUSEWHEEL => {
-                self.alter_modifiers(get_mouse_modifiers(wparam));
-
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
-
-                if wparam & MK_CONTROL.0 as usize != 0 {
-                    self.events
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
-                    InputResult::Zoom
-                } else {
-                    self.events.push(Event::MouseWheel {
-                        unit: MouseWheelUnit::Point, // or another unit according to your needs
-                        delta: Vec2::new(0., delta), // Use the appropriate delta for vertical scroll
-                        modifiers: Modifiers::NONE, // You can set modifiers if needed
-                    });
-                    InputResult::Scroll
-                }
+            WM_KEYDOWN | WM_SYSKEYDOWN => {
+                self.key_down(wparam, lparam);
+                InputResult::Key
             }
-            WM_MOUSEHWHEEL => {
-                self.alter_modifiers(get_mouse_modifiers(wparam));
-
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
-
-                if wparam & MK_CONTROL.0 as usize != 0 {
-                    self.events
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
-                    InputResult::Zoom
-                } else {
-                    self.events.push(Event::MouseWheel {
-                        unit: MouseWheelUnit::Point, // or another unit according to your needs
-                        delta: Vec2::new(0., delta), // Use the appropriate delta for vertical scroll
-                        modifiers: Modifiers::NONE, // You can set modifiers if needed
-                    });
-                    InputResult::Scroll
-                }
+            WM_KEYUP | WM_SYSKEYUP => {
+                self.key_up(wparam, lparam);
+                InputResult::Key
             }
-            msg @ (WM_KEYDOWN | WM_SYSKEYDOWN) => {
-                let modifiers = get_key_modifiers(msg);
-                self.modifiers = Some(modifiers);
+            WM_INPUT if self.raw_input => {
+                let Some(data) = get_raw_input(lparam).as_deref().and_then(raw_input::parse) else {
+                    return InputResult::Unknown;
+                };
+
+                match data {
+                    RawInputData::Mouse(mouse) => {
+                        let (hwnd, client) = (self.hwnd, self.get_screen_rect());
+                        mouse.events(
+                            &mut self.cursor,
+                            client,
+                            |x, y, virtual_desktop| desktop_to_client(hwnd, x, y, virtual_desktop),
+                            self.wheel_unit,
+                            self.modifiers.modifiers(),
+                            &mut self.events,
+                        );
+                        InputResult::MouseMove
+                    }
+                    RawInputData::Keyboard(keyboard) => {
+                        let was_down = self.raw_keys.key(keyboard.vkey, keyboard.pressed());
+                        let Some((vk, lparam)) = keyboard.legacy_message(was_down) else {
+                            return InputResult::Unknown;
+                        };
+
+                        if !keyboard.pressed() {
+                            self.key_up(vk, lparam);
+                            return InputResult::Key;
+                        }
 
-                if let Some(key) = get_key(wparam) {
-                    if key == Key::V && modifiers.ctrl {
+                        self.key_down(vk, lparam);
+                        // Alt types nothing, like `WM_SYSCHAR`.
+                        if !self.modifiers.alt() || self.modifiers.ctrl() {
+                            for unit in get_key_text(vk, lparam, &self.modifiers) {
+                                if let Some(ch) = self.text.process(text::WM_CHAR, unit as usize) {
+                                    self.events.push(Event::Text(ch.into()));
+                                }
+                            }
+                        }
+                        InputResult::Key
+                    }
+                }
+            }
+            WM_APPCOMMAND => {
+                let command = ((lparam >> 16) & !(FAPPCOMMAND_MASK as isize)) as u16;
+                let Some(key) = keys::key_from_app_command(command) else {
+                    return InputResult::Unknown;
+                };
+                let modifiers = self.modifiers.modifiers();
+
+                match key {
+                    Key::Copy => self.events.push(Event::Copy),
+                    Key::Cut => self.events.push(Event::Cut),
+                    _ => {
                         if let Some(clipboard) = get_clipboard_text() {
                             self.events.push(Event::Text(clipboard));
                         }
                     }
-
-                    if key == Key::C && modifiers.ctrl {
-                        self.events.push(Event::Copy);
-                    }
-
-                    if key == Key::X && modifiers.ctrl {
-                        self.events.push(Event::Cut);
-                    }
-
+                }
+                // Commands are not held down, they come as a single message.
+                for pressed in [true, false] {
                     self.events.push(Event::Key {
-                        pressed: true,
-                        modifiers,
                         key,
-                        repeat: lparam & (KF_REPEAT as isize) > 0,
                         physical_key: None,
+                        pressed,
+                        repeat: false,
+                        modifiers,
                     });
                 }
                 InputResult::Key
             }
-            msg @ (WM_KEYUP | WM_SYSKEYUP) => {
-                let modifiers = get_key_modifiers(msg);
-                self.modifiers = Some(modifiers);
+            WM_SETFOCUS => {
+                self.set_focused(true);
+                InputResult::Unknown
+            }
+            WM_KILLFOCUS => {
+                self.set_focused(false);
+                InputResult::Unknown
+            }
+            WM_ACTIVATE => {
+                self.set_focused(wparam & 0xFFFF != WA_INACTIVE as usize);
+                InputResult::Unknown
+            }
+            _ => InputResult::Unknown,
+        }
+    }
 
-                if let Some(key) = get_key(wparam) {
-                    self.events.push(Event::Key {
-                        pressed: false,
-                        modifiers,
-                        key,
-                        repeat: lparam & (KF_REPEAT as isize) > 0,
-                        physical_key: None,
-                    });
+    fn set_focused(&mut self, focused: bool) {
+        if !self.focus.set_focused(focused, &mut self.events) {
+            return;
+        }
+
+        if focused {
+            // Modifiers may have changed while other windows got the key messages.
+            self.modifiers = ModifierState::sync(is_key_down);
+        } else {
+            self.modifiers = ModifierState::default();
+            self.raw_keys = RawKeys::default();
+        }
+    }
+
+    fn key_down(&mut self, wparam: usize, lparam: isize) {
+        self.modifiers.key(wparam as u16, lparam, true);
+        let modifiers = self.modifiers.modifiers();
+
+        if let Some(key) = get_key(wparam) {
+            if key == Key::V && modifiers.ctrl {
+                if let Some(clipboard) = get_clipboard_text() {
+                    self.events.push(Event::Text(clipboard));
                 }
-                InputResult::Key
             }
-            _ => InputResult::Unknown,
+
+            if key == Key::C && modifiers.ctrl {
+                self.events.push(Event::Copy);
+            }
+
+            if key == Key::X && modifiers.ctrl {
+                self.events.push(Event::Cut);
+            }
+
+            self.events.push(Event::Key {
+                pressed: true,
+                modifiers,
+                key,
+                // The previous key state is a flag in the high word.
+                repeat: (lparam >> 16) as u32 & KF_REPEAT != 0,
+                physical_key: keys::physical_key(lparam),
+            });
         }
     }
 
-    fn alter_modifiers(&mut self, new: Modifiers) {
-        if let Some(old) = self.modifiers.as_mut() {
-            *old = new;
+    fn key_up(&mut self, wparam: usize, lparam: isize) {
+        self.modifiers.key(wparam as u16, lparam, false);
+        let modifiers = self.modifiers.modifiers();
+
+        if let Some(key) = get_key(wparam) {
+            self.events.push(Event::Key {
+                pressed: false,
+                modifiers,
+                key,
+                repeat: false,
+                physical_key: keys::physical_key(lparam),
+            });
         }
     }
 
     pub fn collect_input(&mut self, ctx: &Context) -> RawInput {
         RawInput {
-            modifiers: self.modifiers.unwrap_or_default(),
+            modifiers: self.modifiers.modifiers(),
             events: std::mem::take(&mut self.events),
             screen_rect: Some(self.get_screen_rect()),
             time: Some(Self::get_system_time()),
@@ -300,7 +459,7 @@ impl InputCollector {
             predicted_dt: 1. / 60.,
             hovered_files: vec![],
             dropped_files: vec![],
-            focused: true,
+            focused: self.focus.focused(),
             viewport_id: ctx.viewport_id(),
             viewports: ctx.input(|i| i.raw.viewports.clone()),
         }
@@ -343,6 +502,17 @@ impl InputCollector {
             max: self.get_screen_size(),
         }
     }
+
+    fn get_cursor_pos(&self) -> Pos2 {
+        let mut point = POINT::default();
+        unsafe {
+            let _ = GetCursorPos(&mut point);
+            let _ = ScreenToClient(self.hwnd, &mut point);
+        }
+
+        self.get_screen_rect()
+            .clamp(pos2(point.x as f32, point.y as f32))
+    }
 }
 
 fn get_pos(lparam: isize) -> Pos2 {
@@ -352,53 +522,71 @@ fn get_pos(lparam: isize) -> Pos2 {
     Pos2::new(x, y)
 }
 
-fn get_mouse_modifiers(wparam: usize) -> Modifiers {
-    Modifiers {
-        alt: false,
-        ctrl: (wparam & MK_CONTROL.0 as usize) != 0,
-        shift: (wparam & MK_SHIFT.0 as usize) != 0,
-        mac_cmd: false,
-        command: (wparam & MK_CONTROL.0 as usize) != 0,
+/// Reads what a `WM_POINTER*` needs besides its parameters. Returns `None` for mouse and touchpad pointers,
+/// which keep coming as mouse messages.
+fn get_pointer_info(hwnd: HWND, wparam: usize) -> Option<PointerInfo> {
+    let id = (wparam & 0xFFFF) as u32;
+    unsafe {
+        let mut kind = Default::default();
+        GetPointerType(id, &mut kind).ok()?;
+
+        let (info, kind, pressure): (POINTER_INFO, _, _) = match kind {
+            PT_TOUCH => {
+                let mut touch = Default::default();
+                GetPointerTouchInfo(id, &mut touch).ok()?;
+                let pressure = touch.touchMask & TOUCH_MASK_PRESSURE != 0;
+                (
+                    touch.pointerInfo,
+                    PointerKind::Touch,
+                    pressure.then_some(touch.pressure),
+                )
+            }
+            PT_PEN => {
+                let mut pen = Default::default();
+                GetPointerPenInfo(id, &mut pen).ok()?;
+                let pressure = pen.penMask & PEN_MASK_PRESSURE != 0;
+                (
+                    pen.pointerInfo,
+                    PointerKind::Pen,
+                    pressure.then_some(pen.pressure),
+                )
+            }
+            _ => return None,
+        };
+
+        let mut point = info.ptPixelLocation;
+        let _ = ScreenToClient(hwnd, &mut point);
+        Some(PointerInfo {
+            kind,
+            device: info.sourceDevice.0 as u64,
+            pos: pos2(point.x as f32, point.y as f32),
+            pressure,
+        })
     }
 }
 
-fn get_key_modifiers(msg: u32) -> Modifiers {
-    let ctrl = unsafe { GetAsyncKeyState(VK_CONTROL.0 as _) != 0 };
-    let shift = unsafe { GetAsyncKeyState(VK_LSHIFT.0 as _) != 0 };
+/// Asks for a `WM_MOUSELEAVE` once the pointer leaves the window. Returns whether it will come.
+fn track_mouse_leave(hwnd: HWND) -> bool {
+    let mut event = TRACKMOUSEEVENT {
+        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+        dwFlags: TME_LEAVE,
+        hwndTrack: hwnd,
+        dwHoverTime: 0,
+    };
+    unsafe { TrackMouseEvent(&mut event).is_ok() }
+}
 
-    Modifiers {
-        alt: msg == WM_SYSKEYDOWN,
-        mac_cmd: false,
-        command: ctrl,
-        shift,
-        ctrl,
-    }
+/// Whether a key is down according to the messages this thread has read so far.
+fn is_key_down(vk: u16) -> bool {
+    unsafe { GetKeyState(vk as i32) < 0 }
 }
 
 fn get_key(wparam: usize) -> Option<Key> {
-    match wparam {
-        0x30..=0x39 => unsafe { Some(std::mem::transmute::<u8, Key>(wparam as u8 - 0x10)) }, // 0-9
-        0x41..=0x5A => unsafe { Some(std::mem::transmute::<u8, Key>(wparam as u8 - 0x17)) }, // A-Z
-        0x70..=0x83 => unsafe { Some(std::mem::transmute::<u8, Key>(wparam as u8 - 0x2C)) }, // F1-F20
-        _ => match VIRTUAL_KEY(wparam as u16) {
-            VK_DOWN => Some(Key::ArrowDown),
-            VK_LEFT => Some(Key::ArrowLeft),
-            VK_RIGHT => Some(Key::ArrowRight),
-            VK_UP => Some(Key::ArrowUp),
-            VK_ESCAPE => Some(Key::Escape),
-            VK_TAB => Some(Key::Tab),
-            VK_BACK => Some(Key::Backspace),
-            VK_RETURN => Some(Key::Enter),
-            VK_SPACE => Some(Key::Space),
-            VK_INSERT => Some(Key::Insert),
-            VK_DELETE => Some(Key::Delete),
-            VK_HOME => Some(Key::Home),
-            VK_END => Some(Key::End),
-            VK_PRIOR => Some(Key::PageUp),
-            VK_NEXT => Some(Key::PageDown),
-            _ => None,
-        },
-    }
+    keys::key_from_vk(wparam as u16, || {
+        // Dead keys set the top bit, they type nothing by themselves.
+        let c = unsafe { MapVirtualKeyW(wparam as u32, MAPVK_VK_TO_CHAR) };
+        char::from_u32(c).filter(|c| !c.is_control())
+    })
 }
 
 #[test]
@@ -413,6 +601,98 @@ fn test_key_map() {
     assert_eq!(get_key(0x83), Some(Key::F20));
 }
 
+/// Reads the `RAWINPUT` of a `WM_INPUT`.
+fn get_raw_input(lparam: isize) -> Option<Vec<u8>> {
+    unsafe {
+        let handle = HRAWINPUT(lparam as _);
+        let header_size = std::mem::size_of::<RAWINPUTHEADER>() as u32;
+        let mut size = 0;
+        GetRawInputData(handle, RID_INPUT, None, &mut size, header_size);
+
+        let mut buf = vec![0u8; size as usize];
+        let read = GetRawInputData(
+            handle,
+            RID_INPUT,
+            Some(buf.as_mut_ptr().cast()),
+            &mut size,
+            header_size,
+        );
+        (read == size).then_some(buf)
+    }
+}
+
+/// Maps the normalized coordinates of absolute raw input to the client area.
+fn desktop_to_client(hwnd: HWND, x: i32, y: i32, virtual_desktop: bool) -> Pos2 {
+    unsafe {
+        let desktop = if virtual_desktop {
+            Rect::from_min_size(
+                pos2(
+                    GetSystemMetrics(SM_XVIRTUALSCREEN) as f32,
+                    GetSystemMetrics(SM_YVIRTUALSCREEN) as f32,
+                ),
+                vec2(
+                    GetSystemMetrics(SM_CXVIRTUALSCREEN) as f32,
+                    GetSystemMetrics(SM_CYVIRTUALSCREEN) as f32,
+                ),
+            )
+        } else {
+            Rect::from_min_size(
+                Pos2::ZERO,
+                vec2(
+                    GetSystemMetrics(SM_CXSCREEN) as f32,
+                    GetSystemMetrics(SM_CYSCREEN) as f32,
+                ),
+            )
+        };
+
+        let mut origin = POINT::default();
+        let _ = ClientToScreen(hwnd, &mut origin);
+        raw_input::normalized_to_desktop(x, y, desktop) - vec2(origin.x as f32, origin.y as f32)
+    }
+}
+
+/// What a key types with the held modifiers, as UTF-16.
+fn get_key_text(vk: usize, lparam: isize, modifiers: &ModifierState) -> Vec<u16> {
+    let caps_lock = unsafe { GetKeyState(VK_CAPITAL.0 as i32) } & 1 != 0;
+    let state = raw_input::keyboard_state(modifiers, caps_lock);
+    let mut buf = [0u16; 8];
+
+    // Flag 4 leaves the dead key state alone, for `TranslateMessage` in the game.
+    let len = unsafe {
+        ToUnicodeEx(
+            vk as u32,
+            (lparam >> 16 & 0xFF) as u32,
+            &state,
+            &mut buf,
+            1 << 2,
+            GetKeyboardLayout(0),
+        )
+    };
+    buf[..len.clamp(0, buf.len() as i32) as usize].to_vec()
+}
+
+/// Reads the composition or result string of the window's input context.
+fn get_composition_string(hwnd: HWND, flag: u32) -> Option<String> {
+    unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.is_invalid() {
+            return None;
+        }
+
+        // The length is in bytes.
+        let flag = IME_COMPOSITION_STRING(flag);
+        let len = ImmGetCompositionStringW(himc, flag, None, 0);
+        let text = (len >= 0).then(|| {
+            let mut buf = vec![0u16; len as usize / 2];
+            ImmGetCompositionStringW(himc, flag, Some(buf.as_mut_ptr().cast()), len as u32);
+            String::from_utf16_lossy(&buf)
+        });
+
+        let _ = ImmReleaseContext(hwnd, himc);
+        text
+    }
+}
+
 fn get_clipboard_text() -> Option<String> {
     WindowsClipboardContext.get_contents().ok()
 }
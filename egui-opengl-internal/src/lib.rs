@@ -1,3 +1,6 @@
+// `expect!` and `panic_msg!` are only used by the Windows app so far.
+#![cfg_attr(not(windows), allow(unused_macros))]
+
 /// This macros allows to hide panicing messages in output binary when feature `no-msgs` is present.
 macro_rules! expect {
     ($val:expr, $msg:expr) => {
@@ -25,10 +28,47 @@ compile_error!("Only one of features `parking-lot`, `spin-lock` must be enabled.
 #[cfg(not(any(feature = "parking-lot", feature = "spin-lock")))]
 compile_error!("One of the features `parking-lot`, `spin-lock` must be enabled.");
 
+#[cfg(windows)]
 mod app;
+#[cfg(windows)]
 pub use app::OpenGLApp;
 
+mod batch;
+pub use batch::FrameStats;
+
+mod error;
+pub use error::{Error, Result};
+
+mod gl_state;
+pub use gl_state::StateBackup;
+
+pub mod glfw;
+
+#[cfg(windows)]
 mod input;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::{EglApp, GlxApp, LinuxApp};
+
+mod loader;
+#[cfg(unix)]
+pub use loader::GlxLoader;
+#[cfg(windows)]
+pub use loader::WglLoader;
+pub use loader::{load_gl, EglLoader, GlLoader, LoadReport};
+
 mod painter;
+pub use painter::{CallbackFn, Painter};
+
+mod renderer;
+pub use renderer::Renderer;
+
+pub mod sdl;
+
 mod shader;
+#[cfg(windows)]
 pub mod utils;
+
+mod win32;
@@ -0,0 +1,171 @@
+use super::Platform;
+use crate::{load_gl, EglLoader, Error, Result};
+use khronos_egl as egl;
+use std::ffi::c_void;
+
+/// The arguments of an `eglSwapBuffers` hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EglSurface {
+    pub display: *mut c_void,
+    pub surface: *mut c_void,
+}
+
+impl EglSurface {
+    fn display(&self) -> egl::Display {
+        unsafe { egl::Display::from_ptr(self.display) }
+    }
+
+    fn surface(&self) -> egl::Surface {
+        unsafe { egl::Surface::from_ptr(self.surface) }
+    }
+}
+
+/// The overlay's context and the client API it was created for, which has to be bound to make it current.
+#[derive(Debug, Clone, Copy)]
+pub struct EglContext {
+    context: egl::Context,
+    api: egl::Enum,
+}
+
+/// Contexts current on the calling thread, and the API bound for it.
+pub struct EglBindings {
+    display: Option<egl::Display>,
+    draw: Option<egl::Surface>,
+    read: Option<egl::Surface>,
+    context: Option<egl::Context>,
+    api: egl::Enum,
+}
+
+/// `libEGL`, opened at runtime so hooks work in processes that load it late.
+pub struct Egl {
+    egl: egl::DynamicInstance<egl::EGL1_4>,
+}
+
+/// Maps a failed EGL call to [`Error::Context`].
+fn egl_error(call: &'static str) -> impl FnOnce(egl::Error) -> Error {
+    move |err| Error::Context {
+        call,
+        message: err.to_string(),
+    }
+}
+
+impl Platform for Egl {
+    type Surface = EglSurface;
+    type Context = EglContext;
+    type Bindings = EglBindings;
+
+    fn open() -> Result<Self> {
+        let egl =
+            unsafe { egl::DynamicInstance::<egl::EGL1_4>::load_required() }.map_err(|err| {
+                Error::Context {
+                    call: "dlopen",
+                    message: err.to_string(),
+                }
+            })?;
+
+        Ok(Self { egl })
+    }
+
+    fn load_gl(&self) -> Result<()> {
+        let loader = EglLoader::new().ok_or_else(|| Error::Context {
+            call: "dlopen",
+            message: "libEGL.so.1 not found".into(),
+        })?;
+        load_gl(&loader).into_result()
+    }
+
+    fn surface_size(&self, surface: EglSurface) -> Result<(u32, u32)> {
+        let query = |attribute| {
+            self.egl
+                .query_surface(surface.display(), surface.surface(), attribute)
+                .map_err(egl_error("eglQuerySurface"))
+        };
+
+        Ok((query(egl::WIDTH)? as u32, query(egl::HEIGHT)? as u32))
+    }
+
+    fn current_bindings(&self) -> EglBindings {
+        EglBindings {
+            display: self.egl.get_current_display(),
+            draw: self.egl.get_current_surface(egl::DRAW),
+            read: self.egl.get_current_surface(egl::READ),
+            context: self.egl.get_current_context(),
+            api: self.egl.query_api(),
+        }
+    }
+
+    fn restore_bindings(&self, surface: EglSurface, bindings: &EglBindings) -> Result<()> {
+        self.egl
+            .bind_api(bindings.api)
+            .map_err(egl_error("eglBindAPI"))?;
+
+        match bindings.display {
+            Some(display) => {
+                self.egl
+                    .make_current(display, bindings.draw, bindings.read, bindings.context)
+            }
+            None => self.egl.make_current(surface.display(), None, None, None),
+        }
+        .map_err(egl_error("eglMakeCurrent"))
+    }
+
+    /// Uses the config of the surface and the client API and version of the host's current context.
+    fn create_context(&self, surface: EglSurface) -> Result<EglContext> {
+        let display = surface.display();
+        let config_id = self
+            .egl
+            .query_surface(display, surface.surface(), egl::CONFIG_ID)
+            .map_err(egl_error("eglQuerySurface"))?;
+        let config = self
+            .egl
+            .choose_first_config(display, &[egl::CONFIG_ID, config_id, egl::NONE])
+            .map_err(egl_error("eglChooseConfig"))?
+            .ok_or_else(|| Error::Context {
+                call: "eglChooseConfig",
+                message: "the surface's config was not found".into(),
+            })?;
+
+        let api = self.egl.query_api();
+        let attribs = if api == egl::OPENGL_ES_API {
+            let version = self
+                .egl
+                .get_current_context()
+                .and_then(|host| {
+                    self.egl
+                        .query_context(display, host, egl::CONTEXT_CLIENT_VERSION)
+                        .ok()
+                })
+                .unwrap_or(2);
+            vec![egl::CONTEXT_CLIENT_VERSION, version, egl::NONE]
+        } else {
+            vec![egl::NONE]
+        };
+
+        let context = self
+            .egl
+            .create_context(display, config, None, &attribs)
+            .map_err(egl_error("eglCreateContext"))?;
+
+        Ok(EglContext { context, api })
+    }
+
+    fn make_current(&self, surface: EglSurface, context: EglContext) -> Result<()> {
+        self.egl
+            .bind_api(context.api)
+            .map_err(egl_error("eglBindAPI"))?;
+        self.egl
+            .make_current(
+                surface.display(),
+                Some(surface.surface()),
+                Some(surface.surface()),
+                Some(context.context),
+            )
+            .map_err(egl_error("eglMakeCurrent"))
+    }
+
+    fn destroy_context(&self, surface: EglSurface, context: EglContext) -> Result<()> {
+        self.egl
+            .destroy_context(surface.display(), context.context)
+            .map_err(egl_error("eglDestroyContext"))
+    }
+}
@@ -0,0 +1,183 @@
+use super::Platform;
+use crate::{load_gl, Error, GlxLoader, Result};
+use std::ptr;
+use x11_dl::{
+    glx::{self, GLXContext, GLXDrawable},
+    xlib::{self, Xlib},
+};
+
+/// The arguments of a `glXSwapBuffers` hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlxSurface {
+    pub display: *mut xlib::Display,
+    pub drawable: GLXDrawable,
+}
+
+/// The overlay's context.
+#[derive(Debug, Clone, Copy)]
+pub struct GlxContext(GLXContext);
+
+/// Contexts current on the calling thread.
+pub struct GlxBindings {
+    display: *mut xlib::Display,
+    draw: GLXDrawable,
+    read: GLXDrawable,
+    context: GLXContext,
+}
+
+/// `libX11` and `libGL`, opened at runtime so hooks work in processes that load them late.
+pub struct Glx {
+    xlib: Xlib,
+    glx: glx::Glx,
+}
+
+fn glx_error(call: &'static str, message: &str) -> Error {
+    Error::Context {
+        call,
+        message: message.into(),
+    }
+}
+
+impl Platform for Glx {
+    type Surface = GlxSurface;
+    type Context = GlxContext;
+    type Bindings = GlxBindings;
+
+    fn open() -> Result<Self> {
+        let open_error = |err: x11_dl::error::OpenError| Error::Context {
+            call: "dlopen",
+            message: err.to_string(),
+        };
+
+        Ok(Self {
+            xlib: Xlib::open().map_err(open_error)?,
+            glx: glx::Glx::open().map_err(open_error)?,
+        })
+    }
+
+    fn load_gl(&self) -> Result<()> {
+        let loader = GlxLoader::new().ok_or_else(|| glx_error("dlopen", "libGL.so.1 not found"))?;
+        load_gl(&loader).into_result()
+    }
+
+    fn surface_size(&self, surface: GlxSurface) -> Result<(u32, u32)> {
+        let (mut root, mut x, mut y) = (0, 0, 0);
+        let (mut width, mut height, mut border, mut depth) = (0, 0, 0, 0);
+        let status = unsafe {
+            (self.xlib.XGetGeometry)(
+                surface.display,
+                surface.drawable,
+                &mut root,
+                &mut x,
+                &mut y,
+                &mut width,
+                &mut height,
+                &mut border,
+                &mut depth,
+            )
+        };
+        if status == 0 {
+            return Err(glx_error("XGetGeometry", "the drawable does not exist"));
+        }
+
+        Ok((width, height))
+    }
+
+    fn current_bindings(&self) -> GlxBindings {
+        unsafe {
+            GlxBindings {
+                display: (self.glx.glXGetCurrentDisplay)(),
+                draw: (self.glx.glXGetCurrentDrawable)(),
+                read: (self.glx.glXGetCurrentReadDrawable)(),
+                context: (self.glx.glXGetCurrentContext)(),
+            }
+        }
+    }
+
+    fn restore_bindings(&self, surface: GlxSurface, bindings: &GlxBindings) -> Result<()> {
+        let restored = unsafe {
+            if bindings.display.is_null() {
+                (self.glx.glXMakeContextCurrent)(surface.display, 0, 0, ptr::null_mut())
+            } else {
+                (self.glx.glXMakeContextCurrent)(
+                    bindings.display,
+                    bindings.draw,
+                    bindings.read,
+                    bindings.context,
+                )
+            }
+        };
+        if restored == 0 {
+            return Err(glx_error(
+                "glXMakeContextCurrent",
+                "cannot restore the host's context",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Uses the framebuffer config of the host's current context, which is known to fit the drawable.
+    fn create_context(&self, surface: GlxSurface) -> Result<GlxContext> {
+        unsafe {
+            let host = (self.glx.glXGetCurrentContext)();
+            if host.is_null() {
+                return Err(glx_error("glXGetCurrentContext", "no context is current"));
+            }
+
+            let (mut config_id, mut screen) = (0, 0);
+            (self.glx.glXQueryContext)(surface.display, host, glx::GLX_FBCONFIG_ID, &mut config_id);
+            (self.glx.glXQueryContext)(surface.display, host, glx::GLX_SCREEN, &mut screen);
+
+            let attribs = [glx::GLX_FBCONFIG_ID, config_id, 0];
+            let mut count = 0;
+            let configs =
+                (self.glx.glXChooseFBConfig)(surface.display, screen, attribs.as_ptr(), &mut count);
+            if configs.is_null() {
+                return Err(glx_error(
+                    "glXChooseFBConfig",
+                    "the host's framebuffer config was not found",
+                ));
+            }
+            let config = *configs;
+            (self.xlib.XFree)(configs as *mut _);
+
+            let context = (self.glx.glXCreateNewContext)(
+                surface.display,
+                config,
+                glx::GLX_RGBA_TYPE,
+                ptr::null_mut(),
+                xlib::True,
+            );
+            if context.is_null() {
+                return Err(glx_error("glXCreateNewContext", "returned no context"));
+            }
+
+            Ok(GlxContext(context))
+        }
+    }
+
+    fn make_current(&self, surface: GlxSurface, context: GlxContext) -> Result<()> {
+        let current = unsafe {
+            (self.glx.glXMakeContextCurrent)(
+                surface.display,
+                surface.drawable,
+                surface.drawable,
+                context.0,
+            )
+        };
+        if current == 0 {
+            return Err(glx_error(
+                "glXMakeContextCurrent",
+                "cannot bind the drawable",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn destroy_context(&self, surface: GlxSurface, context: GlxContext) -> Result<()> {
+        unsafe { (self.glx.glXDestroyContext)(surface.display, context.0) };
+        Ok(())
+    }
+}
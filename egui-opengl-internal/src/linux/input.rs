@@ -0,0 +1,811 @@
+use crate::loader::Library;
+use egui::{
+    Context, Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect, Vec2,
+};
+use std::ffi::{c_char, c_int, c_uint};
+use x11_dl::{
+    keysym::*,
+    xlib::{self, KeySym, XEvent, XKeyEvent, Xlib},
+};
+
+/// What a key event means under the current keyboard layout.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyInfo {
+    /// Keysym with the event's modifiers and group applied, e.g. `XK_A` while shift is held.
+    pub keysym: KeySym,
+    /// Keysym of the key without modifiers in the first group, used for `physical_key`.
+    pub base_keysym: KeySym,
+    pub text: Option<String>,
+}
+
+/// Resolves key events with the keyboard mapping of their display.
+pub trait KeyLookup {
+    fn lookup(&self, event: &XKeyEvent) -> KeyInfo;
+}
+
+type KeysymToUtf8 = unsafe extern "C" fn(u32, *mut c_char, usize) -> c_int;
+
+/// Looks keys up with `XLookupString` and `XkbKeycodeToKeysym`,
+/// and turns keysyms into text with `libxkbcommon`, falling back to Latin-1 and Unicode keysyms without it.
+pub struct XkbLookup {
+    xlib: Option<Xlib>,
+    keysym_to_utf8: Option<(Library, KeysymToUtf8)>,
+}
+
+impl XkbLookup {
+    pub fn new() -> Self {
+        let keysym_to_utf8 =
+            Library::open(&["libxkbcommon.so.0", "libxkbcommon.so"]).and_then(|library| {
+                let f = library.symbol("xkb_keysym_to_utf8");
+                (!f.is_null()).then(|| {
+                    (library, unsafe {
+                        std::mem::transmute::<*const std::ffi::c_void, KeysymToUtf8>(f)
+                    })
+                })
+            });
+
+        Self {
+            xlib: Xlib::open().ok(),
+            keysym_to_utf8,
+        }
+    }
+
+    fn text(&self, keysym: KeySym) -> Option<String> {
+        let Some((_, keysym_to_utf8)) = &self.keysym_to_utf8 else {
+            return keysym_text(keysym);
+        };
+
+        let mut buffer = [0u8; 8];
+        let len = unsafe {
+            keysym_to_utf8(
+                keysym as u32,
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer.len(),
+            )
+        };
+        // The length includes the terminating null.
+        let len = usize::try_from(len).ok()?.checked_sub(1)?;
+        std::str::from_utf8(&buffer[..len]).ok().map(str::to_owned)
+    }
+}
+
+impl Default for XkbLookup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyLookup for XkbLookup {
+    fn lookup(&self, event: &XKeyEvent) -> KeyInfo {
+        let Some(xlib) = &self.xlib else {
+            return KeyInfo::default();
+        };
+
+        let mut event = *event;
+        let mut keysym = 0;
+        let mut buffer = [0 as c_char; 32];
+        let base_keysym = unsafe {
+            (xlib.XLookupString)(
+                &mut event,
+                buffer.as_mut_ptr(),
+                buffer.len() as c_int,
+                &mut keysym,
+                std::ptr::null_mut(),
+            );
+            (xlib.XkbKeycodeToKeysym)(event.display, event.keycode as u8, 0, 0)
+        };
+
+        KeyInfo {
+            keysym,
+            base_keysym,
+            text: self.text(keysym),
+        }
+    }
+}
+
+/// Text typed by a keysym: Latin-1 keysyms are their own code points and `0x01000000 + c` is any other Unicode character.
+fn keysym_text(keysym: KeySym) -> Option<String> {
+    let c = match keysym {
+        0x20..=0x7e | 0xa0..=0xff => keysym as u32,
+        0x0100_0000..=0x0110_ffff => (keysym - 0x0100_0000) as u32,
+        _ => return None,
+    };
+    char::from_u32(c).map(String::from)
+}
+
+/// Counterpart of the Windows `InputCollector` for X11 event streams.
+pub struct XInputCollector {
+    events: Vec<Event>,
+    modifiers: Modifiers,
+    /// Keys currently down by keycode with the key and physical key of their press, to tell repeats apart,
+    /// release what was pressed even if the modifiers changed the keysym since, and release them when focus is lost.
+    held_keys: Vec<(c_uint, Key, Option<Key>)>,
+    focused: bool,
+}
+
+/// High-level overview of recognized X events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XInputResult {
+    Unknown,
+    Pointer,
+    Key,
+    Focus,
+}
+
+impl XInputCollector {
+    pub fn new() -> Self {
+        Self {
+            events: vec![],
+            modifiers: Modifiers::NONE,
+            held_keys: vec![],
+            focused: true,
+        }
+    }
+
+    pub fn process(&mut self, event: &XEvent, keys: &impl KeyLookup) -> XInputResult {
+        match event.get_type() {
+            ty @ (xlib::KeyPress | xlib::KeyRelease) => {
+                let event = unsafe { &event.key };
+                let pressed = ty == xlib::KeyPress;
+                let modifiers = get_modifiers(event.state);
+                self.modifiers = modifiers;
+
+                let info = keys.lookup(event);
+                let held = self
+                    .held_keys
+                    .iter()
+                    .position(|&(keycode, ..)| keycode == event.keycode);
+                // Pressing `1` then shift releases `!`, so a held key keeps what it was pressed as.
+                // Keysyms without a key, e.g. of Cyrillic letters, fall back to the physical key like egui-winit does.
+                let key = match held {
+                    Some(i) => Some((self.held_keys[i].1, self.held_keys[i].2)),
+                    None => {
+                        let physical_key = get_key(info.base_keysym);
+                        get_key(info.keysym)
+                            .or(physical_key)
+                            .map(|key| (key, physical_key))
+                    }
+                };
+
+                if let Some((key, physical_key)) = key {
+                    let repeat = pressed && held.is_some();
+                    match (pressed, held) {
+                        (true, None) => self.held_keys.push((event.keycode, key, physical_key)),
+                        (false, Some(i)) => {
+                            self.held_keys.swap_remove(i);
+                        }
+                        _ => {}
+                    }
+
+                    if pressed && modifiers.ctrl {
+                        match key {
+                            Key::C => self.events.push(Event::Copy),
+                            Key::X => self.events.push(Event::Cut),
+                            _ => {}
+                        }
+                    }
+
+                    self.events.push(Event::Key {
+                        key,
+                        physical_key,
+                        pressed,
+                        repeat,
+                        modifiers,
+                    });
+                }
+
+                // Shortcuts are not typing, but AltGr (usually `Mod5`) is.
+                if pressed && !modifiers.ctrl && !modifiers.alt {
+                    if let Some(text) = info.text.filter(|text| !text.chars().any(char::is_control))
+                    {
+                        self.events.push(Event::Text(text));
+                    }
+                }
+                XInputResult::Key
+            }
+            ty @ (xlib::ButtonPress | xlib::ButtonRelease) => {
+                let event = unsafe { &event.button };
+                let pressed = ty == xlib::ButtonPress;
+                let modifiers = get_modifiers(event.state);
+                self.modifiers = modifiers;
+                let pos = Pos2::new(event.x as f32, event.y as f32);
+
+                if let Some(delta) = get_wheel_delta(event.button) {
+                    // Every notch is a press and release pair, only the press counts.
+                    if pressed {
+                        self.events.push(Event::MouseWheel {
+                            unit: MouseWheelUnit::Line,
+                            delta,
+                            modifiers,
+                        });
+                    }
+                } else if let Some(button) = get_button(event.button) {
+                    self.events.push(Event::PointerButton {
+                        pos,
+                        button,
+                        pressed,
+                        modifiers,
+                    });
+                }
+                XInputResult::Pointer
+            }
+            xlib::MotionNotify => {
+                let event = unsafe { &event.motion };
+                self.modifiers = get_modifiers(event.state);
+                self.events.push(Event::PointerMoved(Pos2::new(
+                    event.x as f32,
+                    event.y as f32,
+                )));
+                XInputResult::Pointer
+            }
+            xlib::EnterNotify => {
+                let event = unsafe { &event.crossing };
+                self.events.push(Event::PointerMoved(Pos2::new(
+                    event.x as f32,
+                    event.y as f32,
+                )));
+                XInputResult::Pointer
+            }
+            xlib::LeaveNotify => {
+                self.events.push(Event::PointerGone);
+                XInputResult::Pointer
+            }
+            ty @ (xlib::FocusIn | xlib::FocusOut) => {
+                let event = unsafe { &event.focus_change };
+                // Focus moving between the window and its children, or a grab, does not change whether it has the keyboard.
+                if matches!(event.detail, xlib::NotifyInferior | xlib::NotifyPointer)
+                    || event.mode == xlib::NotifyGrab
+                    || event.mode == xlib::NotifyUngrab
+                {
+                    return XInputResult::Focus;
+                }
+
+                self.focused = ty == xlib::FocusIn;
+                if !self.focused {
+                    // Their releases go to whichever window has the focus now.
+                    for (_, key, physical_key) in self.held_keys.drain(..) {
+                        self.events.push(Event::Key {
+                            key,
+                            physical_key,
+                            pressed: false,
+                            repeat: false,
+                            modifiers: Modifiers::NONE,
+                        });
+                    }
+                    self.modifiers = Modifiers::NONE;
+                }
+                self.events.push(Event::WindowFocused(self.focused));
+                XInputResult::Focus
+            }
+            _ => XInputResult::Unknown,
+        }
+    }
+
+    pub fn collect_input(&mut self, ctx: &Context, screen_rect: Rect, time: f64) -> RawInput {
+        RawInput {
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            screen_rect: Some(screen_rect),
+            time: Some(time),
+            focused: self.focused,
+            viewport_id: ctx.viewport_id(),
+            viewports: ctx.input(|i| i.raw.viewports.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for XInputCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn get_modifiers(state: c_uint) -> Modifiers {
+    let ctrl = state & xlib::ControlMask != 0;
+
+    Modifiers {
+        alt: state & xlib::Mod1Mask != 0,
+        ctrl,
+        shift: state & xlib::ShiftMask != 0,
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+/// Buttons 4 to 7 are the notches of the vertical and horizontal wheel.
+fn get_wheel_delta(button: c_uint) -> Option<Vec2> {
+    match button {
+        4 => Some(Vec2::new(0.0, 1.0)),
+        5 => Some(Vec2::new(0.0, -1.0)),
+        6 => Some(Vec2::new(1.0, 0.0)),
+        7 => Some(Vec2::new(-1.0, 0.0)),
+        _ => None,
+    }
+}
+
+fn get_button(button: c_uint) -> Option<PointerButton> {
+    match button {
+        xlib::Button1 => Some(PointerButton::Primary),
+        xlib::Button2 => Some(PointerButton::Middle),
+        xlib::Button3 => Some(PointerButton::Secondary),
+        8 => Some(PointerButton::Extra1),
+        9 => Some(PointerButton::Extra2),
+        _ => None,
+    }
+}
+
+const DIGITS: [Key; 10] = [
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+const FUNCTION_KEYS: [Key; 35] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+    Key::F21,
+    Key::F22,
+    Key::F23,
+    Key::F24,
+    Key::F25,
+    Key::F26,
+    Key::F27,
+    Key::F28,
+    Key::F29,
+    Key::F30,
+    Key::F31,
+    Key::F32,
+    Key::F33,
+    Key::F34,
+    Key::F35,
+];
+
+/// Keysym names are kept as Xlib spells them.
+#[allow(non_upper_case_globals)]
+fn get_key(keysym: KeySym) -> Option<Key> {
+    let Ok(keysym) = c_uint::try_from(keysym) else {
+        return None;
+    };
+
+    let key = match keysym {
+        XK_0..=XK_9 => DIGITS[(keysym - XK_0) as usize],
+        XK_KP_0..=XK_KP_9 => DIGITS[(keysym - XK_KP_0) as usize],
+        XK_a..=XK_z => LETTERS[(keysym - XK_a) as usize],
+        XK_A..=XK_Z => LETTERS[(keysym - XK_A) as usize],
+        XK_F1..=XK_F35 => FUNCTION_KEYS[(keysym - XK_F1) as usize],
+        XK_Down | XK_KP_Down => Key::ArrowDown,
+        XK_Left | XK_KP_Left => Key::ArrowLeft,
+        XK_Right | XK_KP_Right => Key::ArrowRight,
+        XK_Up | XK_KP_Up => Key::ArrowUp,
+        XK_Escape => Key::Escape,
+        XK_Tab | XK_KP_Tab | XK_ISO_Left_Tab => Key::Tab,
+        XK_BackSpace => Key::Backspace,
+        XK_Return | XK_KP_Enter => Key::Enter,
+        XK_space | XK_KP_Space => Key::Space,
+        XK_Insert | XK_KP_Insert => Key::Insert,
+        XK_Delete | XK_KP_Delete => Key::Delete,
+        XK_Home | XK_KP_Home => Key::Home,
+        XK_End | XK_KP_End => Key::End,
+        XK_Prior | XK_KP_Prior => Key::PageUp,
+        XK_Next | XK_KP_Next => Key::PageDown,
+        XK_colon => Key::Colon,
+        XK_comma => Key::Comma,
+        XK_backslash => Key::Backslash,
+        XK_slash | XK_KP_Divide => Key::Slash,
+        XK_bar => Key::Pipe,
+        XK_question => Key::Questionmark,
+        XK_bracketleft => Key::OpenBracket,
+        XK_bracketright => Key::CloseBracket,
+        XK_grave => Key::Backtick,
+        XK_minus | XK_KP_Subtract => Key::Minus,
+        XK_period | XK_KP_Decimal => Key::Period,
+        XK_plus | XK_KP_Add => Key::Plus,
+        XK_equal | XK_KP_Equal => Key::Equals,
+        XK_semicolon => Key::Semicolon,
+        XK_apostrophe => Key::Quote,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A keyboard with the given keycode to `KeyInfo` mapping, and another one while shift is held.
+    struct FakeKeyboard {
+        keys: Vec<(c_uint, KeyInfo)>,
+        shifted: Vec<(c_uint, KeyInfo)>,
+    }
+
+    impl KeyLookup for FakeKeyboard {
+        fn lookup(&self, event: &XKeyEvent) -> KeyInfo {
+            let find = |keys: &[(c_uint, KeyInfo)]| {
+                keys.iter()
+                    .find(|(keycode, _)| *keycode == event.keycode)
+                    .map(|(_, info)| info.clone())
+            };
+            let shifted = (event.state & xlib::ShiftMask != 0)
+                .then(|| find(&self.shifted))
+                .flatten();
+            shifted.or_else(|| find(&self.keys)).unwrap_or_default()
+        }
+    }
+
+    fn key_info(keysym: c_uint, base_keysym: c_uint, text: Option<&str>) -> KeyInfo {
+        KeyInfo {
+            keysym: keysym.into(),
+            base_keysym: base_keysym.into(),
+            text: text.map(str::to_owned),
+        }
+    }
+
+    fn keyboard() -> FakeKeyboard {
+        FakeKeyboard {
+            keys: vec![
+                (38, key_info(XK_a, XK_a, Some("a"))),
+                (54, key_info(XK_c, XK_c, Some("c"))),
+                (36, key_info(XK_Return, XK_Return, Some("\r"))),
+                (44, key_info(XK_Cyrillic_a, XK_j, Some("а"))),
+                (10, key_info(XK_1, XK_1, Some("1"))),
+                (21, key_info(XK_equal, XK_equal, Some("="))),
+                (50, key_info(XK_Shift_L, XK_Shift_L, None)),
+            ],
+            shifted: vec![
+                (10, key_info(XK_exclam, XK_1, Some("!"))),
+                (21, key_info(XK_plus, XK_equal, Some("+"))),
+            ],
+        }
+    }
+
+    fn key_event(ty: c_int, keycode: c_uint, state: c_uint) -> XEvent {
+        let mut event: XKeyEvent = unsafe { std::mem::zeroed() };
+        event.type_ = ty;
+        event.keycode = keycode;
+        event.state = state;
+        XEvent { key: event }
+    }
+
+    fn button_event(ty: c_int, button: c_uint, x: c_int, y: c_int) -> XEvent {
+        let mut event: xlib::XButtonEvent = unsafe { std::mem::zeroed() };
+        event.type_ = ty;
+        event.button = button;
+        event.x = x;
+        event.y = y;
+        XEvent { button: event }
+    }
+
+    fn focus_event(ty: c_int, mode: c_int, detail: c_int) -> XEvent {
+        let mut event: xlib::XFocusChangeEvent = unsafe { std::mem::zeroed() };
+        event.type_ = ty;
+        event.mode = mode;
+        event.detail = detail;
+        XEvent {
+            focus_change: event,
+        }
+    }
+
+    fn collect(collector: &mut XInputCollector) -> Vec<Event> {
+        std::mem::take(&mut collector.events)
+    }
+
+    #[test]
+    fn test_key_press_text_and_repeat() {
+        let keys = keyboard();
+        let mut collector = XInputCollector::new();
+
+        let result = collector.process(&key_event(xlib::KeyPress, 38, 0), &keys);
+        assert_eq!(result, XInputResult::Key);
+        collector.process(&key_event(xlib::KeyPress, 38, 0), &keys);
+        collector.process(&key_event(xlib::KeyRelease, 38, 0), &keys);
+
+        let key = |pressed, repeat| Event::Key {
+            key: Key::A,
+            physical_key: Some(Key::A),
+            pressed,
+            repeat,
+            modifiers: Modifiers::NONE,
+        };
+        assert_eq!(
+            collect(&mut collector),
+            [
+                key(true, false),
+                Event::Text("a".into()),
+                key(true, true),
+                Event::Text("a".into()),
+                key(false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_shortcuts_and_layouts() {
+        let keys = keyboard();
+        let mut collector = XInputCollector::new();
+
+        collector.process(&key_event(xlib::KeyPress, 54, xlib::ControlMask), &keys);
+        collector.process(&key_event(xlib::KeyPress, 36, 0), &keys);
+        collector.process(&key_event(xlib::KeyPress, 44, 0), &keys);
+
+        let events = collect(&mut collector);
+        assert_eq!(events[0], Event::Copy);
+        assert!(matches!(
+            events[1],
+            Event::Key { key: Key::C, modifiers, .. } if modifiers.ctrl && modifiers.command
+        ));
+        // Enter types a carriage return, which is not text.
+        assert!(matches!(
+            events[2],
+            Event::Key {
+                key: Key::Enter,
+                ..
+            }
+        ));
+        // A Cyrillic letter has no logical key, so it is the key at its physical position, `J`, and typed.
+        assert_eq!(
+            events[3],
+            Event::Key {
+                key: Key::J,
+                physical_key: Some(Key::J),
+                pressed: true,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            }
+        );
+        assert_eq!(events[4], Event::Text("а".into()));
+        assert_eq!(events.len(), 5);
+        assert_eq!(collector.modifiers, Modifiers::NONE);
+    }
+
+    #[test]
+    fn test_release_after_shift() {
+        let keys = keyboard();
+        let mut collector = XInputCollector::new();
+
+        // `1` is released as `!` and `=` as `+` with shift down, but egui has to see the keys it saw pressed go up.
+        for keycode in [10, 21] {
+            collector.process(&key_event(xlib::KeyPress, keycode, 0), &keys);
+            collector.process(&key_event(xlib::KeyPress, 50, 0), &keys);
+            collector.process(
+                &key_event(xlib::KeyRelease, keycode, xlib::ShiftMask),
+                &keys,
+            );
+            collector.process(&key_event(xlib::KeyRelease, 50, xlib::ShiftMask), &keys);
+        }
+
+        let key = |key, pressed| Event::Key {
+            key,
+            physical_key: Some(key),
+            pressed,
+            repeat: false,
+            modifiers: if pressed {
+                Modifiers::NONE
+            } else {
+                Modifiers::SHIFT
+            },
+        };
+        assert_eq!(
+            collect(&mut collector),
+            [
+                key(Key::Num1, true),
+                Event::Text("1".into()),
+                key(Key::Num1, false),
+                key(Key::Equals, true),
+                Event::Text("=".into()),
+                key(Key::Equals, false),
+            ]
+        );
+        assert!(collector.held_keys.is_empty());
+    }
+
+    #[test]
+    fn test_buttons_and_wheel() {
+        let keys = keyboard();
+        let mut collector = XInputCollector::new();
+
+        for button in 1..=9 {
+            collector.process(&button_event(xlib::ButtonPress, button, 10, 20), &keys);
+            let result =
+                collector.process(&button_event(xlib::ButtonRelease, button, 10, 20), &keys);
+            assert_eq!(result, XInputResult::Pointer);
+        }
+
+        let pos = Pos2::new(10.0, 20.0);
+        let buttons: Vec<_> = collect(&mut collector)
+            .into_iter()
+            .map(|event| match event {
+                Event::PointerButton {
+                    pos: p,
+                    button,
+                    pressed,
+                    ..
+                } => {
+                    assert_eq!(p, pos);
+                    Ok((button, pressed))
+                }
+                Event::MouseWheel { unit, delta, .. } => {
+                    assert_eq!(unit, MouseWheelUnit::Line);
+                    Err(delta)
+                }
+                event => panic!("unexpected {event:?}"),
+            })
+            .collect();
+
+        use PointerButton::*;
+        assert_eq!(
+            buttons,
+            [
+                Ok((Primary, true)),
+                Ok((Primary, false)),
+                Ok((Middle, true)),
+                Ok((Middle, false)),
+                Ok((Secondary, true)),
+                Ok((Secondary, false)),
+                Err(Vec2::new(0.0, 1.0)),
+                Err(Vec2::new(0.0, -1.0)),
+                Err(Vec2::new(1.0, 0.0)),
+                Err(Vec2::new(-1.0, 0.0)),
+                Ok((Extra1, true)),
+                Ok((Extra1, false)),
+                Ok((Extra2, true)),
+                Ok((Extra2, false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pointer_crossing() {
+        let keys = keyboard();
+        let mut collector = XInputCollector::new();
+
+        let mut motion: xlib::XMotionEvent = unsafe { std::mem::zeroed() };
+        motion.type_ = xlib::MotionNotify;
+        motion.x = 5;
+        motion.y = 6;
+        motion.state = xlib::ShiftMask;
+        let mut crossing: xlib::XCrossingEvent = unsafe { std::mem::zeroed() };
+        crossing.type_ = xlib::EnterNotify;
+        crossing.x = 1;
+        crossing.y = 2;
+
+        collector.process(&XEvent { crossing }, &keys);
+        collector.process(&XEvent { motion }, &keys);
+        crossing.type_ = xlib::LeaveNotify;
+        collector.process(&XEvent { crossing }, &keys);
+
+        assert_eq!(
+            collect(&mut collector),
+            [
+                Event::PointerMoved(Pos2::new(1.0, 2.0)),
+                Event::PointerMoved(Pos2::new(5.0, 6.0)),
+                Event::PointerGone,
+            ]
+        );
+        assert_eq!(collector.modifiers, Modifiers::SHIFT);
+    }
+
+    #[test]
+    fn test_focus_releases_held_keys() {
+        let keys = keyboard();
+        let mut collector = XInputCollector::new();
+
+        collector.process(&key_event(xlib::KeyPress, 38, 0), &keys);
+        collect(&mut collector);
+
+        // Focus moving to a child window changes nothing.
+        let result = collector.process(
+            &focus_event(xlib::FocusOut, xlib::NotifyNormal, xlib::NotifyInferior),
+            &keys,
+        );
+        assert_eq!(result, XInputResult::Focus);
+        assert!(collect(&mut collector).is_empty());
+
+        collector.process(&focus_event(xlib::FocusOut, xlib::NotifyNormal, 0), &keys);
+        assert_eq!(
+            collect(&mut collector),
+            [
+                Event::Key {
+                    key: Key::A,
+                    physical_key: Some(Key::A),
+                    pressed: false,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                },
+                Event::WindowFocused(false),
+            ]
+        );
+        let input = collector.collect_input(&Context::default(), Rect::ZERO, 0.0);
+        assert!(!input.focused);
+
+        collector.process(&focus_event(xlib::FocusIn, xlib::NotifyNormal, 0), &keys);
+        assert_eq!(collect(&mut collector), [Event::WindowFocused(true)]);
+        // The key was released while away, so pressing it again is not a repeat.
+        collector.process(&key_event(xlib::KeyPress, 38, 0), &keys);
+        assert!(matches!(
+            collect(&mut collector)[0],
+            Event::Key { repeat: false, .. }
+        ));
+    }
+
+    #[test]
+    fn test_key_map() {
+        let key = |keysym: c_uint| get_key(keysym.into());
+
+        assert_eq!(key(XK_0), Some(Key::Num0));
+        assert_eq!(key(XK_KP_9), Some(Key::Num9));
+        assert_eq!(key(XK_a), Some(Key::A));
+        assert_eq!(key(XK_Z), Some(Key::Z));
+        assert_eq!(key(XK_F1), Some(Key::F1));
+        assert_eq!(key(XK_F35), Some(Key::F35));
+        assert_eq!(key(XK_ISO_Left_Tab), Some(Key::Tab));
+        assert_eq!(key(XK_KP_Enter), Some(Key::Enter));
+        assert_eq!(key(XK_KP_Page_Up), Some(Key::PageUp));
+        assert_eq!(key(XK_apostrophe), Some(Key::Quote));
+        assert_eq!(key(XK_Cyrillic_a), None);
+        assert_eq!(get_key(0x1_0000_0000), None);
+    }
+
+    #[test]
+    fn test_keysym_text() {
+        assert_eq!(keysym_text(XK_a.into()).as_deref(), Some("a"));
+        assert_eq!(keysym_text(0xe9).as_deref(), Some("é"));
+        assert_eq!(keysym_text(0x0100_20ac).as_deref(), Some("€"));
+        assert_eq!(keysym_text(XK_Return.into()), None);
+        assert_eq!(keysym_text(XK_Cyrillic_a.into()), None);
+    }
+}
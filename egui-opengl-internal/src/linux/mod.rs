@@ -0,0 +1,341 @@
+//! Drawing from `glXSwapBuffers` and `eglSwapBuffers` hooks, the Linux counterpart of [`crate::OpenGLApp`].
+
+mod egl;
+mod glx;
+mod input;
+
+pub use egl::{Egl, EglBindings, EglContext, EglSurface};
+pub use glx::{Glx, GlxBindings, GlxContext, GlxSurface};
+pub use input::{KeyInfo, KeyLookup, XInputCollector, XInputResult, XkbLookup};
+
+use crate::{FrameStats, Painter, Renderer, Result, StateBackup};
+use egui::{pos2, vec2, Context, Rect};
+use std::ops::DerefMut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use x11_dl::xlib::XEvent;
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::{Mutex, MutexGuard};
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::{Mutex, MutexGuard};
+
+use lock_api::MappedMutexGuard;
+
+/// Window system glue behind [`LinuxApp`], implemented by [`Glx`] and [`Egl`].
+/// Every method is called on the thread the host renders on.
+pub trait Platform: Sized {
+    /// The arguments of the swap hook, which name the display and the surface being presented.
+    type Surface: Copy + PartialEq;
+    type Context: Copy;
+    /// Whatever was current before the app switched to its own context.
+    type Bindings;
+
+    /// Opens the window system libraries.
+    fn open() -> Result<Self>;
+
+    /// Loads OpenGL functions through the window system's library.
+    fn load_gl(&self) -> Result<()>;
+
+    /// Size of the surface in pixels.
+    fn surface_size(&self, surface: Self::Surface) -> Result<(u32, u32)>;
+
+    fn current_bindings(&self) -> Self::Bindings;
+
+    /// Makes `bindings` current again, or releases the app's context if nothing was current before.
+    fn restore_bindings(&self, surface: Self::Surface, bindings: &Self::Bindings) -> Result<()>;
+
+    /// Creates a context that can draw to `surface`, while the host's context is current.
+    fn create_context(&self, surface: Self::Surface) -> Result<Self::Context>;
+
+    fn make_current(&self, surface: Self::Surface, context: Self::Context) -> Result<()>;
+
+    fn destroy_context(&self, surface: Self::Surface, context: Self::Context) -> Result<()>;
+}
+
+/// Which context [`LinuxApp`] draws with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ContextMode {
+    /// A context of its own, created with the same config as the host's and made current for each frame.
+    /// The host's OpenGL state is untouched, but switching contexts costs a flush on some drivers.
+    #[default]
+    Dedicated,
+    /// The host's current context, with its state saved and restored by the renderer.
+    /// Requires the host to present with the same context every frame.
+    Host,
+}
+
+#[allow(clippy::type_complexity)]
+struct AppData<P: Platform, T, R> {
+    ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
+    platform: P,
+    surface: P::Surface,
+    /// `None` in [`ContextMode::Host`].
+    gl_context: Option<P::Context>,
+    renderer: R,
+    /// Size the renderer was last resized to.
+    renderer_size: Option<(u32, u32)>,
+    ctx: Context,
+    input_collector: XInputCollector,
+    key_lookup: XkbLookup,
+    start: Instant,
+    state: T,
+}
+
+/// Same as [`crate::OpenGLApp`], but for hooks of `glXSwapBuffers` ([`GlxApp`]) or `eglSwapBuffers` ([`EglApp`]).
+/// * [`Self::render`] - Should be called inside of the swap buffers hook.
+/// * [`Self::x_event`] - Should be called on each event the host reads for its window, e.g. from an `XNextEvent` hook.
+pub struct LinuxApp<P: Platform, T = (), R = Painter> {
+    data: Mutex<Option<AppData<P, T, R>>>,
+    ready: AtomicBool,
+}
+
+/// [`LinuxApp`] for `glXSwapBuffers(Display*, GLXDrawable)` hooks.
+pub type GlxApp<T = (), R = Painter> = LinuxApp<Glx, T, R>;
+
+/// [`LinuxApp`] for `eglSwapBuffers(EGLDisplay, EGLSurface)` hooks.
+pub type EglApp<T = (), R = Painter> = LinuxApp<Egl, T, R>;
+
+impl<P: Platform, T, R> Default for LinuxApp<P, T, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Platform, T, R> LinuxApp<P, T, R> {
+    /// Creates new [`LinuxApp`] in const context. You are supposed to create a single static item to store the application state.
+    pub const fn new() -> Self {
+        Self {
+            data: Mutex::new(None),
+            ready: AtomicBool::new(false),
+        }
+    }
+
+    /// Checks if the app is ready to draw and if it's safe to invoke `render`.
+    /// `true` means that you have already called an `init_*` on the application and not [`Self::destroy`].
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    #[cfg(feature = "parking-lot")]
+    pub fn lock_state(&self) -> MappedMutexGuard<'_, parking_lot::RawMutex, T> {
+        MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
+    }
+
+    #[cfg(feature = "spin-lock")]
+    pub fn lock_state(&self) -> MappedMutexGuard<'_, spin::mutex::Mutex<()>, T> {
+        MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
+    }
+
+    fn lock_data(&self) -> impl DerefMut<Target = AppData<P, T, R>> + '_ {
+        MutexGuard::map(self.data.lock(), |app| {
+            expect!(app.as_mut(), "You need to call init first")
+        })
+    }
+}
+
+impl<P: Platform, T, R: Renderer> LinuxApp<P, T, R> {
+    /// Initializes application and state with a custom renderer. You should call this only once,
+    /// from the swap hook or anywhere else the host's context is current.
+    /// `create_renderer` runs with the context selected by `mode` current.
+    /// On error nothing is kept and the app stays uninitialized.
+    pub fn init_with_renderer(
+        &self,
+        surface: P::Surface,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+        mode: ContextMode,
+        create_renderer: impl FnOnce(&P) -> Result<R>,
+    ) -> Result<()> {
+        let mut data = self.data.lock();
+        if data.is_some() {
+            panic_msg!("You must call init only once");
+        }
+
+        let platform = P::open()?;
+        let (gl_context, renderer) = match mode {
+            ContextMode::Host => (None, create_renderer(&platform)?),
+            ContextMode::Dedicated => {
+                let gl_context = platform.create_context(surface)?;
+                let bindings = platform.current_bindings();
+
+                let renderer = platform
+                    .make_current(surface, gl_context)
+                    .and_then(|()| create_renderer(&platform));
+                let restored = platform.restore_bindings(surface, &bindings);
+
+                match renderer.and_then(|renderer| restored.map(|()| renderer)) {
+                    Ok(renderer) => (Some(gl_context), renderer),
+                    Err(err) => {
+                        let _ = platform.destroy_context(surface, gl_context);
+                        return Err(err);
+                    }
+                }
+            }
+        };
+
+        *data = Some(AppData {
+            ui: Box::new(ui),
+            platform,
+            surface,
+            gl_context,
+            renderer,
+            renderer_size: None,
+            ctx: context,
+            input_collector: XInputCollector::new(),
+            key_lookup: XkbLookup::new(),
+            start: Instant::now(),
+            state,
+        });
+        self.ready.store(true, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Present call. Should be called once per original present call, before or inside of hook.
+    /// An error means this frame was skipped, the host's context is current again and later frames may still succeed.
+    pub fn render(&self, surface: P::Surface) -> Result<()> {
+        let this = &mut *self.lock_data();
+        this.surface = surface;
+
+        let Some(gl_context) = this.gl_context else {
+            return Self::paint_frame(this);
+        };
+
+        let bindings = this.platform.current_bindings();
+        this.platform.make_current(surface, gl_context)?;
+
+        let result = Self::paint_frame(this);
+
+        this.platform.restore_bindings(surface, &bindings)?;
+        result
+    }
+
+    /// Feeds an event of the host's window to egui.
+    /// Returns `true` if egui wants the keyboard or pointer input the event carries, in which case the host should not see it.
+    pub fn x_event(&self, event: &XEvent) -> bool {
+        let this = &mut *self.lock_data();
+        match this.input_collector.process(event, &this.key_lookup) {
+            XInputResult::Key => this.ctx.wants_keyboard_input(),
+            XInputResult::Pointer => this.ctx.wants_pointer_input(),
+            XInputResult::Focus | XInputResult::Unknown => false,
+        }
+    }
+
+    /// Destroys the renderer and the app's own context, e.g. before unhooking.
+    /// Must not run concurrently with [`Self::render`]. Afterwards the app is no longer ready until it is initialized again.
+    pub fn destroy(&self) -> Result<()> {
+        let Some(mut data) = self.data.lock().take() else {
+            return Ok(());
+        };
+        self.ready.store(false, Ordering::Release);
+
+        let Some(gl_context) = data.gl_context else {
+            data.renderer.destroy();
+            return Ok(());
+        };
+
+        let platform = &data.platform;
+        let bindings = platform.current_bindings();
+        // Destroying the context frees whatever the renderer could not, so that still happens on failure.
+        let current = platform.make_current(data.surface, gl_context);
+        if current.is_ok() {
+            data.renderer.destroy();
+        }
+        let restored = platform.restore_bindings(data.surface, &bindings);
+        let destroyed = platform.destroy_context(data.surface, gl_context);
+
+        current.and(restored).and(destroyed)
+    }
+
+    /// Runs the ui and draws it with the app's context current.
+    fn paint_frame(this: &mut AppData<P, T, R>) -> Result<()> {
+        let size = this.platform.surface_size(this.surface)?;
+        let input = this.input_collector.collect_input(
+            &this.ctx,
+            Rect::from_min_size(pos2(0.0, 0.0), vec2(size.0 as f32, size.1 as f32)),
+            this.start.elapsed().as_secs_f64(),
+        );
+
+        let output = this.ctx.run(input, |ctx| {
+            (this.ui)(ctx, &mut this.state);
+        });
+
+        if output.shapes.is_empty() {
+            return Ok(());
+        }
+
+        if this.renderer_size != Some(size) {
+            this.renderer.resize(size);
+            this.renderer_size = Some(size);
+        }
+
+        let clipped_shapes = this.ctx.tessellate(output.shapes, 1.);
+        this.renderer
+            .render_frame(1.0, &clipped_shapes, &output.textures_delta)
+    }
+}
+
+impl<P: Platform, T> LinuxApp<P, T> {
+    /// Initializes application and state, drawing with the [`Painter`] in the given kind of context. You should call this only once!
+    pub fn init_with_mode(
+        &self,
+        surface: P::Surface,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+        mode: ContextMode,
+    ) -> Result<()> {
+        self.init_with_renderer(surface, ui, state, context, mode, |platform| {
+            platform.load_gl()?;
+            Painter::new(StateBackup::default())
+        })
+    }
+
+    /// Initializes application and state in a [`ContextMode::Dedicated`] context. You should call this only once!
+    #[inline]
+    pub fn init_with_state_context(
+        &self,
+        surface: P::Surface,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+    ) -> Result<()> {
+        self.init_with_mode(surface, ui, state, context, ContextMode::Dedicated)
+    }
+
+    /// Initializes application and state. Sets egui's context to default value. You should call this only once!
+    #[inline]
+    pub fn init_with_state(
+        &self,
+        surface: P::Surface,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+    ) -> Result<()> {
+        self.init_with_state_context(surface, ui, state, Context::default())
+    }
+
+    /// Selects how much of the host's OpenGL state is saved and restored around each [`Self::render`].
+    /// Defaults to [`StateBackup::Full`], which [`ContextMode::Host`] relies on.
+    pub fn set_state_backup(&self, state_backup: StateBackup) {
+        self.lock_data().renderer.set_state_backup(state_backup);
+    }
+
+    /// Draw call and upload counters for the most recently rendered frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.lock_data().renderer.frame_stats()
+    }
+}
+
+impl<P: Platform, T: Default> LinuxApp<P, T> {
+    /// Initializes application and sets the state to its default value. You should call this only once!
+    #[inline]
+    pub fn init_default(
+        &self,
+        surface: P::Surface,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+    ) -> Result<()> {
+        self.init_with_state(surface, ui, T::default())
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{Error, Result};
+use std::ffi::{c_void, CString};
+
+/// Resolves OpenGL function pointers by name, e.g. `glCreateShader`.
+///
+/// Built in are [`WglLoader`] (Windows), [`GlxLoader`] (X11) and [`EglLoader`] (Mesa, ANGLE, Android),
+/// and any `Fn(&str) -> *const c_void` closure works too, e.g. `glfw::Window::get_proc_address`.
+pub trait GlLoader {
+    /// Address of the function, null if it does not exist.
+    fn get_proc_address(&self, name: &str) -> *const c_void;
+}
+
+impl<F: Fn(&str) -> *const c_void> GlLoader for F {
+    fn get_proc_address(&self, name: &str) -> *const c_void {
+        self(name)
+    }
+}
+
+type IsLoaded = fn() -> bool;
+
+/// Functions the painter cannot work without. Everything else is optional and checked with `is_loaded` before use.
+///
+/// Vertex array objects are core since GL 3.0 and GLES 3.0. A GLES 2.0 context needs `OES_vertex_array_object`,
+/// whose `glBindVertexArrayOES` and friends the `gl` crate loads as fallbacks of the core names.
+const REQUIRED_FUNCTIONS: &[(&str, IsLoaded)] = &[
+    ("glActiveTexture", gl::ActiveTexture::is_loaded),
+    ("glAttachShader", gl::AttachShader::is_loaded),
+    ("glBindBuffer", gl::BindBuffer::is_loaded),
+    ("glBindTexture", gl::BindTexture::is_loaded),
+    ("glBindVertexArray", gl::BindVertexArray::is_loaded),
+    ("glBlendEquation", gl::BlendEquation::is_loaded),
+    (
+        "glBlendEquationSeparate",
+        gl::BlendEquationSeparate::is_loaded,
+    ),
+    ("glBlendFunc", gl::BlendFunc::is_loaded),
+    ("glBlendFuncSeparate", gl::BlendFuncSeparate::is_loaded),
+    ("glBufferData", gl::BufferData::is_loaded),
+    ("glBufferSubData", gl::BufferSubData::is_loaded),
+    ("glColorMask", gl::ColorMask::is_loaded),
+    ("glCompileShader", gl::CompileShader::is_loaded),
+    ("glCreateProgram", gl::CreateProgram::is_loaded),
+    ("glCreateShader", gl::CreateShader::is_loaded),
+    ("glDeleteBuffers", gl::DeleteBuffers::is_loaded),
+    ("glDeleteProgram", gl::DeleteProgram::is_loaded),
+    ("glDeleteShader", gl::DeleteShader::is_loaded),
+    ("glDeleteTextures", gl::DeleteTextures::is_loaded),
+    ("glDeleteVertexArrays", gl::DeleteVertexArrays::is_loaded),
+    ("glDisable", gl::Disable::is_loaded),
+    ("glDrawElements", gl::DrawElements::is_loaded),
+    ("glEnable", gl::Enable::is_loaded),
+    (
+        "glEnableVertexAttribArray",
+        gl::EnableVertexAttribArray::is_loaded,
+    ),
+    ("glGenBuffers", gl::GenBuffers::is_loaded),
+    ("glGenTextures", gl::GenTextures::is_loaded),
+    ("glGenVertexArrays", gl::GenVertexArrays::is_loaded),
+    ("glGetAttribLocation", gl::GetAttribLocation::is_loaded),
+    ("glGetError", gl::GetError::is_loaded),
+    ("glGetIntegerv", gl::GetIntegerv::is_loaded),
+    ("glGetProgramInfoLog", gl::GetProgramInfoLog::is_loaded),
+    ("glGetProgramiv", gl::GetProgramiv::is_loaded),
+    ("glGetShaderInfoLog", gl::GetShaderInfoLog::is_loaded),
+    ("glGetShaderiv", gl::GetShaderiv::is_loaded),
+    ("glGetString", gl::GetString::is_loaded),
+    ("glGetUniformLocation", gl::GetUniformLocation::is_loaded),
+    ("glIsEnabled", gl::IsEnabled::is_loaded),
+    ("glLinkProgram", gl::LinkProgram::is_loaded),
+    ("glPixelStorei", gl::PixelStorei::is_loaded),
+    ("glScissor", gl::Scissor::is_loaded),
+    ("glShaderSource", gl::ShaderSource::is_loaded),
+    ("glTexImage2D", gl::TexImage2D::is_loaded),
+    ("glTexParameteri", gl::TexParameteri::is_loaded),
+    ("glTexSubImage2D", gl::TexSubImage2D::is_loaded),
+    ("glUniform1i", gl::Uniform1i::is_loaded),
+    ("glUniform2f", gl::Uniform2f::is_loaded),
+    ("glUseProgram", gl::UseProgram::is_loaded),
+    ("glVertexAttribPointer", gl::VertexAttribPointer::is_loaded),
+    ("glViewport", gl::Viewport::is_loaded),
+];
+
+/// Required functions that are still unresolved after loading.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    pub missing: Vec<&'static str>,
+}
+
+impl LoadReport {
+    /// Checks the functions currently loaded into the `gl` crate, however they got there.
+    pub fn current() -> Self {
+        Self {
+            missing: REQUIRED_FUNCTIONS
+                .iter()
+                .filter(|(_, is_loaded)| !is_loaded())
+                .map(|&(name, _)| name)
+                .collect(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<()> {
+        if self.is_complete() {
+            Ok(())
+        } else {
+            Err(Error::GlLoad {
+                missing: self.missing,
+            })
+        }
+    }
+}
+
+/// Loads every function of the `gl` crate through `loader` and reports the required ones that did not resolve.
+pub fn load_gl(loader: &impl GlLoader) -> LoadReport {
+    gl::load_with(|name| loader.get_proc_address(name));
+    LoadReport::current()
+}
+
+/// A shared library kept open for as long as the loader using it lives.
+pub(crate) struct Library(*mut c_void);
+
+impl Library {
+    /// Opens the first library of `names` that exists.
+    pub(crate) fn open(names: &[&str]) -> Option<Self> {
+        names.iter().find_map(|name| {
+            let name = CString::new(*name).ok()?;
+            let handle = unsafe { Self::open_raw(&name) };
+            (!handle.is_null()).then_some(Self(handle))
+        })
+    }
+
+    #[cfg(unix)]
+    unsafe fn open_raw(name: &CString) -> *mut c_void {
+        libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL)
+    }
+
+    #[cfg(windows)]
+    unsafe fn open_raw(name: &CString) -> *mut c_void {
+        use windows::{core::PCSTR, Win32::System::LibraryLoader::LoadLibraryA};
+
+        LoadLibraryA(PCSTR::from_raw(name.as_ptr() as *const u8))
+            .map(|module| module.0)
+            .unwrap_or(std::ptr::null_mut())
+    }
+
+    pub(crate) fn symbol(&self, name: &str) -> *const c_void {
+        let Ok(name) = CString::new(name) else {
+            return std::ptr::null();
+        };
+        unsafe { Self::symbol_raw(self.0, &name) }
+    }
+
+    #[cfg(unix)]
+    unsafe fn symbol_raw(handle: *mut c_void, name: &CString) -> *const c_void {
+        libc::dlsym(handle, name.as_ptr())
+    }
+
+    #[cfg(windows)]
+    unsafe fn symbol_raw(handle: *mut c_void, name: &CString) -> *const c_void {
+        use windows::{
+            core::PCSTR,
+            Win32::{Foundation::HMODULE, System::LibraryLoader::GetProcAddress},
+        };
+
+        GetProcAddress(HMODULE(handle), PCSTR::from_raw(name.as_ptr() as *const u8))
+            .map_or(std::ptr::null(), |f| f as *const c_void)
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::dlclose(self.0);
+        }
+
+        #[cfg(windows)]
+        unsafe {
+            use windows::Win32::Foundation::{FreeLibrary, HMODULE};
+
+            let _ = FreeLibrary(HMODULE(self.0));
+        }
+    }
+}
+
+type GetProcAddressFn = unsafe extern "system" fn(*const std::ffi::c_char) -> *const c_void;
+
+/// Resolves through `opengl32.dll` exports first and `wglGetProcAddress` for everything past GL 1.1,
+/// which needs a current context.
+#[cfg(windows)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WglLoader;
+
+#[cfg(windows)]
+impl GlLoader for WglLoader {
+    fn get_proc_address(&self, name: &str) -> *const c_void {
+        unsafe { crate::utils::get_proc_address(name) as *const c_void }
+    }
+}
+
+/// Resolves through `libGL.so.1`, falling back to `glXGetProcAddressARB` for functions it does not export.
+///
+/// GLX hands out a stub for any name, so unlike the other loaders a non-null result does not prove
+/// the driver implements the function.
+#[cfg(unix)]
+pub struct GlxLoader {
+    library: Library,
+    get_proc_address: Option<GetProcAddressFn>,
+}
+
+#[cfg(unix)]
+impl GlxLoader {
+    /// `None` if no `libGL` can be opened.
+    pub fn new() -> Option<Self> {
+        let library = Library::open(&["libGL.so.1", "libGL.so"])?;
+        let get_proc_address = ["glXGetProcAddressARB", "glXGetProcAddress"]
+            .into_iter()
+            .map(|name| library.symbol(name))
+            .find(|f| !f.is_null())
+            .map(|f| unsafe { std::mem::transmute::<*const c_void, GetProcAddressFn>(f) });
+
+        Some(Self {
+            library,
+            get_proc_address,
+        })
+    }
+}
+
+#[cfg(unix)]
+impl GlLoader for GlxLoader {
+    fn get_proc_address(&self, name: &str) -> *const c_void {
+        let f = self.library.symbol(name);
+        if !f.is_null() {
+            return f;
+        }
+
+        match (self.get_proc_address, CString::new(name)) {
+            (Some(get_proc_address), Ok(name)) => unsafe { get_proc_address(name.as_ptr()) },
+            _ => std::ptr::null(),
+        }
+    }
+}
+
+/// Resolves through `eglGetProcAddress`, falling back to the exports of the client API library,
+/// since EGL before 1.5 only returns extension functions.
+pub struct EglLoader {
+    _egl: Library,
+    get_proc_address: GetProcAddressFn,
+    client: Option<Library>,
+}
+
+impl EglLoader {
+    #[cfg(unix)]
+    const EGL: &'static [&'static str] = &["libEGL.so.1", "libEGL.so"];
+    #[cfg(unix)]
+    const CLIENT: &'static [&'static str] = &["libGLESv2.so.2", "libGLESv2.so", "libGL.so.1"];
+
+    #[cfg(windows)]
+    const EGL: &'static [&'static str] = &["libEGL.dll"];
+    #[cfg(windows)]
+    const CLIENT: &'static [&'static str] = &["libGLESv2.dll", "opengl32.dll"];
+
+    /// `None` if no EGL library can be opened.
+    pub fn new() -> Option<Self> {
+        let egl = Library::open(Self::EGL)?;
+        let get_proc_address = egl.symbol("eglGetProcAddress");
+        if get_proc_address.is_null() {
+            return None;
+        }
+
+        Some(Self {
+            get_proc_address: unsafe {
+                std::mem::transmute::<*const c_void, GetProcAddressFn>(get_proc_address)
+            },
+            _egl: egl,
+            client: Library::open(Self::CLIENT),
+        })
+    }
+}
+
+impl GlLoader for EglLoader {
+    fn get_proc_address(&self, name: &str) -> *const c_void {
+        let f = match CString::new(name) {
+            Ok(name) => unsafe { (self.get_proc_address)(name.as_ptr()) },
+            Err(_) => return std::ptr::null(),
+        };
+        if !f.is_null() {
+            return f;
+        }
+
+        self.client
+            .as_ref()
+            .map_or(std::ptr::null(), |client| client.symbol(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_report() {
+        // Fake addresses are never called, only checked for being non-null.
+        let dangling = std::ptr::NonNull::<c_void>::dangling().as_ptr() as *const c_void;
+
+        let report = load_gl(&|_: &str| std::ptr::null());
+        assert_eq!(report.missing.len(), REQUIRED_FUNCTIONS.len());
+
+        let missing = ["glLinkProgram", "glTexSubImage2D"];
+        // The `gl` crate also asks for `ARB`/`EXT` aliases when a name does not resolve.
+        let report = load_gl(&|name: &str| {
+            if missing.iter().any(|missing| name.starts_with(missing)) {
+                std::ptr::null()
+            } else {
+                dangling
+            }
+        });
+        assert_eq!(report.missing, missing);
+        assert!(matches!(
+            report.into_result(),
+            Err(Error::GlLoad { missing: m }) if m == missing
+        ));
+
+        // A GLES 2.0 driver only has the `OES_vertex_array_object` names.
+        let report = load_gl(&|name: &str| {
+            if name.contains("VertexArray") && !name.ends_with("OES") {
+                std::ptr::null()
+            } else {
+                dangling
+            }
+        });
+        assert!(report.is_complete());
+
+        let report = load_gl(&|_: &str| dangling);
+        assert!(report.is_complete());
+        assert!(report.into_result().is_ok());
+    }
+}
@@ -1,11 +1,51 @@
-use crate::shader;
+use crate::{
+    batch::{DrawCommand, FrameBatch, FrameStats, MeshDraw},
+    gl_state::{Gl, GlFunctions, GlStateSnapshot, StateBackup},
+    loader::LoadReport,
+    renderer::set_each,
+    shader::{self, gl_string, ContextInfo, GlslVersion},
+    Error, Renderer, Result,
+};
 use egui::{
     emath::Rect,
-    epaint::{Mesh, Primitive},
-    Color32, TextureFilter,
+    epaint::{ImageDelta, Mesh, PaintCallbackInfo, Primitive, Vertex},
+    Color32, TextureFilter, TextureOptions, TextureWrapMode,
 };
 use gl::types::*;
-use std::ffi::{c_void, CString};
+use std::{
+    borrow::Cow,
+    ffi::{c_void, CStr},
+    mem::{offset_of, size_of},
+};
+
+/// Custom OpenGL drawing inside an egui layer.
+///
+/// Wrap it in an [`egui::PaintCallback`] and add it to a [`egui::Painter`].
+/// The viewport is set to the callback rect and the scissor box to its clip rect before it runs,
+/// and the painter's state is saved before and restored after it, so the callback can bind whatever it needs.
+/// Callbacks of any other type are skipped.
+///
+/// ```ignore
+/// let callback = egui::PaintCallback {
+///     rect,
+///     callback: std::sync::Arc::new(egui_opengl_internal::CallbackFn::new(|info, painter| unsafe {
+///         gl::UseProgram(my_program);
+///         gl::DrawArrays(gl::TRIANGLES, 0, 3);
+///     })),
+/// };
+/// ui.painter().add(callback);
+/// ```
+#[allow(clippy::type_complexity)]
+pub struct CallbackFn {
+    f: Box<dyn Fn(PaintCallbackInfo, &Painter) + Sync + Send>,
+}
+
+impl CallbackFn {
+    pub fn new<F: Fn(PaintCallbackInfo, &Painter) + Sync + Send + 'static>(callback: F) -> Self {
+        let f = Box::new(callback);
+        CallbackFn { f }
+    }
+}
 
 pub struct UserTexture {
     size: (usize, usize),
@@ -16,9 +56,14 @@ pub struct UserTexture {
     /// Lazily uploaded
     gl_texture_id: Option<GLuint>,
 
-    /// For user textures there is a choice between
-    /// Linear (default) and Nearest.
-    filtering: TextureFilter,
+    /// Filtering and wrapping requested by egui.
+    options: TextureOptions,
+
+    /// Whether a mipmap chain should be generated. Only used for images, the font atlas is drawn 1:1.
+    mipmaps: bool,
+
+    /// Whether the uploaded texture actually has mipmaps, which need regenerating after partial updates.
+    has_mipmaps: bool,
 
     /// User textures can be modified and this flag
     /// is used to indicate if pixel data for the
@@ -27,6 +72,18 @@ pub struct UserTexture {
 }
 
 impl UserTexture {
+    fn new(size: (usize, usize), pixels: Vec<u8>, options: TextureOptions, mipmaps: bool) -> Self {
+        Self {
+            size,
+            pixels,
+            gl_texture_id: None,
+            options,
+            mipmaps,
+            has_mipmaps: false,
+            dirty: true,
+        }
+    }
+
     pub fn update_texture_part(
         &mut self,
         x_offset: i32,
@@ -38,21 +95,32 @@ impl UserTexture {
         assert!(x_offset + width <= self.size.0 as _);
         assert!(y_offset + height <= self.size.1 as _);
 
-        unsafe {
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_A, gl::RED as _);
+        match self.gl_texture_id {
+            // Not uploaded yet, patch the pending pixels instead.
+            None => {
+                let row_len = width as usize * 4;
+                for (row, src) in bytes.chunks_exact(row_len).enumerate() {
+                    let start = ((y_offset as usize + row) * self.size.0 + x_offset as usize) * 4;
+                    self.pixels[start..start + row_len].copy_from_slice(src);
+                }
+            }
 
-            gl::TexSubImage2D(
-                gl::TEXTURE_2D,
-                0,
-                x_offset as _,
-                y_offset as _,
-                width as _,
-                height as _,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                bytes.as_ptr() as *const _,
-            );
+            // The unpack state was reset before the textures delta, see `reset_unpack_state`.
+            Some(texture) => unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+
+                gl::TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    x_offset as _,
+                    y_offset as _,
+                    width as _,
+                    height as _,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    bytes.as_ptr() as *const _,
+                );
+            },
         }
 
         self.dirty = true;
@@ -62,7 +130,9 @@ impl UserTexture {
         Self {
             size: (0, 0),
             gl_texture_id: Some(id),
-            filtering: TextureFilter::Linear,
+            options: TextureOptions::LINEAR,
+            mipmaps: false,
+            has_mipmaps: false,
             dirty: false,
             pixels: Vec::with_capacity(0),
         }
@@ -77,68 +147,520 @@ impl UserTexture {
     }
 }
 
-pub struct Painter {
-    program: GLuint,
+fn gl_wrap_mode(wrap_mode: TextureWrapMode) -> GLenum {
+    match wrap_mode {
+        TextureWrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+        TextureWrapMode::Repeat => gl::REPEAT,
+        TextureWrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+    }
+}
+
+/// GLES2 treats non-power-of-two textures with mipmaps or a repeating wrap mode as incomplete, which samples black.
+fn npot_complete(gles2: bool, (w, h): (usize, usize)) -> bool {
+    !gles2 || (w.is_power_of_two() && h.is_power_of_two())
+}
+
+/// Falls back to `CLAMP_TO_EDGE` where repeating would make the texture incomplete.
+fn texture_wrap_mode(wrap_mode: TextureWrapMode, gles2: bool, size: (usize, usize)) -> GLenum {
+    if npot_complete(gles2, size) {
+        gl_wrap_mode(wrap_mode)
+    } else {
+        gl::CLAMP_TO_EDGE
+    }
+}
+
+fn gl_mag_filter(filter: TextureFilter) -> GLenum {
+    match filter {
+        TextureFilter::Nearest => gl::NEAREST,
+        TextureFilter::Linear => gl::LINEAR,
+    }
+}
+
+/// With mipmaps, levels are picked and blended with the same filter as the texels.
+fn gl_min_filter(filter: TextureFilter, mipmaps: bool) -> GLenum {
+    match (filter, mipmaps) {
+        (TextureFilter::Nearest, false) => gl::NEAREST,
+        (TextureFilter::Linear, false) => gl::LINEAR,
+        (TextureFilter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+/// Index buffer contents in the type passed to `glDrawElements`.
+enum Indices<'a> {
+    U16(Cow<'a, [u16]>),
+    U32(&'a [u32]),
+}
+
+impl Indices<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    fn gl_type(&self) -> GLenum {
+        match self {
+            Indices::U16(_) => gl::UNSIGNED_SHORT,
+            Indices::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// Part of a mesh that can be drawn with a single `glDrawElements` call.
+struct DrawChunk<'a> {
+    vertices: Cow<'a, [Vertex]>,
+    indices: Indices<'a>,
+}
+
+/// Borrows the mesh as-is when 32-bit indices can be drawn.
+/// Otherwise narrows the indices to `u16` in `scratch`, splitting meshes with too many vertices to be addressed that way.
+fn prepare_indices<'a>(
+    mesh: &'a Mesh,
+    u32_indices: bool,
+    scratch: &'a mut Vec<u16>,
+) -> Vec<DrawChunk<'a>> {
+    if u32_indices {
+        return vec![DrawChunk {
+            vertices: Cow::Borrowed(&mesh.vertices),
+            indices: Indices::U32(&mesh.indices),
+        }];
+    }
+
+    if mesh.vertices.len() <= u16::MAX as usize {
+        scratch.clear();
+        scratch.extend(mesh.indices.iter().map(|&idx| idx as u16));
+        return vec![DrawChunk {
+            vertices: Cow::Borrowed(&mesh.vertices),
+            indices: Indices::U16(Cow::Borrowed(scratch)),
+        }];
+    }
+
+    mesh.clone()
+        .split_to_u16()
+        .into_iter()
+        .map(|mesh16| DrawChunk {
+            vertices: Cow::Owned(mesh16.vertices),
+            indices: Indices::U16(Cow::Owned(mesh16.indices)),
+        })
+        .collect()
+}
+
+/// Whether the draw framebuffer's color buffer is sRGB-encoded, so `FRAMEBUFFER_SRGB` has an effect.
+fn framebuffer_is_srgb() -> bool {
+    if !gl::GetFramebufferAttachmentParameteriv::is_loaded() {
+        return false;
+    }
+
+    unsafe {
+        let mut framebuffer = 0;
+        gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+        let mut draw_buffer = 0;
+        gl::GetIntegerv(gl::DRAW_BUFFER, &mut draw_buffer);
+
+        // The default framebuffer has to be asked about a specific left/right buffer.
+        let attachment = match draw_buffer as GLenum {
+            gl::BACK if framebuffer == 0 => gl::BACK_LEFT,
+            gl::FRONT if framebuffer == 0 => gl::FRONT_LEFT,
+            gl::NONE if framebuffer == 0 => gl::BACK_LEFT,
+            gl::NONE => gl::COLOR_ATTACHMENT0,
+            attachment => attachment,
+        };
+
+        let mut encoding = 0;
+        gl::GetFramebufferAttachmentParameteriv(
+            gl::DRAW_FRAMEBUFFER,
+            attachment,
+            gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+            &mut encoding,
+        );
+        encoding as GLenum == gl::SRGB
+    }
+}
+
+/// Desktop GL and GLES3 always accept `UNSIGNED_INT` indices, GLES2 only with an extension.
+fn supports_u32_indices(gles2: bool) -> bool {
+    !gles2
+        || gl_string(gl::EXTENSIONS)
+            .split_ascii_whitespace()
+            .any(|ext| ext == "GL_OES_element_index_uint")
+}
+
+/// The vertex array and the two buffers it streams every mesh through.
+struct MeshBuffers {
     vertex_array: GLuint,
+    vertex_buffer: GLuint,
     index_buffer: GLuint,
-    pos_buffer: GLuint,
-    tc_buffer: GLuint,
-    color_buffer: GLuint,
-    textures: std::collections::HashMap<egui::TextureId, UserTexture>,
+    /// Allocated sizes in bytes, grown to the next power of two when a frame does not fit.
+    vertex_capacity: usize,
+    index_capacity: usize,
 }
 
-impl Painter {
-    pub fn new() -> Painter {
-        let vs =
-            shader::Shader::compile_shader(include_str!("shader/vertex.vert"), gl::VERTEX_SHADER);
-        let fs = shader::Shader::compile_shader(
-            include_str!("shader/fragment.frag"),
-            gl::FRAGMENT_SHADER,
-        );
+/// Orphans `buffer` and writes `data` to its start, so the driver never waits on the previous frame's draws.
+unsafe fn stream_buffer<T>(target: GLenum, buffer: GLuint, capacity: &mut usize, data: &[T]) {
+    let size = std::mem::size_of_val(data);
+    if size > *capacity {
+        *capacity = size.next_power_of_two();
+    }
 
-        let program = shader::Shader::link_program(vs, fs);
+    gl::BindBuffer(target, buffer);
+    gl::BufferData(
+        target,
+        *capacity as GLsizeiptr,
+        core::ptr::null(),
+        gl::STREAM_DRAW,
+    );
+    gl::BufferSubData(
+        target,
+        0,
+        size as GLsizeiptr,
+        data.as_ptr() as *const GLvoid,
+    );
+}
+
+impl MeshBuffers {
+    /// Creates the buffers and records the interleaved [`Vertex`] layout in the vertex array,
+    /// so drawing only has to bind it and upload data.
+    fn new(program: GLuint) -> Result<Self> {
+        let attrib_location = |name: &CStr| {
+            let location = unsafe { gl::GetAttribLocation(program, name.as_ptr()) };
+            if location < 0 {
+                return Err(Error::Link {
+                    log: format!("attribute {name:?} not found in program"),
+                });
+            }
+            Ok(location as GLuint)
+        };
+        let a_pos = attrib_location(c"a_pos")?;
+        let a_tc = attrib_location(c"a_tc")?;
+        let a_srgba = attrib_location(c"a_srgba")?;
 
         let mut vertex_array = 0;
+        let mut vertex_buffer = 0;
         let mut index_buffer = 0;
-        let mut pos_buffer = 0;
-        let mut tc_buffer = 0;
-        let mut color_buffer = 0;
+        let stride = size_of::<Vertex>() as GLsizei;
         unsafe {
             gl::GenVertexArrays(1, &mut vertex_array);
-            gl::BindVertexArray(vertex_array);
+            gl::GenBuffers(1, &mut vertex_buffer);
             gl::GenBuffers(1, &mut index_buffer);
-            gl::GenBuffers(1, &mut pos_buffer);
-            gl::GenBuffers(1, &mut tc_buffer);
-            gl::GenBuffers(1, &mut color_buffer);
+
+            gl::BindVertexArray(vertex_array);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
+
+            gl::VertexAttribPointer(
+                a_pos,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                offset_of!(Vertex, pos) as *const _,
+            );
+            gl::EnableVertexAttribArray(a_pos);
+
+            gl::VertexAttribPointer(
+                a_tc,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                offset_of!(Vertex, uv) as *const _,
+            );
+            gl::EnableVertexAttribArray(a_tc);
+
+            // The shader expects 0-255 sRGB values, so the bytes are not normalized.
+            gl::VertexAttribPointer(
+                a_srgba,
+                4,
+                gl::UNSIGNED_BYTE,
+                gl::FALSE,
+                stride,
+                offset_of!(Vertex, color) as *const _,
+            );
+            gl::EnableVertexAttribArray(a_srgba);
+
+            gl::BindVertexArray(0);
         }
 
-        Painter {
-            program,
+        Ok(Self {
             vertex_array,
+            vertex_buffer,
             index_buffer,
-            pos_buffer,
-            tc_buffer,
-            color_buffer,
-            textures: Default::default(),
+            vertex_capacity: 0,
+            index_capacity: 0,
+        })
+    }
+
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vertex_array);
+            gl::DeleteBuffers(1, &self.vertex_buffer);
+            gl::DeleteBuffers(1, &self.index_buffer);
         }
     }
 
+    /// Uploads a whole frame, to be drawn piecewise with [`Self::draw_range`].
+    fn upload(&mut self, vertices: &[Vertex], indices: &[u32]) {
+        unsafe {
+            gl::BindVertexArray(self.vertex_array);
+            stream_buffer(
+                gl::ARRAY_BUFFER,
+                self.vertex_buffer,
+                &mut self.vertex_capacity,
+                vertices,
+            );
+            stream_buffer(
+                gl::ELEMENT_ARRAY_BUFFER,
+                self.index_buffer,
+                &mut self.index_capacity,
+                indices,
+            );
+        }
+    }
+
+    fn draw_range(&self, draw: &MeshDraw, base_vertex: bool) {
+        let offset = (draw.first_index * size_of::<u32>()) as *const GLvoid;
+        unsafe {
+            gl::BindVertexArray(self.vertex_array);
+            if base_vertex {
+                gl::DrawElementsBaseVertex(
+                    gl::TRIANGLES,
+                    draw.index_count as GLsizei,
+                    gl::UNSIGNED_INT,
+                    offset,
+                    draw.base_vertex as GLint,
+                );
+            } else {
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    draw.index_count as GLsizei,
+                    gl::UNSIGNED_INT,
+                    offset,
+                );
+            }
+        }
+    }
+
+    /// Uploads and draws a single chunk, for contexts that cannot batch with 32-bit indices.
+    fn draw(&mut self, chunk: &DrawChunk<'_>) {
+        unsafe {
+            gl::BindVertexArray(self.vertex_array);
+            stream_buffer(
+                gl::ARRAY_BUFFER,
+                self.vertex_buffer,
+                &mut self.vertex_capacity,
+                chunk.vertices.as_ref(),
+            );
+            match &chunk.indices {
+                Indices::U16(indices) => stream_buffer(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    self.index_buffer,
+                    &mut self.index_capacity,
+                    indices.as_ref(),
+                ),
+                Indices::U32(indices) => stream_buffer(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    self.index_buffer,
+                    &mut self.index_capacity,
+                    indices,
+                ),
+            }
+
+            gl::DrawElements(
+                gl::TRIANGLES,
+                chunk.indices.len() as GLsizei,
+                chunk.indices.gl_type(),
+                core::ptr::null(),
+            );
+        }
+    }
+}
+
+/// Unbinds the host's pixel unpack buffer and resets its unpack parameters, which would otherwise
+/// turn the pixel pointers of texture uploads into offsets or make them skip rows.
+pub(crate) unsafe fn reset_unpack_state(gl: &impl GlFunctions) {
+    if gl.has_unpack_buffer() {
+        gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, 0);
+    }
+    if gl.has_unpack_subimage() {
+        gl.pixel_store(gl::UNPACK_ROW_LENGTH, 0);
+        gl.pixel_store(gl::UNPACK_SKIP_PIXELS, 0);
+        gl.pixel_store(gl::UNPACK_SKIP_ROWS, 0);
+    }
+    gl.pixel_store(gl::UNPACK_ALIGNMENT, 1);
+}
+
+/// Replaces whatever the host left behind with the state the painter draws with.
+/// `framebuffer_srgb` is ignored on GLES, which lacks the capability.
+pub(crate) unsafe fn set_paint_state(
+    gl: &impl GlFunctions,
+    program: GLuint,
+    framebuffer_srgb: bool,
+) {
+    if gl.has_framebuffer_srgb() {
+        gl.set_enabled(gl::FRAMEBUFFER_SRGB, framebuffer_srgb);
+    }
+
+    gl.set_enabled(gl::SCISSOR_TEST, true);
+    gl.set_enabled(gl::BLEND, true);
+    gl.blend_equation_separate(gl::FUNC_ADD, gl::FUNC_ADD);
+    // premultiplied alpha
+    gl.blend_func_separate(
+        gl::ONE,
+        gl::ONE_MINUS_SRC_ALPHA,
+        gl::ONE,
+        gl::ONE_MINUS_SRC_ALPHA,
+    );
+    gl.use_program(program);
+    gl.active_texture(gl::TEXTURE0);
+
+    // The host may leave any of these set, which would hide or distort the ui.
+    gl.set_enabled(gl::DEPTH_TEST, false);
+    gl.set_enabled(gl::STENCIL_TEST, false);
+    gl.set_enabled(gl::CULL_FACE, false);
+    gl.color_mask([true; 4]);
+    if gl.has_polygon_mode() {
+        gl.polygon_mode(gl::FILL);
+    }
+    if gl.has_samplers() {
+        gl.bind_sampler(0, 0);
+    }
+}
+
+/// Texture deltas are produced by egui, but a malformed one must not take the host down.
+fn check_texel_count(id: egui::TextureId, [w, h]: [usize; 2], texels: usize) -> Result<()> {
+    if w * h == texels {
+        Ok(())
+    } else {
+        Err(Error::Texture {
+            id,
+            reason: format!("{w}x{h} image has {texels} texels"),
+        })
+    }
+}
+
+/// Rejects textures the driver cannot hold before uploading them, see `GL_MAX_TEXTURE_SIZE`.
+fn check_texture_size(id: egui::TextureId, (w, h): (usize, usize), max: usize) -> Result<()> {
+    if w <= max && h <= max {
+        Ok(())
+    } else {
+        Err(Error::Texture {
+            id,
+            reason: format!("{w}x{h} texture is larger than the maximum of {max}x{max}"),
+        })
+    }
+}
+
+pub struct Painter {
+    program: GLuint,
+    u_screen_size: GLint,
+    u_sampler: GLint,
+    buffers: MeshBuffers,
+    /// Reused across frames for narrowing indices to `u16`.
+    index_scratch: Vec<u16>,
+    textures: std::collections::HashMap<egui::TextureId, UserTexture>,
+    /// `GL_MAX_TEXTURE_SIZE`, the largest width and height of a texture.
+    max_texture_size: usize,
+    state_backup: StateBackup,
+    gl: Gl,
+    /// GLES2 lacks 32-bit indices without an extension and mipmaps for non-power-of-two textures.
+    gles2: bool,
+    /// Textures are stored as `SRGB8_ALPHA8` and the shader blends in gamma space,
+    /// otherwise the legacy shader decodes sRGB itself and relies on `FRAMEBUFFER_SRGB`.
+    srgb_textures: bool,
+    /// `false` on GLES2 contexts without `OES_element_index_uint`,
+    /// where meshes are split into chunks that fit 16-bit indices and drawn one by one.
+    u32_indices: bool,
+    /// Whether `glDrawElementsBaseVertex` is available, otherwise batched indices are made absolute.
+    base_vertex: bool,
+    batch: FrameBatch,
+    frame_stats: FrameStats,
+    /// Last size passed to [`Renderer::resize`].
+    screen_size: (u32, u32),
+}
+
+impl Painter {
+    pub fn new(state_backup: StateBackup) -> Result<Painter> {
+        LoadReport::current().into_result()?;
+
+        let context = ContextInfo::current();
+        let version = context.version;
+        let glsl = GlslVersion::select(context);
+        let (vertex_src, fragment_src) = glsl.sources();
+
+        let program = shader::Shader::build_program(&vertex_src, &fragment_src)?;
+
+        let (u_screen_size, u_sampler) = unsafe {
+            (
+                gl::GetUniformLocation(program, c"u_screen_size".as_ptr()),
+                gl::GetUniformLocation(program, c"u_sampler".as_ptr()),
+            )
+        };
+
+        let gles2 = version.es && version.major == 2;
+        let mut max_texture_size = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size) };
+
+        Ok(Painter {
+            program,
+            u_screen_size,
+            u_sampler,
+            buffers: MeshBuffers::new(program)?,
+            index_scratch: Vec::new(),
+            textures: Default::default(),
+            max_texture_size: max_texture_size as usize,
+            state_backup,
+            gl: Gl::current(version),
+            gles2,
+            srgb_textures: glsl.srgb_textures(),
+            u32_indices: supports_u32_indices(gles2),
+            base_vertex: gl::DrawElementsBaseVertex::is_loaded(),
+            batch: FrameBatch::default(),
+            frame_stats: FrameStats::default(),
+            screen_size: (0, 0),
+        })
+    }
+
+    /// Counters for the most recently painted frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    pub fn set_state_backup(&mut self, state_backup: StateBackup) {
+        self.state_backup = state_backup;
+    }
+
     pub fn paint_and_update_textures(
         &mut self,
         pixels_per_point: f32,
         clipped_primitives: &[egui::ClippedPrimitive],
         textures_delta: &egui::TexturesDelta,
         client_rect: &(u32, u32),
-    ) {
-        for (id, image_delta) in &textures_delta.set {
-            self.set_texture(*id, image_delta);
-        }
+    ) -> Result<()> {
+        let snapshot = match self.state_backup {
+            StateBackup::None => None,
+            StateBackup::Full => Some(unsafe { GlStateSnapshot::capture(&self.gl) }),
+        };
 
-        self.paint_primitives(pixels_per_point, clipped_primitives, client_rect);
+        unsafe { reset_unpack_state(&self.gl) };
+
+        // Every delta is applied and only painting is skipped after an error,
+        // textures are still freed and the host's state restored.
+        let result = set_each(&textures_delta.set, |id, image_delta| {
+            self.set_texture(id, image_delta)
+        })
+        .and_then(|()| self.paint_primitives(pixels_per_point, clipped_primitives, client_rect));
 
         for &id in &textures_delta.free {
             self.free_texture(id);
         }
+
+        if let Some(snapshot) = snapshot {
+            unsafe { snapshot.restore(&self.gl) };
+        }
+
+        result
     }
 
     /// Main entry-point for painting a frame.
@@ -147,44 +669,101 @@ impl Painter {
         pixels_per_point: f32,
         clipped_primitives: &[egui::ClippedPrimitive],
         client_rect: &(u32, u32),
-    ) {
-        self.upload_user_textures();
+    ) -> Result<()> {
+        self.upload_user_textures()?;
 
-        unsafe {
-            //Let OpenGL know we are dealing with SRGB colors so that it
-            //can do the blending correctly. Not setting the framebuffer
-            //leads to darkened, oversaturated colors.
-            gl::Enable(gl::FRAMEBUFFER_SRGB);
+        //Let OpenGL know we are dealing with SRGB colors so that it
+        //can do the blending correctly. Not setting the framebuffer
+        //leads to darkened, oversaturated colors.
+        //The sRGB shader writes gamma values itself, which egui expects to be blended as is.
+        let framebuffer_srgb =
+            self.gl.has_framebuffer_srgb() && !self.srgb_textures && framebuffer_is_srgb();
+        unsafe { set_paint_state(&self.gl, self.program, framebuffer_srgb) };
 
-            gl::Enable(gl::SCISSOR_TEST);
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA); // premultiplied alpha
-            gl::UseProgram(self.program);
-            gl::ActiveTexture(gl::TEXTURE0);
-        }
-
-        let u_screen_size = CString::new("u_screen_size").unwrap();
-        let u_screen_size_ptr = u_screen_size.as_ptr();
-        let u_screen_size_loc = unsafe { gl::GetUniformLocation(self.program, u_screen_size_ptr) };
         let screen_size_pixels = egui::vec2(client_rect.0 as f32, client_rect.1 as f32);
         let screen_size_points = screen_size_pixels / pixels_per_point;
 
         unsafe {
             gl::Uniform2f(
-                u_screen_size_loc,
+                self.u_screen_size,
                 screen_size_points.x,
                 screen_size_points.y,
             );
         }
 
-        let u_sampler = CString::new("u_sampler").unwrap();
-        let u_sampler_ptr = u_sampler.as_ptr();
-        let u_sampler_loc = unsafe { gl::GetUniformLocation(self.program, u_sampler_ptr) };
         unsafe {
-            gl::Uniform1i(u_sampler_loc, 0);
+            gl::Uniform1i(self.u_sampler, 0);
             gl::Viewport(0, 0, client_rect.0 as i32, client_rect.1 as i32);
         }
 
+        self.frame_stats = if self.u32_indices {
+            self.paint_batched(pixels_per_point, clipped_primitives, client_rect)
+        } else {
+            self.paint_unbatched(pixels_per_point, clipped_primitives, client_rect)
+        };
+
+        if self.gl.has_framebuffer_srgb() {
+            unsafe { self.gl.set_enabled(gl::FRAMEBUFFER_SRGB, false) };
+        }
+
+        Ok(())
+    }
+
+    /// Uploads the whole frame once and draws merged ranges of it.
+    fn paint_batched(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        client_rect: &(u32, u32),
+    ) -> FrameStats {
+        self.batch.build(clipped_primitives, self.base_vertex);
+        self.buffers
+            .upload(&self.batch.vertices, &self.batch.indices);
+
+        let mut stats = FrameStats {
+            meshes: self.batch.meshes,
+            vertices: self.batch.vertices.len(),
+            indices: self.batch.indices.len(),
+            ..Default::default()
+        };
+
+        for command in &self.batch.commands {
+            match *command {
+                DrawCommand::Mesh(draw) => {
+                    if self.bind_texture(draw.texture_id) {
+                        self.set_clip_rect(&draw.clip_rect, pixels_per_point, client_rect);
+                        self.buffers.draw_range(&draw, self.base_vertex);
+                        stats.draw_calls += 1;
+                    }
+                }
+
+                DrawCommand::Callback(i) => {
+                    let egui::ClippedPrimitive {
+                        clip_rect,
+                        primitive,
+                    } = &clipped_primitives[i];
+                    if let Primitive::Callback(callback) = primitive {
+                        if callback.rect.is_positive() {
+                            self.paint_callback(callback, clip_rect, pixels_per_point, client_rect);
+                            stats.callbacks += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Uploads and draws every mesh on its own.
+    fn paint_unbatched(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        client_rect: &(u32, u32),
+    ) -> FrameStats {
+        let mut stats = FrameStats::default();
+
         for egui::ClippedPrimitive {
             clip_rect,
             primitive,
@@ -192,21 +771,25 @@ impl Painter {
         {
             match primitive {
                 Primitive::Mesh(mesh) => {
-                    self.paint_mesh(mesh, clip_rect, pixels_per_point, client_rect);
-                    unsafe {
-                        gl::Disable(gl::SCISSOR_TEST);
+                    if !mesh.is_empty() {
+                        stats.meshes += 1;
+                        stats.vertices += mesh.vertices.len();
+                        stats.indices += mesh.indices.len();
+                        stats.draw_calls +=
+                            self.paint_mesh(mesh, clip_rect, pixels_per_point, client_rect);
                     }
                 }
 
-                Primitive::Callback(_) => {
-                    panic!("Custom rendering callbacks are not implemented in egui_glium");
+                Primitive::Callback(callback) => {
+                    if callback.rect.is_positive() {
+                        self.paint_callback(callback, clip_rect, pixels_per_point, client_rect);
+                        stats.callbacks += 1;
+                    }
                 }
             }
         }
 
-        unsafe {
-            gl::Disable(gl::FRAMEBUFFER_SRGB);
-        }
+        stats
     }
 
     pub fn new_opengl_texture(&mut self, openl_id: u32) -> egui::TextureId {
@@ -228,266 +811,197 @@ impl Painter {
         let pixels: Vec<u8> = srgba_pixels.iter().flat_map(|a| a.to_array()).collect();
         let id = egui::TextureId::User(self.textures.len() as u64);
 
-        self.textures.insert(
-            id,
-            UserTexture {
-                size,
-                pixels,
-                gl_texture_id: None,
-                filtering,
-                dirty: true,
-            },
-        );
+        let options = TextureOptions {
+            magnification: filtering,
+            minification: filtering,
+            ..Default::default()
+        };
+        self.textures
+            .insert(id, UserTexture::new(size, pixels, options, true));
 
         id
     }
 
-    pub fn update_user_texture_data(&mut self, texture_id: &egui::TextureId, pixels: &[Color32]) {
+    pub fn update_user_texture_data(
+        &mut self,
+        texture_id: &egui::TextureId,
+        pixels: &[Color32],
+    ) -> Result<()> {
         let texture = self
             .textures
             .get_mut(texture_id)
-            .expect("Texture with id has not been created");
+            .ok_or_else(|| Error::Texture {
+                id: *texture_id,
+                reason: "texture has not been created".to_owned(),
+            })?;
+        check_texel_count(*texture_id, [texture.size.0, texture.size.1], pixels.len())?;
 
         texture.pixels = pixels.iter().flat_map(|a| a.to_array()).collect();
         texture.dirty = true;
+        Ok(())
     }
 
-    fn paint_mesh(
+    fn paint_callback(
         &self,
-        mesh: &Mesh,
+        callback: &egui::PaintCallback,
         clip_rect: &Rect,
         pixels_per_point: f32,
         client_rect: &(u32, u32),
     ) {
-        debug_assert!(mesh.is_valid());
-
-        if let Some(it) = self.textures.get(&mesh.texture_id) {
-            unsafe {
-                gl::BindTexture(
-                    gl::TEXTURE_2D,
-                    it.gl_texture_id
-                        .expect("Texture should have a valid OpenGL id now"),
-                );
-            }
-
-            let screen_size_pixels = egui::vec2(client_rect.0 as f32, client_rect.1 as f32);
-
-            let clip_min_x = pixels_per_point * clip_rect.min.x;
-            let clip_min_y = pixels_per_point * clip_rect.min.y;
-            let clip_max_x = pixels_per_point * clip_rect.max.x;
-            let clip_max_y = pixels_per_point * clip_rect.max.y;
-            let clip_min_x = clip_min_x.clamp(0.0, screen_size_pixels.x);
-            let clip_min_y = clip_min_y.clamp(0.0, screen_size_pixels.y);
-            let clip_max_x = clip_max_x.clamp(clip_min_x, screen_size_pixels.x);
-            let clip_max_y = clip_max_y.clamp(clip_min_y, screen_size_pixels.y);
-            let clip_min_x = clip_min_x.round() as i32;
-            let clip_min_y = clip_min_y.round() as i32;
-            let clip_max_x = clip_max_x.round() as i32;
-            let clip_max_y = clip_max_y.round() as i32;
-
-            //scissor Y coordinate is from the bottom
-            unsafe {
-                gl::Scissor(
-                    clip_min_x,
-                    client_rect.1 as i32 - clip_max_y,
-                    clip_max_x - clip_min_x,
-                    clip_max_y - clip_min_y,
-                );
-            }
+        let Some(callback_fn) = callback.callback.downcast_ref::<CallbackFn>() else {
+            return;
+        };
 
-            let indices: Vec<u16> = mesh.indices.iter().map(move |idx| *idx as u16).collect();
-            let indices_len = indices.len();
-            let vertices_len = mesh.vertices.len();
+        let info = PaintCallbackInfo {
+            viewport: callback.rect,
+            clip_rect: *clip_rect,
+            pixels_per_point,
+            screen_size_px: [client_rect.0, client_rect.1],
+        };
 
-            unsafe {
-                gl::BindVertexArray(self.vertex_array);
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.index_buffer);
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    (indices_len * core::mem::size_of::<u16>()) as GLsizeiptr,
-                    //mem::transmute(&indices.as_ptr()),
-                    indices.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
+        let snapshot = unsafe { GlStateSnapshot::capture(&self.gl) };
 
-            let mut positions: Vec<f32> = Vec::with_capacity(2 * vertices_len);
-            let mut tex_coords: Vec<f32> = Vec::with_capacity(2 * vertices_len);
-            let mut colors: Vec<u8> = Vec::with_capacity(4 * vertices_len);
-            for v in &mesh.vertices {
-                positions.push(v.pos.x);
-                positions.push(v.pos.y);
+        let viewport_px = info.viewport_in_pixels();
+        unsafe {
+            gl::Viewport(
+                viewport_px.left_px,
+                viewport_px.from_bottom_px,
+                viewport_px.width_px,
+                viewport_px.height_px,
+            );
+            gl::Enable(gl::SCISSOR_TEST);
+        }
+        self.set_clip_rect(clip_rect, pixels_per_point, client_rect);
 
-                tex_coords.push(v.uv.x);
-                tex_coords.push(v.uv.y);
+        (callback_fn.f)(info, self);
 
-                colors.push(v.color[0]);
-                colors.push(v.color[1]);
-                colors.push(v.color[2]);
-                colors.push(v.color[3]);
-            }
+        unsafe { snapshot.restore(&self.gl) };
+    }
 
-            unsafe {
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.pos_buffer);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (positions.len() * core::mem::size_of::<f32>()) as GLsizeiptr,
-                    //mem::transmute(&positions.as_ptr()),
-                    positions.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
+    fn set_clip_rect(&self, clip_rect: &Rect, pixels_per_point: f32, client_rect: &(u32, u32)) {
+        let screen_size_pixels = egui::vec2(client_rect.0 as f32, client_rect.1 as f32);
 
-            let a_pos = CString::new("a_pos").unwrap();
-            let a_pos_ptr = a_pos.as_ptr();
-            let a_pos_loc = unsafe { gl::GetAttribLocation(self.program, a_pos_ptr) };
-            assert!(a_pos_loc >= 0);
-            let a_pos_loc = a_pos_loc as u32;
+        let clip_min_x = pixels_per_point * clip_rect.min.x;
+        let clip_min_y = pixels_per_point * clip_rect.min.y;
+        let clip_max_x = pixels_per_point * clip_rect.max.x;
+        let clip_max_y = pixels_per_point * clip_rect.max.y;
+        let clip_min_x = clip_min_x.clamp(0.0, screen_size_pixels.x);
+        let clip_min_y = clip_min_y.clamp(0.0, screen_size_pixels.y);
+        let clip_max_x = clip_max_x.clamp(clip_min_x, screen_size_pixels.x);
+        let clip_max_y = clip_max_y.clamp(clip_min_y, screen_size_pixels.y);
+        let clip_min_x = clip_min_x.round() as i32;
+        let clip_min_y = clip_min_y.round() as i32;
+        let clip_max_x = clip_max_x.round() as i32;
+        let clip_max_y = clip_max_y.round() as i32;
+
+        //scissor Y coordinate is from the bottom
+        unsafe {
+            gl::Scissor(
+                clip_min_x,
+                client_rect.1 as i32 - clip_max_y,
+                clip_max_x - clip_min_x,
+                clip_max_y - clip_min_y,
+            );
+        }
+    }
 
-            let stride = 0;
-            unsafe {
-                gl::VertexAttribPointer(
-                    a_pos_loc,
-                    2,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    stride,
-                    core::ptr::null(),
-                );
-                gl::EnableVertexAttribArray(a_pos_loc);
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.tc_buffer);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (tex_coords.len() * core::mem::size_of::<f32>()) as GLsizeiptr,
-                    //mem::transmute(&tex_coords.as_ptr()),
-                    tex_coords.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
+    fn paint_mesh(
+        &mut self,
+        mesh: &Mesh,
+        clip_rect: &Rect,
+        pixels_per_point: f32,
+        client_rect: &(u32, u32),
+    ) -> usize {
+        debug_assert!(mesh.is_valid());
 
-            let a_tc = CString::new("a_tc").unwrap();
-            let a_tc_ptr = a_tc.as_ptr();
-            let a_tc_loc = unsafe { gl::GetAttribLocation(self.program, a_tc_ptr) };
-            assert!(a_tc_loc >= 0);
-            let a_tc_loc = a_tc_loc as u32;
+        if !self.bind_texture(mesh.texture_id) {
+            return 0;
+        }
 
-            let stride = 0;
-            unsafe {
-                gl::VertexAttribPointer(
-                    a_tc_loc,
-                    2,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    stride,
-                    core::ptr::null(),
-                );
-                gl::EnableVertexAttribArray(a_tc_loc);
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.color_buffer);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (colors.len() * core::mem::size_of::<u8>()) as GLsizeiptr,
-                    //mem::transmute(&colors.as_ptr()),
-                    colors.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
+        self.set_clip_rect(clip_rect, pixels_per_point, client_rect);
 
-            let a_srgba = CString::new("a_srgba").unwrap();
-            let a_srgba_ptr = a_srgba.as_ptr();
-            let a_srgba_loc = unsafe { gl::GetAttribLocation(self.program, a_srgba_ptr) };
-            assert!(a_srgba_loc >= 0);
-            let a_srgba_loc = a_srgba_loc as u32;
+        let chunks = prepare_indices(mesh, self.u32_indices, &mut self.index_scratch);
+        for chunk in &chunks {
+            self.buffers.draw(chunk);
+        }
+        chunks.len()
+    }
 
-            let stride = 0;
-            unsafe {
-                gl::VertexAttribPointer(
-                    a_srgba_loc,
-                    4,
-                    gl::UNSIGNED_BYTE,
-                    gl::FALSE,
-                    stride,
-                    core::ptr::null(),
-                );
-                gl::EnableVertexAttribArray(a_srgba_loc);
+    /// Returns `false` if the texture is unknown and whatever uses it should be skipped.
+    fn bind_texture(&self, texture_id: egui::TextureId) -> bool {
+        let Some(it) = self.textures.get(&texture_id) else {
+            return false;
+        };
 
-                gl::DrawElements(
-                    gl::TRIANGLES,
-                    indices_len as i32,
-                    gl::UNSIGNED_SHORT,
-                    core::ptr::null(),
-                );
-                gl::DisableVertexAttribArray(a_pos_loc);
-                gl::DisableVertexAttribArray(a_tc_loc);
-                gl::DisableVertexAttribArray(a_srgba_loc);
-            }
+        unsafe {
+            gl::BindTexture(
+                gl::TEXTURE_2D,
+                it.gl_texture_id
+                    .expect("Texture should have a valid OpenGL id now"),
+            );
         }
+        true
     }
 
-    pub fn set_texture(&mut self, tex_id: egui::TextureId, delta: &egui::epaint::ImageDelta) {
+    pub fn set_texture(
+        &mut self,
+        tex_id: egui::TextureId,
+        delta: &egui::epaint::ImageDelta,
+    ) -> Result<()> {
         let [w, h] = delta.image.size();
 
         if let Some([x, y]) = delta.pos {
-            if let Some(texture) = self.textures.get_mut(&tex_id) {
-                match &delta.image {
-                    egui::ImageData::Color(image) => {
-                        assert_eq!(
-                            image.width() * image.height(),
-                            image.pixels.len(),
-                            "Mismatch between texture size and texel count"
-                        );
+            let Some(texture) = self.textures.get_mut(&tex_id) else {
+                return Err(Error::Texture {
+                    id: tex_id,
+                    reason: "partial update of a texture that has not been created".to_owned(),
+                });
+            };
+            if x + w > texture.size.0 || y + h > texture.size.1 {
+                return Err(Error::Texture {
+                    id: tex_id,
+                    reason: format!(
+                        "{w}x{h} update at ({x}, {y}) is outside of the {}x{} texture",
+                        texture.size.0, texture.size.1
+                    ),
+                });
+            }
 
-                        let data: Vec<u8> =
-                            image.pixels.iter().flat_map(|a| a.to_array()).collect();
+            match &delta.image {
+                egui::ImageData::Color(image) => {
+                    check_texel_count(tex_id, image.size, image.pixels.len())?;
 
-                        texture.update_texture_part(x as _, y as _, w as _, h as _, &data);
-                    }
+                    let data: Vec<u8> = image.pixels.iter().flat_map(|a| a.to_array()).collect();
 
-                    egui::ImageData::Font(image) => {
-                        assert_eq!(
-                            image.width() * image.height(),
-                            image.pixels.len(),
-                            "Mismatch between texture size and texel count"
-                        );
+                    texture.update_texture_part(x as _, y as _, w as _, h as _, &data);
+                }
 
-                        let gamma = 1.0;
-                        let data: Vec<u8> = image
-                            .srgba_pixels(Some(gamma))
-                            .flat_map(|a| a.to_array())
-                            .collect();
+                egui::ImageData::Font(image) => {
+                    check_texel_count(tex_id, image.size, image.pixels.len())?;
 
-                        texture.update_texture_part(x as _, y as _, w as _, h as _, &data);
-                    }
+                    let gamma = 1.0;
+                    let data: Vec<u8> = image
+                        .srgba_pixels(Some(gamma))
+                        .flat_map(|a| a.to_array())
+                        .collect();
+
+                    texture.update_texture_part(x as _, y as _, w as _, h as _, &data);
                 }
-            } else {
-                eprintln!("Failed to find egui texture {:?}", tex_id);
             }
         } else {
+            // Never stored, so the error is reported once instead of on every frame that uploads.
+            check_texture_size(tex_id, (w, h), self.max_texture_size)?;
+
             let texture = match &delta.image {
                 egui::ImageData::Color(image) => {
-                    assert_eq!(
-                        image.width() * image.height(),
-                        image.pixels.len(),
-                        "Mismatch between texture size and texel count"
-                    );
+                    check_texel_count(tex_id, image.size, image.pixels.len())?;
 
                     let pixels = image.pixels.iter().flat_map(|a| a.to_array()).collect();
 
-                    UserTexture {
-                        size: (w, h),
-                        pixels,
-                        gl_texture_id: None,
-                        filtering: TextureFilter::Linear,
-                        dirty: true,
-                    }
+                    UserTexture::new((w, h), pixels, delta.options, true)
                 }
                 egui::ImageData::Font(image) => {
-                    assert_eq!(
-                        image.width() * image.height(),
-                        image.pixels.len(),
-                        "Mismatch between texture size and texel count"
-                    );
+                    check_texel_count(tex_id, image.size, image.pixels.len())?;
 
                     let gamma = 1.0;
                     let pixels = image
@@ -495,13 +1009,7 @@ impl Painter {
                         .flat_map(|a| a.to_array())
                         .collect();
 
-                    UserTexture {
-                        size: (w, h),
-                        pixels,
-                        gl_texture_id: None,
-                        filtering: TextureFilter::Linear,
-                        dirty: true,
-                    }
+                    UserTexture::new((w, h), pixels, delta.options, false)
                 }
             };
 
@@ -510,13 +1018,27 @@ impl Painter {
                 previous.delete();
             }
         }
+
+        Ok(())
     }
 
-    fn upload_user_textures(&mut self) {
+    fn upload_user_textures(&mut self) -> Result<()> {
+        unsafe { reset_unpack_state(&self.gl) };
+
+        let gles2 = self.gles2;
+        let max_texture_size = self.max_texture_size;
+        let internal_format = if self.srgb_textures {
+            gl::SRGB8_ALPHA8
+        } else {
+            gl::RGBA
+        };
+        let can_mipmap = |size| gl::GenerateMipmap::is_loaded() && npot_complete(gles2, size);
+
         self.textures
-            .values_mut()
-            .filter(|user_texture| user_texture.gl_texture_id.is_none() || user_texture.dirty)
-            .for_each(|user_texture| {
+            .iter_mut()
+            .filter(|(_, user_texture)| user_texture.gl_texture_id.is_none() || user_texture.dirty)
+            .try_for_each(|(&id, user_texture)| {
+                check_texture_size(id, user_texture.size, max_texture_size)?;
                 let pixels = std::mem::take(&mut user_texture.pixels);
 
                 match user_texture.gl_texture_id {
@@ -529,56 +1051,39 @@ impl Painter {
                         unsafe {
                             gl::GenTextures(1, &mut gl_texture);
                             gl::BindTexture(gl::TEXTURE_2D, gl_texture);
+                        }
+                        user_texture.gl_texture_id = Some(gl_texture);
+                        user_texture.has_mipmaps =
+                            user_texture.mipmaps && can_mipmap(user_texture.size);
+
+                        let options = &user_texture.options;
+                        let wrap_mode =
+                            texture_wrap_mode(options.wrap_mode, gles2, user_texture.size) as GLint;
+                        unsafe {
+                            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap_mode);
+                            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap_mode);
                             gl::TexParameteri(
                                 gl::TEXTURE_2D,
-                                gl::TEXTURE_WRAP_S,
-                                gl::CLAMP_TO_EDGE as i32,
+                                gl::TEXTURE_MAG_FILTER,
+                                gl_mag_filter(options.magnification) as GLint,
                             );
                             gl::TexParameteri(
                                 gl::TEXTURE_2D,
-                                gl::TEXTURE_WRAP_T,
-                                gl::CLAMP_TO_EDGE as i32,
+                                gl::TEXTURE_MIN_FILTER,
+                                gl_min_filter(options.minification, user_texture.has_mipmaps)
+                                    as GLint,
                             );
                         }
-
-                        match user_texture.filtering {
-                            TextureFilter::Nearest => unsafe {
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MIN_FILTER,
-                                    gl::LINEAR as i32,
-                                );
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MAG_FILTER,
-                                    gl::LINEAR as i32,
-                                );
-                            },
-
-                            TextureFilter::Linear => unsafe {
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MIN_FILTER,
-                                    gl::NEAREST as i32,
-                                );
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MAG_FILTER,
-                                    gl::NEAREST as i32,
-                                );
-                            },
-                        }
-                        user_texture.gl_texture_id = Some(gl_texture);
                     }
                 }
 
                 if !pixels.is_empty() {
                     let level = 0;
-                    let internal_format = gl::RGBA;
                     let border = 0;
                     let src_format = gl::RGBA;
                     let src_type = gl::UNSIGNED_BYTE;
-                    unsafe {
+                    // The host's errors stay queued, one reported right after the upload is taken as its own.
+                    let error = unsafe {
                         gl::TexImage2D(
                             gl::TEXTURE_2D,
                             level,
@@ -590,11 +1095,29 @@ impl Painter {
                             src_type,
                             pixels.as_ptr() as *const c_void,
                         );
+                        gl::GetError()
+                    };
+                    if error != gl::NO_ERROR {
+                        return Err(Error::Texture {
+                            id,
+                            reason: format!(
+                                "glTexImage2D of {}x{} texels failed with {error:#06x}",
+                                user_texture.size.0, user_texture.size.1
+                            ),
+                        });
+                    }
+                }
+
+                // Covers both full uploads and partial updates made since the last frame.
+                if user_texture.has_mipmaps {
+                    unsafe {
+                        gl::GenerateMipmap(gl::TEXTURE_2D);
                     }
                 }
 
                 user_texture.dirty = false;
-            });
+                Ok(())
+            })
     }
 
     pub fn free_texture(&mut self, tex_id: egui::TextureId) {
@@ -603,3 +1126,227 @@ impl Painter {
         }
     }
 }
+
+impl Renderer for Painter {
+    fn resize(&mut self, size: (u32, u32)) {
+        self.screen_size = size;
+    }
+
+    fn update_textures(&mut self, set: &[(egui::TextureId, ImageDelta)]) -> Result<()> {
+        unsafe { reset_unpack_state(&self.gl) };
+        set_each(set, |id, image_delta| self.set_texture(id, image_delta))
+    }
+
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+    ) -> Result<()> {
+        let screen_size = self.screen_size;
+        self.paint_primitives(pixels_per_point, clipped_primitives, &screen_size)
+    }
+
+    fn free_textures(&mut self, free: &[egui::TextureId]) {
+        for &id in free {
+            self.free_texture(id);
+        }
+    }
+
+    fn destroy(&mut self) {
+        for (_, texture) in self.textures.drain() {
+            texture.delete();
+        }
+        self.buffers.delete();
+        unsafe { gl::DeleteProgram(self.program) };
+    }
+
+    /// Saves and restores the host's state once around the whole frame, see [`Painter::set_state_backup`].
+    fn render_frame(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        textures_delta: &egui::TexturesDelta,
+    ) -> Result<()> {
+        let screen_size = self.screen_size;
+        self.paint_and_update_textures(
+            pixels_per_point,
+            clipped_primitives,
+            textures_delta,
+            &screen_size,
+        )
+    }
+}
+
+#[cfg(test)]
+fn quad_mesh(quads: usize) -> Mesh {
+    let mut mesh = Mesh::default();
+    for i in 0..quads {
+        let min = egui::pos2(i as f32, 0.0);
+        mesh.add_colored_rect(
+            Rect::from_min_size(min, egui::vec2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+    }
+    mesh
+}
+
+#[cfg(test)]
+fn chunk_triangles(chunk: &DrawChunk<'_>) -> Vec<[egui::Pos2; 3]> {
+    let indices: Vec<usize> = match &chunk.indices {
+        Indices::U16(indices) => indices.iter().map(|&i| i as usize).collect(),
+        Indices::U32(indices) => indices.iter().map(|&i| i as usize).collect(),
+    };
+    let (triangles, _) = indices.as_chunks::<3>();
+    triangles
+        .iter()
+        .map(|tri| tri.map(|i| chunk.vertices[i].pos))
+        .collect()
+}
+
+#[test]
+fn test_prepare_indices_u32() {
+    let mesh = quad_mesh(40_000);
+    assert!(mesh.vertices.len() > u16::MAX as usize);
+
+    let mut scratch = vec![];
+    let chunks = prepare_indices(&mesh, true, &mut scratch);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].indices.gl_type(), gl::UNSIGNED_INT);
+    assert_eq!(chunks[0].indices.len(), mesh.indices.len());
+    assert!(matches!(chunks[0].vertices, Cow::Borrowed(_)));
+}
+
+#[test]
+fn test_prepare_indices_split_to_u16() {
+    let mesh = quad_mesh(40_000);
+    let expected = chunk_triangles(&prepare_indices(&mesh, true, &mut vec![])[0]);
+
+    let mut scratch = vec![];
+    let chunks = prepare_indices(&mesh, false, &mut scratch);
+    assert!(chunks.len() > 1);
+
+    let mut triangles = vec![];
+    for chunk in &chunks {
+        assert_eq!(chunk.indices.gl_type(), gl::UNSIGNED_SHORT);
+        assert!(chunk.vertices.len() <= u16::MAX as usize);
+        triangles.extend(chunk_triangles(chunk));
+    }
+
+    // Nothing is dropped or wrapped around into the wrong vertices.
+    assert_eq!(triangles, expected);
+}
+
+#[test]
+fn test_prepare_indices_small_mesh_u16() {
+    let mesh = quad_mesh(10);
+    let mut scratch = vec![];
+    let chunks = prepare_indices(&mesh, false, &mut scratch);
+    assert_eq!(chunks.len(), 1);
+    assert!(matches!(chunks[0].vertices, Cow::Borrowed(_)));
+    assert!(matches!(chunks[0].indices, Indices::U16(Cow::Borrowed(_))));
+    assert_eq!(
+        chunk_triangles(&chunks[0]),
+        chunk_triangles(&prepare_indices(&mesh, true, &mut vec![])[0])
+    );
+}
+
+#[test]
+fn test_vertex_layout() {
+    // The attribute pointers in `MeshBuffers::new` read `Vertex` directly.
+    assert_eq!(size_of::<Vertex>(), 20);
+    assert_eq!(offset_of!(Vertex, pos), 0);
+    assert_eq!(offset_of!(Vertex, uv), 8);
+    assert_eq!(offset_of!(Vertex, color), 16);
+}
+
+#[test]
+fn test_texture_options_mapping() {
+    assert_eq!(gl_mag_filter(TextureFilter::Nearest), gl::NEAREST);
+    assert_eq!(gl_mag_filter(TextureFilter::Linear), gl::LINEAR);
+
+    assert_eq!(gl_min_filter(TextureFilter::Nearest, false), gl::NEAREST);
+    assert_eq!(gl_min_filter(TextureFilter::Linear, false), gl::LINEAR);
+    assert_eq!(
+        gl_min_filter(TextureFilter::Nearest, true),
+        gl::NEAREST_MIPMAP_NEAREST
+    );
+    assert_eq!(
+        gl_min_filter(TextureFilter::Linear, true),
+        gl::LINEAR_MIPMAP_LINEAR
+    );
+
+    assert_eq!(
+        gl_wrap_mode(TextureWrapMode::ClampToEdge),
+        gl::CLAMP_TO_EDGE
+    );
+    assert_eq!(gl_wrap_mode(TextureWrapMode::Repeat), gl::REPEAT);
+    assert_eq!(
+        gl_wrap_mode(TextureWrapMode::MirroredRepeat),
+        gl::MIRRORED_REPEAT
+    );
+}
+
+#[test]
+fn test_gles2_npot_wrap_mode() {
+    for wrap_mode in [TextureWrapMode::Repeat, TextureWrapMode::MirroredRepeat] {
+        assert_eq!(
+            texture_wrap_mode(wrap_mode, true, (64, 32)),
+            gl_wrap_mode(wrap_mode)
+        );
+        assert_eq!(
+            texture_wrap_mode(wrap_mode, true, (100, 32)),
+            gl::CLAMP_TO_EDGE
+        );
+        assert_eq!(
+            texture_wrap_mode(wrap_mode, true, (64, 3)),
+            gl::CLAMP_TO_EDGE
+        );
+        assert_eq!(
+            texture_wrap_mode(wrap_mode, false, (100, 3)),
+            gl_wrap_mode(wrap_mode)
+        );
+    }
+}
+
+#[test]
+fn test_update_pending_texture_part() {
+    let mut texture = UserTexture::new((4, 3), vec![0; 4 * 3 * 4], TextureOptions::NEAREST, false);
+    let patch = [255u8; 2 * 2 * 4];
+    texture.update_texture_part(1, 1, 2, 2, &patch);
+
+    let texels: Vec<bool> = texture
+        .pixels
+        .as_chunks::<4>()
+        .0
+        .iter()
+        .map(|px| px[0] == 255)
+        .collect();
+    #[rustfmt::skip]
+    assert_eq!(texels, [
+        false, false, false, false,
+        false, true,  true,  false,
+        false, true,  true,  false,
+    ]);
+}
+
+#[test]
+fn test_texel_count_error() {
+    let id = egui::TextureId::Managed(3);
+    assert!(check_texel_count(id, [4, 2], 8).is_ok());
+
+    let err = check_texel_count(id, [4, 2], 7).unwrap_err();
+    assert!(matches!(err, Error::Texture { id: err_id, .. } if err_id == id));
+    assert_eq!(err.to_string(), "texture Managed(3): 4x2 image has 7 texels");
+}
+
+#[test]
+fn test_texture_size_error() {
+    let id = egui::TextureId::Managed(1);
+    assert!(check_texture_size(id, (2048, 16), 2048).is_ok());
+
+    let err = check_texture_size(id, (16, 4096), 2048).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "texture Managed(1): 16x4096 texture is larger than the maximum of 2048x2048"
+    );
+}
@@ -0,0 +1,217 @@
+use crate::Result;
+use egui::{epaint::ImageDelta, ClippedPrimitive, TextureId, TexturesDelta};
+
+/// Draws what egui produced each frame. [`crate::Painter`] is the OpenGL implementation,
+/// anything else (another GL painter, a recording mock, a CPU rasterizer) can be plugged into the app instead.
+///
+/// The app calls every method with its own OpenGL context current.
+pub trait Renderer {
+    /// Size of the area to draw into in pixels, called before the first frame and whenever the window changes size.
+    fn resize(&mut self, size: (u32, u32));
+
+    /// Creates new textures and patches existing ones before they are painted.
+    ///
+    /// egui sends every delta only once, so all of them should be applied even if one fails,
+    /// returning the first error.
+    fn update_textures(&mut self, set: &[(TextureId, ImageDelta)]) -> Result<()>;
+
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()>;
+
+    /// Deletes textures egui no longer uses, called after they were painted for the last time.
+    fn free_textures(&mut self, free: &[TextureId]);
+
+    /// Releases everything the renderer created, called once before the app's context is deleted.
+    fn destroy(&mut self);
+
+    /// One whole frame. Textures are freed even when updating or painting failed.
+    fn render_frame(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+    ) -> Result<()> {
+        let result = self
+            .update_textures(&textures_delta.set)
+            .and_then(|()| self.paint(pixels_per_point, clipped_primitives));
+        self.free_textures(&textures_delta.free);
+        result
+    }
+}
+
+/// Applies every delta with `set_texture` and returns the first error, so one bad texture does not lose
+/// the deltas queued after it.
+pub(crate) fn set_each(
+    set: &[(TextureId, ImageDelta)],
+    mut set_texture: impl FnMut(TextureId, &ImageDelta) -> Result<()>,
+) -> Result<()> {
+    let mut result = Ok(());
+    for (id, image_delta) in set {
+        let applied = set_texture(*id, image_delta);
+        if result.is_ok() {
+            result = applied;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use egui::{epaint::Primitive, Color32, ColorImage, Mesh, Rect, TextureOptions};
+
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        Resize((u32, u32)),
+        Update(Vec<TextureId>),
+        Set(TextureId),
+        Paint(usize),
+        Free(Vec<TextureId>),
+        Destroy,
+    }
+
+    #[derive(Default)]
+    struct RecordingRenderer {
+        calls: Vec<Call>,
+        fail_updates: bool,
+        /// Deltas of this texture are rejected one by one.
+        fail_texture: Option<TextureId>,
+    }
+
+    impl Renderer for RecordingRenderer {
+        fn resize(&mut self, size: (u32, u32)) {
+            self.calls.push(Call::Resize(size));
+        }
+
+        fn update_textures(&mut self, set: &[(TextureId, ImageDelta)]) -> Result<()> {
+            self.calls
+                .push(Call::Update(set.iter().map(|(id, _)| *id).collect()));
+            match set.first() {
+                Some(&(id, _)) if self.fail_updates => Err(Error::Texture {
+                    id,
+                    reason: "rejected".into(),
+                }),
+                _ => set_each(set, |id, _| {
+                    if self.fail_texture == Some(id) {
+                        return Err(Error::Texture {
+                            id,
+                            reason: "rejected".into(),
+                        });
+                    }
+                    self.calls.push(Call::Set(id));
+                    Ok(())
+                }),
+            }
+        }
+
+        fn paint(&mut self, _: f32, clipped_primitives: &[ClippedPrimitive]) -> Result<()> {
+            self.calls.push(Call::Paint(clipped_primitives.len()));
+            Ok(())
+        }
+
+        fn free_textures(&mut self, free: &[TextureId]) {
+            self.calls.push(Call::Free(free.to_vec()));
+        }
+
+        fn destroy(&mut self) {
+            self.calls.push(Call::Destroy);
+        }
+    }
+
+    fn frame() -> (Vec<ClippedPrimitive>, TexturesDelta) {
+        let primitive = ClippedPrimitive {
+            clip_rect: Rect::EVERYTHING,
+            primitive: Primitive::Mesh(Mesh::default()),
+        };
+        let image = ColorImage::new([1, 1], Color32::WHITE);
+        let textures_delta = TexturesDelta {
+            set: vec![(
+                TextureId::Managed(1),
+                ImageDelta::full(image, TextureOptions::LINEAR),
+            )],
+            free: vec![TextureId::Managed(0)],
+        };
+        (vec![primitive], textures_delta)
+    }
+
+    #[test]
+    fn test_render_frame_order() {
+        let (primitives, textures_delta) = frame();
+        let mut renderer = RecordingRenderer::default();
+        renderer.resize((640, 480));
+        renderer
+            .render_frame(1.0, &primitives, &textures_delta)
+            .unwrap();
+        renderer.destroy();
+
+        assert_eq!(
+            renderer.calls,
+            [
+                Call::Resize((640, 480)),
+                Call::Update(vec![TextureId::Managed(1)]),
+                Call::Set(TextureId::Managed(1)),
+                Call::Paint(1),
+                Call::Free(vec![TextureId::Managed(0)]),
+                Call::Destroy,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_frame_frees_after_error() {
+        let (primitives, textures_delta) = frame();
+        let mut renderer = RecordingRenderer {
+            fail_updates: true,
+            ..Default::default()
+        };
+
+        let err = renderer
+            .render_frame(1.0, &primitives, &textures_delta)
+            .unwrap_err();
+        assert!(matches!(err, Error::Texture { .. }));
+        // Nothing is painted with half-updated textures, but dead ones are still released.
+        assert_eq!(
+            renderer.calls,
+            [
+                Call::Update(vec![TextureId::Managed(1)]),
+                Call::Free(vec![TextureId::Managed(0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_frame_applies_deltas_after_error() {
+        let (primitives, mut textures_delta) = frame();
+        let delta = textures_delta.set[0].1.clone();
+        textures_delta.set = (1..=3)
+            .map(|i| (TextureId::Managed(i), delta.clone()))
+            .collect();
+        let mut renderer = RecordingRenderer {
+            fail_texture: Some(TextureId::Managed(2)),
+            ..Default::default()
+        };
+
+        let err = renderer
+            .render_frame(1.0, &primitives, &textures_delta)
+            .unwrap_err();
+        assert!(matches!(err, Error::Texture { id, .. } if id == TextureId::Managed(2)));
+        // The delta after the failed one is still applied, only painting is skipped.
+        assert_eq!(
+            renderer.calls,
+            [
+                Call::Update(vec![
+                    TextureId::Managed(1),
+                    TextureId::Managed(2),
+                    TextureId::Managed(3),
+                ]),
+                Call::Set(TextureId::Managed(1)),
+                Call::Set(TextureId::Managed(3)),
+                Call::Free(vec![TextureId::Managed(0)]),
+            ]
+        );
+    }
+}
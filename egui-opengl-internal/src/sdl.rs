@@ -0,0 +1,719 @@
+//! Input from SDL2 games, for hooks of `SDL_PollEvent` or `SDL_PeepEvents` which see events before the game does.
+
+use egui::{
+    Context, Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect, Vec2,
+};
+use std::ffi::c_char;
+
+pub const SDL_WINDOWEVENT: u32 = 0x200;
+pub const SDL_KEYDOWN: u32 = 0x300;
+pub const SDL_KEYUP: u32 = 0x301;
+pub const SDL_TEXTINPUT: u32 = 0x303;
+pub const SDL_MOUSEMOTION: u32 = 0x400;
+pub const SDL_MOUSEBUTTONDOWN: u32 = 0x401;
+pub const SDL_MOUSEBUTTONUP: u32 = 0x402;
+pub const SDL_MOUSEWHEEL: u32 = 0x403;
+
+pub const SDL_WINDOWEVENT_SIZE_CHANGED: u8 = 6;
+pub const SDL_WINDOWEVENT_LEAVE: u8 = 11;
+pub const SDL_WINDOWEVENT_FOCUS_GAINED: u8 = 12;
+pub const SDL_WINDOWEVENT_FOCUS_LOST: u8 = 13;
+
+pub const SDL_MOUSEWHEEL_FLIPPED: u32 = 1;
+
+/// Set in keycodes of keys that do not type a character, the rest of the keycode is the scancode.
+const SDLK_SCANCODE_MASK: i32 = 1 << 30;
+
+/// `SDL_Keysym`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlKeysym {
+    pub scancode: i32,
+    pub sym: i32,
+    pub modifiers: u16,
+    pub unused: u32,
+}
+
+/// `SDL_KeyboardEvent`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlKeyboardEvent {
+    pub type_: u32,
+    pub timestamp: u32,
+    pub window_id: u32,
+    pub state: u8,
+    pub repeat: u8,
+    pub padding: [u8; 2],
+    pub keysym: SdlKeysym,
+}
+
+/// `SDL_TextInputEvent`, with the UTF-8 text null terminated.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlTextInputEvent {
+    pub type_: u32,
+    pub timestamp: u32,
+    pub window_id: u32,
+    pub text: [c_char; 32],
+}
+
+/// `SDL_MouseMotionEvent`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlMouseMotionEvent {
+    pub type_: u32,
+    pub timestamp: u32,
+    pub window_id: u32,
+    pub which: u32,
+    pub state: u32,
+    pub x: i32,
+    pub y: i32,
+    pub xrel: i32,
+    pub yrel: i32,
+}
+
+/// `SDL_MouseButtonEvent`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlMouseButtonEvent {
+    pub type_: u32,
+    pub timestamp: u32,
+    pub window_id: u32,
+    pub which: u32,
+    pub button: u8,
+    pub state: u8,
+    pub clicks: u8,
+    pub padding: u8,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// `SDL_MouseWheelEvent`. `precise_x` and `precise_y` were added in SDL 2.0.18 and are zero before.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlMouseWheelEvent {
+    pub type_: u32,
+    pub timestamp: u32,
+    pub window_id: u32,
+    pub which: u32,
+    pub x: i32,
+    pub y: i32,
+    pub direction: u32,
+    pub precise_x: f32,
+    pub precise_y: f32,
+}
+
+/// `SDL_WindowEvent`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdlWindowEvent {
+    pub type_: u32,
+    pub timestamp: u32,
+    pub window_id: u32,
+    pub event: u8,
+    pub padding: [u8; 3],
+    pub data1: i32,
+    pub data2: i32,
+}
+
+/// `SDL_Event`, with the same layout so a pointer from a hook can be cast to it.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union SdlEvent {
+    pub type_: u32,
+    pub key: SdlKeyboardEvent,
+    pub text: SdlTextInputEvent,
+    pub motion: SdlMouseMotionEvent,
+    pub button: SdlMouseButtonEvent,
+    pub wheel: SdlMouseWheelEvent,
+    pub window: SdlWindowEvent,
+    pub padding: [u8; 56],
+}
+
+/// Collects input of a single SDL window, like [`crate::linux::XInputCollector`] does for X11.
+pub struct SdlInputCollector {
+    events: Vec<Event>,
+    modifiers: Modifiers,
+    /// Keys currently down by scancode, released when the window loses focus.
+    held_keys: Vec<(i32, Key)>,
+    focused: bool,
+    screen_size: Option<(u32, u32)>,
+}
+
+/// What an event was about, and so which of egui's wishes decides if the game should see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdlInputResult {
+    Unknown,
+    Pointer,
+    Key,
+    Window,
+}
+
+impl SdlInputResult {
+    /// Whether egui wants the input the event carries, in which case the hook should not pass it on.
+    pub fn swallow(self, ctx: &Context) -> bool {
+        match self {
+            Self::Pointer => ctx.wants_pointer_input(),
+            Self::Key => ctx.wants_keyboard_input(),
+            Self::Unknown | Self::Window => false,
+        }
+    }
+}
+
+impl SdlInputCollector {
+    /// `screen_size` is the window's size until a resize event says otherwise.
+    pub fn new(screen_size: Option<(u32, u32)>) -> Self {
+        Self {
+            events: vec![],
+            modifiers: Modifiers::NONE,
+            held_keys: vec![],
+            focused: true,
+            screen_size,
+        }
+    }
+
+    pub fn process(&mut self, event: &SdlEvent) -> SdlInputResult {
+        match unsafe { event.type_ } {
+            ty @ (SDL_KEYDOWN | SDL_KEYUP) => {
+                let event = unsafe { &event.key };
+                let pressed = ty == SDL_KEYDOWN;
+                let modifiers = get_modifiers(event.keysym.modifiers);
+                self.modifiers = modifiers;
+
+                let Some(key) = get_key(event.keysym.sym) else {
+                    return SdlInputResult::Key;
+                };
+                let scancode = event.keysym.scancode;
+                let held = self
+                    .held_keys
+                    .iter()
+                    .position(|&(held, _)| held == scancode);
+                match (pressed, held) {
+                    (true, None) => self.held_keys.push((scancode, key)),
+                    (false, Some(i)) => {
+                        self.held_keys.swap_remove(i);
+                    }
+                    _ => {}
+                }
+
+                if pressed && modifiers.ctrl {
+                    match key {
+                        Key::C => self.events.push(Event::Copy),
+                        Key::X => self.events.push(Event::Cut),
+                        _ => {}
+                    }
+                }
+
+                self.events.push(Event::Key {
+                    key,
+                    physical_key: get_physical_key(scancode),
+                    pressed,
+                    repeat: pressed && event.repeat != 0,
+                    modifiers,
+                });
+                SdlInputResult::Key
+            }
+            SDL_TEXTINPUT => {
+                let text = unsafe { &event.text.text };
+                // A full buffer has no terminator.
+                let len = text.iter().position(|&c| c == 0).unwrap_or(text.len());
+                let bytes = unsafe { std::slice::from_raw_parts(text.as_ptr() as *const u8, len) };
+                if let Ok(text) = std::str::from_utf8(bytes) {
+                    if !text.is_empty() && !text.chars().any(char::is_control) {
+                        self.events.push(Event::Text(text.into()));
+                    }
+                }
+                SdlInputResult::Key
+            }
+            SDL_MOUSEMOTION => {
+                let event = unsafe { &event.motion };
+                self.events.push(Event::PointerMoved(Pos2::new(
+                    event.x as f32,
+                    event.y as f32,
+                )));
+                SdlInputResult::Pointer
+            }
+            ty @ (SDL_MOUSEBUTTONDOWN | SDL_MOUSEBUTTONUP) => {
+                let event = unsafe { &event.button };
+                if let Some(button) = get_button(event.button) {
+                    self.events.push(Event::PointerButton {
+                        pos: Pos2::new(event.x as f32, event.y as f32),
+                        button,
+                        pressed: ty == SDL_MOUSEBUTTONDOWN,
+                        modifiers: self.modifiers,
+                    });
+                }
+                SdlInputResult::Pointer
+            }
+            SDL_MOUSEWHEEL => {
+                let event = unsafe { &event.wheel };
+                self.events.push(Event::MouseWheel {
+                    unit: MouseWheelUnit::Line,
+                    delta: get_wheel_delta(event),
+                    modifiers: self.modifiers,
+                });
+                SdlInputResult::Pointer
+            }
+            SDL_WINDOWEVENT => {
+                let event = unsafe { &event.window };
+                match event.event {
+                    SDL_WINDOWEVENT_SIZE_CHANGED => {
+                        self.screen_size =
+                            Some((event.data1.max(0) as u32, event.data2.max(0) as u32));
+                    }
+                    SDL_WINDOWEVENT_LEAVE => self.events.push(Event::PointerGone),
+                    SDL_WINDOWEVENT_FOCUS_GAINED => self.set_focused(true),
+                    SDL_WINDOWEVENT_FOCUS_LOST => self.set_focused(false),
+                    _ => return SdlInputResult::Unknown,
+                }
+                SdlInputResult::Window
+            }
+            _ => SdlInputResult::Unknown,
+        }
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused {
+            // Their releases go to whichever window has the focus now.
+            for (_, key) in self.held_keys.drain(..) {
+                self.events.push(Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed: false,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                });
+            }
+            self.modifiers = Modifiers::NONE;
+        }
+        self.events.push(Event::WindowFocused(focused));
+    }
+
+    /// Size of the window from the last resize event, or the one it was created with.
+    pub fn screen_size(&self) -> Option<(u32, u32)> {
+        self.screen_size
+    }
+
+    pub fn collect_input(&mut self, ctx: &Context, time: f64) -> RawInput {
+        RawInput {
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            screen_rect: self.screen_size.map(|(width, height)| {
+                Rect::from_min_size(Pos2::ZERO, Vec2::new(width as f32, height as f32))
+            }),
+            time: Some(time),
+            focused: self.focused,
+            viewport_id: ctx.viewport_id(),
+            viewports: ctx.input(|i| i.raw.viewports.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+fn get_modifiers(modifiers: u16) -> Modifiers {
+    const KMOD_SHIFT: u16 = 0x0001 | 0x0002;
+    const KMOD_CTRL: u16 = 0x0040 | 0x0080;
+    const KMOD_ALT: u16 = 0x0100 | 0x0200;
+
+    let ctrl = modifiers & KMOD_CTRL != 0;
+
+    Modifiers {
+        alt: modifiers & KMOD_ALT != 0,
+        ctrl,
+        shift: modifiers & KMOD_SHIFT != 0,
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+fn get_button(button: u8) -> Option<PointerButton> {
+    match button {
+        1 => Some(PointerButton::Primary),
+        2 => Some(PointerButton::Middle),
+        3 => Some(PointerButton::Secondary),
+        4 => Some(PointerButton::Extra1),
+        5 => Some(PointerButton::Extra2),
+        _ => None,
+    }
+}
+
+/// SDL counts scrolling right as positive, egui moves the content right.
+fn get_wheel_delta(event: &SdlMouseWheelEvent) -> Vec2 {
+    let mut delta = if event.precise_x != 0.0 || event.precise_y != 0.0 {
+        Vec2::new(-event.precise_x, event.precise_y)
+    } else {
+        Vec2::new(-event.x as f32, event.y as f32)
+    };
+    if event.direction == SDL_MOUSEWHEEL_FLIPPED {
+        delta = -delta;
+    }
+    delta
+}
+
+/// Keys by `SDL_Scancode`, which are USB HID usage IDs.
+const SCANCODE_KEYS: &[(i32, Key)] = &[
+    (4, Key::A),
+    (5, Key::B),
+    (6, Key::C),
+    (7, Key::D),
+    (8, Key::E),
+    (9, Key::F),
+    (10, Key::G),
+    (11, Key::H),
+    (12, Key::I),
+    (13, Key::J),
+    (14, Key::K),
+    (15, Key::L),
+    (16, Key::M),
+    (17, Key::N),
+    (18, Key::O),
+    (19, Key::P),
+    (20, Key::Q),
+    (21, Key::R),
+    (22, Key::S),
+    (23, Key::T),
+    (24, Key::U),
+    (25, Key::V),
+    (26, Key::W),
+    (27, Key::X),
+    (28, Key::Y),
+    (29, Key::Z),
+    (30, Key::Num1),
+    (31, Key::Num2),
+    (32, Key::Num3),
+    (33, Key::Num4),
+    (34, Key::Num5),
+    (35, Key::Num6),
+    (36, Key::Num7),
+    (37, Key::Num8),
+    (38, Key::Num9),
+    (39, Key::Num0),
+    (40, Key::Enter),
+    (41, Key::Escape),
+    (42, Key::Backspace),
+    (43, Key::Tab),
+    (44, Key::Space),
+    (45, Key::Minus),
+    (46, Key::Equals),
+    (47, Key::OpenBracket),
+    (48, Key::CloseBracket),
+    (49, Key::Backslash),
+    (51, Key::Semicolon),
+    (52, Key::Quote),
+    (53, Key::Backtick),
+    (54, Key::Comma),
+    (55, Key::Period),
+    (56, Key::Slash),
+    (58, Key::F1),
+    (59, Key::F2),
+    (60, Key::F3),
+    (61, Key::F4),
+    (62, Key::F5),
+    (63, Key::F6),
+    (64, Key::F7),
+    (65, Key::F8),
+    (66, Key::F9),
+    (67, Key::F10),
+    (68, Key::F11),
+    (69, Key::F12),
+    (73, Key::Insert),
+    (74, Key::Home),
+    (75, Key::PageUp),
+    (76, Key::Delete),
+    (77, Key::End),
+    (78, Key::PageDown),
+    (79, Key::ArrowRight),
+    (80, Key::ArrowLeft),
+    (81, Key::ArrowDown),
+    (82, Key::ArrowUp),
+    (84, Key::Slash),
+    (86, Key::Minus),
+    (87, Key::Plus),
+    (88, Key::Enter),
+    (89, Key::Num1),
+    (90, Key::Num2),
+    (91, Key::Num3),
+    (92, Key::Num4),
+    (93, Key::Num5),
+    (94, Key::Num6),
+    (95, Key::Num7),
+    (96, Key::Num8),
+    (97, Key::Num9),
+    (98, Key::Num0),
+    (99, Key::Period),
+    (103, Key::Equals),
+    (104, Key::F13),
+    (105, Key::F14),
+    (106, Key::F15),
+    (107, Key::F16),
+    (108, Key::F17),
+    (109, Key::F18),
+    (110, Key::F19),
+    (111, Key::F20),
+    (112, Key::F21),
+    (113, Key::F22),
+    (114, Key::F23),
+    (115, Key::F24),
+];
+
+fn get_physical_key(scancode: i32) -> Option<Key> {
+    SCANCODE_KEYS
+        .iter()
+        .find(|&&(code, _)| code == scancode)
+        .map(|&(_, key)| key)
+}
+
+/// The logical key of an `SDL_Keycode`: the character it types under the current layout,
+/// or the scancode for keys that type nothing.
+fn get_key(keycode: i32) -> Option<Key> {
+    if keycode & SDLK_SCANCODE_MASK != 0 {
+        return get_physical_key(keycode & !SDLK_SCANCODE_MASK);
+    }
+
+    let key = match u8::try_from(keycode).ok()? {
+        b'\r' => Key::Enter,
+        0x1b => Key::Escape,
+        0x08 => Key::Backspace,
+        b'\t' => Key::Tab,
+        b' ' => Key::Space,
+        0x7f => Key::Delete,
+        c @ b'a'..=b'z' => return Key::from_name(&(c.to_ascii_uppercase() as char).to_string()),
+        c @ b'0'..=b'9' => return Key::from_name(&(c as char).to_string()),
+        b':' => Key::Colon,
+        b',' => Key::Comma,
+        b'\\' => Key::Backslash,
+        b'/' => Key::Slash,
+        b'|' => Key::Pipe,
+        b'?' => Key::Questionmark,
+        b'[' => Key::OpenBracket,
+        b']' => Key::CloseBracket,
+        b'`' => Key::Backtick,
+        b'-' => Key::Minus,
+        b'.' => Key::Period,
+        b'+' => Key::Plus,
+        b'=' => Key::Equals,
+        b';' => Key::Semicolon,
+        b'\'' => Key::Quote,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_event(type_: u32, scancode: i32, sym: i32, modifiers: u16, repeat: u8) -> SdlEvent {
+        SdlEvent {
+            key: SdlKeyboardEvent {
+                type_,
+                repeat,
+                keysym: SdlKeysym {
+                    scancode,
+                    sym,
+                    modifiers,
+                    unused: 0,
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    fn text_event(text: &str) -> SdlEvent {
+        let mut event = SdlTextInputEvent {
+            type_: SDL_TEXTINPUT,
+            ..Default::default()
+        };
+        for (dst, &src) in event.text.iter_mut().zip(text.as_bytes()) {
+            *dst = src as c_char;
+        }
+        SdlEvent { text: event }
+    }
+
+    fn window_event(event: u8, data1: i32, data2: i32) -> SdlEvent {
+        SdlEvent {
+            window: SdlWindowEvent {
+                type_: SDL_WINDOWEVENT,
+                event,
+                data1,
+                data2,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(size_of::<SdlEvent>(), 56);
+        assert_eq!(size_of::<SdlKeyboardEvent>(), 32);
+        assert_eq!(std::mem::offset_of!(SdlMouseButtonEvent, x), 20);
+        assert_eq!(std::mem::offset_of!(SdlMouseWheelEvent, precise_x), 28);
+        assert_eq!(std::mem::offset_of!(SdlWindowEvent, data1), 16);
+    }
+
+    #[test]
+    fn test_keys_and_text() {
+        let mut collector = SdlInputCollector::new(None);
+
+        // `Z` on a German layout, where the key is in the place of `Y`.
+        let result = collector.process(&key_event(SDL_KEYDOWN, 28, b'z' as i32, 0, 0));
+        assert_eq!(result, SdlInputResult::Key);
+        collector.process(&text_event("z"));
+        collector.process(&key_event(SDL_KEYDOWN, 28, b'z' as i32, 0, 1));
+        collector.process(&key_event(SDL_KEYUP, 28, b'z' as i32, 0, 0));
+        // Ctrl+C, with the right control key.
+        collector.process(&key_event(SDL_KEYDOWN, 6, b'c' as i32, 0x0080, 0));
+        // F5 has no character and is identified by its scancode.
+        collector.process(&key_event(SDL_KEYDOWN, 62, 62 | SDLK_SCANCODE_MASK, 0, 0));
+
+        let key = |key, physical_key, pressed, repeat, modifiers| Event::Key {
+            key,
+            physical_key: Some(physical_key),
+            pressed,
+            repeat,
+            modifiers,
+        };
+        let ctrl = Modifiers {
+            ctrl: true,
+            command: true,
+            ..Modifiers::NONE
+        };
+        assert_eq!(
+            collector.events,
+            [
+                key(Key::Z, Key::Y, true, false, Modifiers::NONE),
+                Event::Text("z".into()),
+                key(Key::Z, Key::Y, true, true, Modifiers::NONE),
+                key(Key::Z, Key::Y, false, false, Modifiers::NONE),
+                Event::Copy,
+                key(Key::C, Key::C, true, false, ctrl),
+                key(Key::F5, Key::F5, true, false, Modifiers::NONE),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mouse() {
+        let mut collector = SdlInputCollector::new(None);
+
+        let motion = SdlMouseMotionEvent {
+            type_: SDL_MOUSEMOTION,
+            x: 3,
+            y: 4,
+            ..Default::default()
+        };
+        collector.process(&SdlEvent { motion });
+        for (type_, button) in [(SDL_MOUSEBUTTONDOWN, 3), (SDL_MOUSEBUTTONUP, 4)] {
+            let button = SdlMouseButtonEvent {
+                type_,
+                button,
+                x: 3,
+                y: 4,
+                ..Default::default()
+            };
+            assert_eq!(
+                collector.process(&SdlEvent { button }),
+                SdlInputResult::Pointer
+            );
+        }
+        let wheels = [
+            (1, 2, 0.0, 0.0, 0),
+            (0, 0, 0.5, -1.5, 0),
+            (0, 1, 0.0, 0.0, SDL_MOUSEWHEEL_FLIPPED),
+        ];
+        for (x, y, precise_x, precise_y, direction) in wheels {
+            let wheel = SdlMouseWheelEvent {
+                type_: SDL_MOUSEWHEEL,
+                x,
+                y,
+                precise_x,
+                precise_y,
+                direction,
+                ..Default::default()
+            };
+            collector.process(&SdlEvent { wheel });
+        }
+
+        let pos = Pos2::new(3.0, 4.0);
+        let wheel = |x, y| Event::MouseWheel {
+            unit: MouseWheelUnit::Line,
+            delta: Vec2::new(x, y),
+            modifiers: Modifiers::NONE,
+        };
+        assert_eq!(
+            collector.events,
+            [
+                Event::PointerMoved(pos),
+                Event::PointerButton {
+                    pos,
+                    button: PointerButton::Secondary,
+                    pressed: true,
+                    modifiers: Modifiers::NONE,
+                },
+                Event::PointerButton {
+                    pos,
+                    button: PointerButton::Extra1,
+                    pressed: false,
+                    modifiers: Modifiers::NONE,
+                },
+                wheel(-1.0, 2.0),
+                wheel(-0.5, -1.5),
+                wheel(0.0, -1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_events() {
+        let mut collector = SdlInputCollector::new(Some((640, 480)));
+
+        collector.process(&key_event(SDL_KEYDOWN, 44, b' ' as i32, 0x0001, 0));
+        collector.events.clear();
+
+        let result = collector.process(&window_event(SDL_WINDOWEVENT_SIZE_CHANGED, 800, 600));
+        assert_eq!(result, SdlInputResult::Window);
+        collector.process(&window_event(SDL_WINDOWEVENT_LEAVE, 0, 0));
+        collector.process(&window_event(SDL_WINDOWEVENT_FOCUS_LOST, 0, 0));
+        assert_eq!(
+            collector.events,
+            [
+                Event::PointerGone,
+                Event::Key {
+                    key: Key::Space,
+                    physical_key: None,
+                    pressed: false,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                },
+                Event::WindowFocused(false),
+            ]
+        );
+
+        let input = collector.collect_input(&Context::default(), 1.0);
+        assert_eq!(
+            input.screen_rect,
+            Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0)))
+        );
+        assert!(!input.focused);
+        assert_eq!(input.modifiers, Modifiers::NONE);
+        assert!(!SdlInputResult::Window.swallow(&Context::default()));
+    }
+
+    #[test]
+    fn test_key_tables() {
+        assert_eq!(get_key(b'a' as i32), Some(Key::A));
+        assert_eq!(get_key(b'9' as i32), Some(Key::Num9));
+        assert_eq!(get_key(b'\r' as i32), Some(Key::Enter));
+        assert_eq!(get_key(79 | SDLK_SCANCODE_MASK), Some(Key::ArrowRight));
+        assert_eq!(get_key(0xe9), None);
+        assert_eq!(get_physical_key(98), Some(Key::Num0));
+        assert_eq!(get_physical_key(115), Some(Key::F24));
+        assert_eq!(get_physical_key(0), None);
+
+        // Every scancode means one key.
+        for (i, (code, _)) in SCANCODE_KEYS.iter().enumerate() {
+            assert!(SCANCODE_KEYS[..i].iter().all(|(other, _)| other != code));
+        }
+    }
+}
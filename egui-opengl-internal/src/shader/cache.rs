@@ -0,0 +1,264 @@
+use super::gl_string;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"EGLB";
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a, used instead of `DefaultHasher` because file names have to stay the same across builds.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Identifies a program binary: one driver build and one exact pair of shader sources.
+/// A driver update changes the key, so a stale binary is never even looked up.
+pub fn cache_key(vendor: &str, renderer: &str, version: &str, sources: &[&str]) -> String {
+    // The terminator keeps `["ab", "c"]` and `["a", "bc"]` apart.
+    let sources = sources.iter().fold(FNV_OFFSET, |hash, source| {
+        fnv1a(fnv1a(hash, source.as_bytes()), &[0])
+    });
+    format!("{vendor}\n{renderer}\n{version}\n{sources:016x}")
+}
+
+/// `MAGIC`, key length and key, binary format, binary length and the binary itself. Integers are little endian.
+pub fn encode(key: &str, format: GLenum, binary: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 12 + key.len() + binary.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(key.as_bytes());
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(binary);
+    bytes
+}
+
+/// Returns the binary format and binary if `bytes` is a complete entry stored under `key`.
+pub fn decode<'a>(key: &str, bytes: &'a [u8]) -> Option<(GLenum, &'a [u8])> {
+    let rest = bytes.strip_prefix(MAGIC)?;
+    let (len, rest) = rest.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len) as usize;
+    if rest.get(..len)? != key.as_bytes() {
+        return None;
+    }
+    let (format, rest) = rest[len..].split_first_chunk::<4>()?;
+    let (len, binary) = rest.split_first_chunk::<4>()?;
+    if binary.is_empty() || binary.len() != u32::from_le_bytes(*len) as usize {
+        return None;
+    }
+
+    Some((GLenum::from_le_bytes(*format), binary))
+}
+
+/// What the cache may do with the files it owns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl CachePolicy {
+    /// The cache is enabled by the `save-blob` feature and bypassed with `force-compile`.
+    pub const FEATURES: Self = Self {
+        read: cfg!(feature = "save-blob") && !cfg!(feature = "force-compile"),
+        write: cfg!(feature = "save-blob") && !cfg!(feature = "force-compile"),
+    };
+}
+
+/// On-disk cache of linked programs, see [`CachePolicy::FEATURES`].
+pub struct ProgramCache {
+    key: String,
+    path: PathBuf,
+    policy: CachePolicy,
+}
+
+impl ProgramCache {
+    /// `None` if the features turn it off or the context cannot hand out program binaries,
+    /// which needs `ARB_get_program_binary` on GL before 4.1.
+    pub fn new(sources: &[&str]) -> Option<Self> {
+        let policy = CachePolicy::FEATURES;
+        if !policy.read && !policy.write
+            || !gl::GetProgramBinary::is_loaded()
+            || !gl::ProgramBinary::is_loaded()
+            || !gl::ProgramParameteri::is_loaded()
+        {
+            return None;
+        }
+
+        let mut formats = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats) };
+        if formats <= 0 {
+            return None;
+        }
+
+        let key = cache_key(
+            &gl_string(gl::VENDOR),
+            &gl_string(gl::RENDERER),
+            &gl_string(gl::VERSION),
+            sources,
+        );
+        Some(Self::in_dir(
+            &std::env::temp_dir().join("egui-opengl-internal"),
+            key,
+            policy,
+        ))
+    }
+
+    pub fn in_dir(dir: &Path, key: String, policy: CachePolicy) -> Self {
+        let path = dir.join(format!("{:016x}.bin", fnv1a(FNV_OFFSET, key.as_bytes())));
+        Self { key, path, policy }
+    }
+
+    /// Creates a program from the stored binary. Anything the driver rejects is deleted so it gets rebuilt.
+    ///
+    /// An entry under another key is left alone, it may belong to another driver whose key hashes to the same name.
+    pub fn load(&self) -> Option<GLuint> {
+        if !self.policy.read {
+            return None;
+        }
+
+        let bytes = std::fs::read(&self.path).ok()?;
+        let (format, binary) = decode(&self.key, &bytes)?;
+
+        unsafe {
+            let program = gl::CreateProgram();
+            gl::ProgramBinary(
+                program,
+                format,
+                binary.as_ptr() as *const _,
+                binary.len() as GLsizei,
+            );
+
+            let mut status = gl::FALSE as GLint;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+            if status == gl::TRUE as GLint {
+                return Some(program);
+            }
+
+            gl::DeleteProgram(program);
+        }
+        let _ = std::fs::remove_file(&self.path);
+        None
+    }
+
+    /// Best effort, a program that cannot be saved just gets compiled again next time.
+    pub fn store(&self, program: GLuint) {
+        if !self.policy.write {
+            return;
+        }
+
+        let mut len = 0;
+        unsafe { gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut len) };
+        if len <= 0 {
+            return;
+        }
+
+        let mut binary = vec![0u8; len as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                program,
+                len,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut _,
+            );
+        }
+        binary.truncate(written.max(0) as usize);
+        if binary.is_empty() {
+            return;
+        }
+
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(&self.path, encode(&self.key, format, &binary));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let key = cache_key(
+            "NVIDIA Corporation",
+            "RTX 4070",
+            "4.6.0 NVIDIA 537.58",
+            &["v", "f"],
+        );
+        let bytes = encode(&key, 0x8e21, &[1, 2, 3, 4, 5]);
+
+        assert_eq!(decode(&key, &bytes), Some((0x8e21, &[1u8, 2, 3, 4, 5][..])));
+        // Another key in the same file, e.g. after a hash collision, is a miss.
+        assert_eq!(decode("other", &bytes), None);
+        // Truncated writes are rejected instead of being handed to the driver.
+        assert_eq!(decode(&key, &bytes[..bytes.len() - 2]), None);
+        assert_eq!(decode(&key, &bytes[..10]), None);
+        assert_eq!(decode(&key, b"not a cache entry"), None);
+    }
+
+    #[test]
+    fn test_cache_key_changes_with_driver_and_sources() {
+        let key = |version: &str, sources: &[&str]| cache_key("Intel", "Iris Xe", version, sources);
+
+        assert_eq!(
+            key("4.6.0 - Build 31.0.101.4502", &["v", "f"]),
+            key("4.6.0 - Build 31.0.101.4502", &["v", "f"])
+        );
+        assert_ne!(
+            key("4.6.0 - Build 31.0.101.4502", &["v", "f"]),
+            key("4.6.0 - Build 31.0.101.5186", &["v", "f"])
+        );
+        assert_ne!(key("4.6.0", &["v", "f"]), key("4.6.0", &["v", "g"]));
+        assert_ne!(key("4.6.0", &["v", "f"]), key("4.6.0", &["f", "v"]));
+
+        let dir = Path::new("cache");
+        let policy = CachePolicy::FEATURES;
+        assert_ne!(
+            ProgramCache::in_dir(dir, key("4.6.0", &["v", "f"]), policy).path,
+            ProgramCache::in_dir(dir, key("4.6.1", &["v", "f"]), policy).path
+        );
+    }
+
+    #[test]
+    fn test_policy_gates_files() {
+        let dir =
+            std::env::temp_dir().join(format!("egui-opengl-internal-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = cache_key("Mesa", "llvmpipe", "4.5", &["v", "f"]);
+
+        // Neither calls into GL, which is not loaded here.
+        let write_only = CachePolicy {
+            read: false,
+            write: true,
+        };
+        let cache = ProgramCache::in_dir(&dir, key.clone(), write_only);
+        std::fs::write(&cache.path, encode(&key, 0x8e21, &[1, 2, 3])).unwrap();
+        assert_eq!(cache.load(), None);
+
+        let off = CachePolicy {
+            read: false,
+            write: false,
+        };
+        let cache = ProgramCache::in_dir(&dir, cache_key("Mesa", "llvmpipe", "4.6", &[]), off);
+        cache.store(1);
+        assert!(!cache.path.exists());
+
+        // Another driver's entry under the same name is a miss and stays.
+        let read = CachePolicy {
+            read: true,
+            write: true,
+        };
+        let cache = ProgramCache::in_dir(&dir, key, read);
+        std::fs::write(&cache.path, encode("other", 0x8e21, &[1, 2, 3])).unwrap();
+        assert_eq!(cache.load(), None);
+        assert!(cache.path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,27 @@
-#version 100
-
-uniform sampler2D u_sampler;
+// The version directive and feature defines are prepended by `GlslVersion::sources`,
+// see `vertex.vert` for what they mean.
 
+#ifdef GL_ES
+#ifdef GL_FRAGMENT_PRECISION_HIGH
 precision highp float;
+#else
+precision mediump float;
+#endif
+#endif
+
+#ifdef NEW_SHADER_INTERFACE
+#define I in
+#define texture2D texture
+out vec4 f_color;
+#else
+#define I varying
+#define f_color gl_FragColor
+#endif
+
+uniform sampler2D u_sampler;
 
-varying vec2 v_tc;
-varying vec4 v_rgba;
+I vec2 v_tc;
+I vec4 v_rgba;
 
 // 0-1 linear  from  0-255 sRGB
 vec3 linear_from_srgb(vec3 srgb) {
@@ -33,7 +49,22 @@ vec4 srgba_from_linear(vec4 rgba) {
     return vec4(srgb_from_linear(rgba.rgb), 255.0 * rgba.a);
 }
 
+// 0-1 sRGB gamma  from  0-1 linear
+vec3 srgb_gamma_from_linear(vec3 rgb) {
+    bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
+    vec3 lower = rgb * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, vec3(cutoff));
+}
+
 void main() {
+#ifdef SRGB_TEXTURES
+    // SRGB8_ALPHA8 textures are filtered in linear space and decoded by the sampler.
+    vec4 texture_linear = texture2D(u_sampler, v_tc);
+    vec4 texture_in_gamma = vec4(srgb_gamma_from_linear(texture_linear.rgb), texture_linear.a);
+    // egui multiplies and blends in gamma space, so FRAMEBUFFER_SRGB stays off for this path.
+    f_color = v_rgba * texture_in_gamma;
+#else
     // We must decode the colors, since WebGL1 doesn't come with sRGBA textures:
     vec4 texture_rgba = linear_from_srgba(texture2D(u_sampler, v_tc) * 255.0);
     // WebGL1 doesn't support linear blending in the framebuffer,
@@ -52,5 +83,6 @@ void main() {
         texture_rgba.rgb *= texture_rgba.a;
     }
     /// Multiply vertex color with texture color (in linear space).
-    gl_FragColor = v_rgba * texture_rgba;
-}
\ No newline at end of file
+    f_color = v_rgba * texture_rgba;
+#endif
+}
@@ -1,15 +1,226 @@
-use gl::types::{GLchar, GLenum, GLint, GLuint};
+use crate::{Error, Result};
+use cache::ProgramCache;
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
+use std::ffi::CStr;
+
+mod cache;
+
+/// Reads a `glGetString` value, empty if the query is not supported.
+pub fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let ptr = gl::GetString(name);
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr as *const _)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+}
+
+/// Context version as reported by `GL_VERSION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub es: bool,
+}
+
+impl GlVersion {
+    /// Parses strings like `4.6.0 NVIDIA 537.58` or `OpenGL ES 3.2 Mesa 23.2.1`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let (es, rest) = match version.strip_prefix("OpenGL ES") {
+            // Skips profile markers like `-CM` on GLES 1.x.
+            Some(rest) => (true, rest.trim_start_matches(|c: char| !c.is_ascii_digit())),
+            None => (false, version),
+        };
+
+        let number = rest.split_whitespace().next()?;
+        let mut parts = number.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts
+            .next()
+            .and_then(|minor| minor.parse().ok())
+            .unwrap_or(0);
+
+        Some(Self { major, minor, es })
+    }
+
+    /// Version of the current context. Unparsable strings are treated as GL 2.0, which the legacy shader supports.
+    pub fn current() -> Self {
+        Self::parse(&gl_string(gl::VERSION)).unwrap_or(Self {
+            major: 2,
+            minor: 0,
+            es: false,
+        })
+    }
+
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+}
+
+/// Parses `GL_SHADING_LANGUAGE_VERSION` strings like `4.60 NVIDIA`, `1.20` or `OpenGL ES GLSL ES 3.00`
+/// into `(major, minor)`, with the minor version in hundredths as in `#version` directives.
+pub fn parse_glsl_version(version: &str) -> Option<(u32, u32)> {
+    let number = version
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split_whitespace()
+        .next()?;
+    let (major, minor) = number.split_once('.').unwrap_or((number, "0"));
+    let major = major.parse().ok()?;
+    // `1.2` means 1.20, and some drivers append a patch level like `4.60.0`.
+    let minor: String = minor.chars().take_while(char::is_ascii_digit).collect();
+    let minor = format!("{minor:0<2}")[..2].parse().ok()?;
+
+    Some((major, minor))
+}
+
+/// What the shader generator needs to know about the current context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextInfo {
+    pub version: GlVersion,
+    /// Parsed `GL_SHADING_LANGUAGE_VERSION`, `None` if the driver did not report a usable one.
+    pub glsl: Option<(u32, u32)>,
+    /// Whether `CONTEXT_PROFILE_MASK` has the core bit set, so the fixed-function era keywords are gone.
+    pub core_profile: bool,
+}
+
+impl ContextInfo {
+    pub fn current() -> Self {
+        let version = GlVersion::current();
+        let glsl = parse_glsl_version(&gl_string(gl::SHADING_LANGUAGE_VERSION));
+
+        // Profiles only exist on desktop GL 3.2 and newer, older contexts reject the query.
+        let core_profile = !version.es
+            && version.at_least(3, 2)
+            && unsafe {
+                let mut mask = 0;
+                gl::GetIntegerv(gl::CONTEXT_PROFILE_MASK, &mut mask);
+                mask as GLenum & gl::CONTEXT_CORE_PROFILE_BIT != 0
+            };
+
+        Self {
+            version,
+            glsl,
+            core_profile,
+        }
+    }
+}
+
+/// GLSL dialect the shaders are generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslVersion {
+    /// Desktop GL 2.1.
+    V120,
+    /// Desktop GL 3.1 and compatibility contexts without GLSL 3.30.
+    V140,
+    /// Desktop GL 3.3+, required by core profiles on macOS and some Mesa drivers.
+    V330Core,
+    /// OpenGL ES 2.0 and WebGL-style ANGLE contexts.
+    Es100,
+    /// OpenGL ES 3.0+.
+    Es300,
+}
+
+impl GlslVersion {
+    /// Picks the newest dialect the context can compile that the templates support.
+    /// The GL version stands in for the GLSL one when the driver does not report it.
+    pub fn select(context: ContextInfo) -> Self {
+        let version = context.version;
+        let glsl = context
+            .glsl
+            .unwrap_or(match (version.major, version.minor) {
+                _ if version.es => (version.major.max(1), 0),
+                (2, 0) => (1, 10),
+                (2, _) => (1, 20),
+                (3, 0) => (1, 30),
+                (3, 1) => (1, 40),
+                (3, 2) => (1, 50),
+                (major, minor) => (major, minor * 10),
+            });
+
+        if version.es {
+            if glsl >= (3, 0) {
+                GlslVersion::Es300
+            } else {
+                GlslVersion::Es100
+            }
+        } else if glsl >= (3, 30) {
+            GlslVersion::V330Core
+        } else if glsl >= (1, 40) || context.core_profile {
+            GlslVersion::V140
+        } else {
+            GlslVersion::V120
+        }
+    }
+
+    /// The `#version` directive heading both shaders.
+    pub fn directive(self) -> &'static str {
+        match self {
+            GlslVersion::V120 => "#version 120",
+            GlslVersion::V140 => "#version 140",
+            GlslVersion::V330Core => "#version 330 core",
+            GlslVersion::Es100 => "#version 100",
+            GlslVersion::Es300 => "#version 300 es",
+        }
+    }
+
+    /// Whether `in`/`out` replace `attribute`/`varying`/`gl_FragColor`.
+    pub fn new_shader_interface(self) -> bool {
+        !matches!(self, GlslVersion::V120 | GlslVersion::Es100)
+    }
+
+    /// Whether textures are uploaded as `SRGB8_ALPHA8` and the shader blends in gamma space exactly like egui expects.
+    /// Older dialects decode sRGB by hand and rely on `FRAMEBUFFER_SRGB` instead.
+    pub fn srgb_textures(self) -> bool {
+        self.new_shader_interface()
+    }
+
+    /// Vertex and fragment sources generated from the shared templates.
+    pub fn sources(self) -> (String, String) {
+        let mut header = format!("{}\n", self.directive());
+        if self.new_shader_interface() {
+            header.push_str("#define NEW_SHADER_INTERFACE\n");
+        }
+        if self.srgb_textures() {
+            header.push_str("#define SRGB_TEXTURES\n");
+        }
+
+        (
+            format!("{header}{}", include_str!("vertex.vert")),
+            format!("{header}{}", include_str!("fragment.frag")),
+        )
+    }
+}
+
+/// Reads a shader or program info log through the matching `glGet*iv`/`glGet*InfoLog` pair.
+unsafe fn info_log(
+    object: GLuint,
+    get_iv: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut len: GLint = 0;
+    get_iv(object, gl::INFO_LOG_LENGTH, &mut len);
+
+    let mut buf = vec![0u8; len.max(0) as usize];
+    let mut written: GLsizei = 0;
+    get_log(object, len, &mut written, buf.as_mut_ptr() as *mut GLchar);
+    buf.truncate(written.max(0) as usize);
+
+    String::from_utf8_lossy(&buf).into_owned()
+}
 
 pub struct Shader;
 
 impl Shader {
-    pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
+    pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint> {
         let id = unsafe { gl::CreateShader(ty) };
         unsafe {
-            let ptr: *const u8 = src.as_bytes().as_ptr();
-            let ptr_i8: *const i8 = std::mem::transmute(ptr);
+            let ptr = src.as_ptr() as *const GLchar;
             let len = src.len() as GLint;
-            gl::ShaderSource(id, 1, &ptr_i8, &len);
+            gl::ShaderSource(id, 1, &ptr, &len);
         }
 
         let successful = unsafe {
@@ -21,16 +232,59 @@ impl Shader {
         };
 
         if !successful {
-            panic!()
+            let log = unsafe {
+                let log = info_log(id, gl::GetShaderiv, gl::GetShaderInfoLog);
+                gl::DeleteShader(id);
+                log
+            };
+
+            let stage = match ty {
+                gl::VERTEX_SHADER => "vertex",
+                gl::FRAGMENT_SHADER => "fragment",
+                _ => "unknown",
+            };
+            return Err(Error::ShaderCompile { stage, log });
         }
 
-        id
+        Ok(id)
     }
 
-    pub fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
+    /// Loads the program from the binary cache when possible, otherwise compiles and links it and caches the result.
+    pub fn build_program(vertex_src: &str, fragment_src: &str) -> Result<GLuint> {
+        let cache = ProgramCache::new(&[vertex_src, fragment_src]);
+        if let Some(program) = cache.as_ref().and_then(ProgramCache::load) {
+            return Ok(program);
+        }
+
+        let vs = Self::compile_shader(vertex_src, gl::VERTEX_SHADER)?;
+        let fs = Self::compile_shader(fragment_src, gl::FRAGMENT_SHADER)
+            .inspect_err(|_| unsafe { gl::DeleteShader(vs) })?;
+
+        let program = Self::link_program(vs, fs, cache.is_some());
+        unsafe {
+            gl::DeleteShader(vs);
+            gl::DeleteShader(fs);
+        }
+        let program = program?;
+
+        if let Some(cache) = cache {
+            cache.store(program);
+        }
+        Ok(program)
+    }
+
+    /// `retrievable` asks the driver to keep the binary around for `glGetProgramBinary`, if it can.
+    pub fn link_program(vs: GLuint, fs: GLuint, retrievable: bool) -> Result<GLuint> {
         let program = unsafe { gl::CreateProgram() };
 
         unsafe {
+            if retrievable && gl::ProgramParameteri::is_loaded() {
+                gl::ProgramParameteri(
+                    program,
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as GLint,
+                );
+            }
             gl::AttachShader(program, vs);
             gl::AttachShader(program, fs);
             gl::LinkProgram(program);
@@ -42,28 +296,108 @@ impl Shader {
         }
 
         if status != (gl::TRUE as GLint) {
-            let mut len: GLint = 0;
-            unsafe {
-                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-            }
+            let log = unsafe {
+                let log = info_log(program, gl::GetProgramiv, gl::GetProgramInfoLog);
+                gl::DeleteProgram(program);
+                log
+            };
 
-            let mut buf = vec![0; len as usize];
+            return Err(Error::Link { log });
+        }
 
-            unsafe {
-                gl::GetProgramInfoLog(
-                    program,
-                    len,
-                    core::ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut GLchar,
-                );
-            }
+        Ok(program)
+    }
+}
 
-            panic!(
-                "{}",
-                core::str::from_utf8(&buf).expect("ProgramInfoLog not valid utf8")
-            );
-        }
+#[test]
+fn test_parse_glsl_version() {
+    assert_eq!(parse_glsl_version("4.60 NVIDIA"), Some((4, 60)));
+    assert_eq!(parse_glsl_version("4.60.0"), Some((4, 60)));
+    assert_eq!(parse_glsl_version("1.20"), Some((1, 20)));
+    assert_eq!(parse_glsl_version("1.2"), Some((1, 20)));
+    assert_eq!(parse_glsl_version("OpenGL ES GLSL ES 3.00"), Some((3, 0)));
+    assert_eq!(parse_glsl_version("OpenGL ES GLSL ES 1.0.17"), Some((1, 0)));
+    assert_eq!(parse_glsl_version(""), None);
+}
+
+#[test]
+fn test_select_glsl_version() {
+    let select = |version: &str, glsl: &str, core_profile| {
+        GlslVersion::select(ContextInfo {
+            version: GlVersion::parse(version).unwrap(),
+            glsl: parse_glsl_version(glsl),
+            core_profile,
+        })
+    };
+
+    assert_eq!(select("2.1 Mesa 10.1", "1.20", false), GlslVersion::V120);
+    assert_eq!(select("2.0", "", false), GlslVersion::V120);
+    assert_eq!(select("3.0 Mesa 23.2.1", "1.30", false), GlslVersion::V120);
+    assert_eq!(select("3.1.0", "1.40", false), GlslVersion::V140);
+    assert_eq!(select("3.2.0", "1.50", true), GlslVersion::V140);
+    assert_eq!(
+        select("3.3 (Core Profile) Mesa 23.2.1", "3.30", true),
+        GlslVersion::V330Core
+    );
+    assert_eq!(
+        select("4.6.0 NVIDIA 537.58", "4.60 NVIDIA", false),
+        GlslVersion::V330Core
+    );
+    assert_eq!(select("4.1 INTEL", "", true), GlslVersion::V330Core);
+    assert_eq!(
+        select(
+            "OpenGL ES 2.0 (ANGLE 2.1.0)",
+            "OpenGL ES GLSL ES 1.00",
+            false
+        ),
+        GlslVersion::Es100
+    );
+    assert_eq!(
+        select("OpenGL ES 3.2 Mesa 23.2.1", "OpenGL ES GLSL ES 3.20", false),
+        GlslVersion::Es300
+    );
+    assert_eq!(select("OpenGL ES 3.0", "", false), GlslVersion::Es300);
+}
+
+#[test]
+fn test_shader_sources_header() {
+    let (vertex, fragment) = GlslVersion::V330Core.sources();
+    assert!(vertex
+        .starts_with("#version 330 core\n#define NEW_SHADER_INTERFACE\n#define SRGB_TEXTURES\n"));
+    assert!(fragment.starts_with("#version 330 core\n"));
 
-        program
+    let (vertex, _) = GlslVersion::Es100.sources();
+    assert!(vertex.starts_with("#version 100\n"));
+    assert!(!vertex.contains("#define NEW_SHADER_INTERFACE"));
+    assert!(!vertex.contains("#define SRGB_TEXTURES"));
+
+    for version in [
+        GlslVersion::V120,
+        GlslVersion::V140,
+        GlslVersion::V330Core,
+        GlslVersion::Es100,
+        GlslVersion::Es300,
+    ] {
+        let (vertex, fragment) = version.sources();
+        assert_eq!(vertex.matches("#version").count(), 1);
+        assert_eq!(fragment.matches("#version").count(), 1);
     }
 }
+
+#[test]
+fn test_parse_gl_version() {
+    let parse = |s| GlVersion::parse(s).map(|v| (v.major, v.minor, v.es));
+
+    assert_eq!(parse("4.6.0 NVIDIA 537.58"), Some((4, 6, false)));
+    assert_eq!(parse("3.3 (Core Profile) Mesa 23.2.1"), Some((3, 3, false)));
+    assert_eq!(parse("2.1 Mesa 10.1"), Some((2, 1, false)));
+    assert_eq!(parse("OpenGL ES 3.2 Mesa 23.2.1"), Some((3, 2, true)));
+    assert_eq!(parse("OpenGL ES 2.0 (ANGLE 2.1.0)"), Some((2, 0, true)));
+    assert_eq!(parse("OpenGL ES-CM 1.1"), Some((1, 1, true)));
+    assert_eq!(parse(""), None);
+
+    let version = GlVersion::parse("3.1.0").unwrap();
+    assert!(version.at_least(3, 1));
+    assert!(version.at_least(2, 9));
+    assert!(!version.at_least(3, 2));
+}
@@ -1,13 +1,23 @@
-#version 100
+// The version directive and feature defines are prepended by `GlslVersion::sources`:
+// NEW_SHADER_INTERFACE - `in`/`out` instead of `attribute`/`varying` (GLSL 1.40+, ES 3.00).
+// SRGB_TEXTURES - textures are SRGB8_ALPHA8 and colors stay in gamma space.
+
+#ifdef NEW_SHADER_INTERFACE
+#define I in
+#define O out
+#else
+#define I attribute
+#define O varying
+#endif
 
 uniform vec2 u_screen_size;
 
-attribute vec2 a_pos;
-attribute vec2 a_tc;
-attribute vec4 a_srgba;
+I vec2 a_pos;
+I vec2 a_tc;
+I vec4 a_srgba;
 
-varying vec2 v_tc;
-varying vec4 v_rgba;
+O vec2 v_tc;
+O vec4 v_rgba;
 
 // 0-1 linear  from  0-255 sRGB
 vec3 linear_from_srgb(vec3 srgb) {
@@ -15,8 +25,6 @@ vec3 linear_from_srgb(vec3 srgb) {
     vec3 lower = srgb / vec3(3294.6);
     vec3 higher = pow((srgb + vec3(14.025)) / vec3(269.025), vec3(2.4));
     return mix(higher, lower, vec3(cutoff));
-
-
 }
 
 // 0-1 linear  from  0-255 sRGBA
@@ -24,19 +32,6 @@ vec4 linear_from_srgba(vec4 srgba) {
     return vec4(linear_from_srgb(srgba.rgb), srgba.a / 255.0);
 }
 
-// 0-255 sRGB  from  0-1 linear
-vec3 srgb_from_linear(vec3 rgb) {
-    bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
-    vec3 lower = rgb * vec3(3294.6);
-    vec3 higher = vec3(269.025) * pow(rgb, vec3(1.0 / 2.4)) - vec3(14.025);
-    return mix(higher, lower, vec3(cutoff));
-}
-
-// 0-255 sRGBA  from  0-1 linear
-vec4 srgba_from_linear(vec4 rgba) {
-    return vec4(srgb_from_linear(rgba.rgb), 255.0 * rgba.a);
-}
-
 void main() {
     gl_Position = vec4(
         2.0 * a_pos.x / u_screen_size.x - 1.0,
@@ -44,6 +39,11 @@ void main() {
         0.0,
     1.0);
     v_tc = a_tc;
+#ifdef SRGB_TEXTURES
+    // egui's vertex colors are premultiplied sRGBA and stay that way:
+    v_rgba = a_srgba / 255.0;
+#else
     v_rgba = linear_from_srgba(a_srgba);
     v_rgba.a = pow(v_rgba.a, 1.6);
-}
\ No newline at end of file
+#endif
+}
@@ -24,12 +24,11 @@ pub unsafe fn get_proc_address(function_name: &str) -> *const usize {
     let c_proc_name = CString::new(function_name).unwrap();
     let process_address = wglGetProcAddress(PCSTR::from_raw(c_proc_name.as_ptr() as *const u8));
 
-    if let Some(process_address) = process_address {
-        return process_address as _;
+    // Some drivers return small sentinel values instead of null for unknown functions.
+    match process_address.map(|f| f as usize) {
+        Some(0..=3 | usize::MAX) | None => std::ptr::null(),
+        Some(address) => address as _,
     }
-
-    // this shouldn't silently error tbh, but im going to copy the old behavior
-    std::ptr::null()
 }
 
 pub fn get_module(module_name: &str) -> HMODULE {
@@ -0,0 +1,171 @@
+use egui::{Event, Key, Modifiers, PointerButton, Pos2};
+
+/// Follows the focus of the window and what egui saw go down, because the releases go to whichever
+/// window has the focus then.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusState {
+    focused: bool,
+    keys: Vec<(Key, Option<Key>)>,
+    buttons: Vec<PointerButton>,
+    pointer: Pos2,
+}
+
+impl Default for FocusState {
+    fn default() -> Self {
+        // An overlay usually starts in the window the player is looking at.
+        Self {
+            focused: true,
+            keys: vec![],
+            buttons: vec![],
+            pointer: Pos2::ZERO,
+        }
+    }
+}
+
+impl FocusState {
+    pub fn focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Follows an event that was given to egui.
+    pub fn track(&mut self, event: &Event) {
+        match *event {
+            Event::Key {
+                key,
+                physical_key,
+                pressed,
+                ..
+            } => {
+                self.keys.retain(|held| held.0 != key);
+                if pressed {
+                    self.keys.push((key, physical_key));
+                }
+            }
+            Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                ..
+            } => {
+                self.pointer = pos;
+                self.buttons.retain(|held| *held != button);
+                if pressed {
+                    self.buttons.push(button);
+                }
+            }
+            Event::PointerMoved(pos) => self.pointer = pos,
+            _ => {}
+        }
+    }
+
+    /// Pushes [`Event::WindowFocused`] if the focus changed, after releasing every held key and button when it was lost.
+    /// Returns whether it changed, as `WM_ACTIVATE` and `WM_SETFOCUS` both report the same change.
+    pub fn set_focused(&mut self, focused: bool, events: &mut Vec<Event>) -> bool {
+        if self.focused == focused {
+            return false;
+        }
+        self.focused = focused;
+
+        if !focused {
+            for (key, physical_key) in self.keys.drain(..) {
+                events.push(Event::Key {
+                    key,
+                    physical_key,
+                    pressed: false,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                });
+            }
+            for button in self.buttons.drain(..) {
+                events.push(Event::PointerButton {
+                    pos: self.pointer,
+                    button,
+                    pressed: false,
+                    modifiers: Modifiers::NONE,
+                });
+            }
+        }
+        events.push(Event::WindowFocused(focused));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    fn key(key: Key, pressed: bool) -> Event {
+        Event::Key {
+            key,
+            physical_key: Some(key),
+            pressed,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    fn button(button: PointerButton, pos: Pos2, pressed: bool) -> Event {
+        Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_focus_loss_releases_held_input() {
+        let mut state = FocusState::default();
+        for event in [
+            key(Key::W, true),
+            key(Key::W, true),
+            key(Key::A, true),
+            key(Key::A, false),
+            button(PointerButton::Primary, pos2(1.0, 2.0), true),
+            button(PointerButton::Secondary, pos2(1.0, 2.0), true),
+            button(PointerButton::Secondary, pos2(1.0, 2.0), false),
+            Event::PointerMoved(pos2(5.0, 6.0)),
+        ] {
+            state.track(&event);
+        }
+
+        let mut events = vec![];
+        assert!(state.set_focused(false, &mut events));
+        assert_eq!(
+            events,
+            [
+                key(Key::W, false),
+                button(PointerButton::Primary, pos2(5.0, 6.0), false),
+                Event::WindowFocused(false),
+            ]
+        );
+        assert!(!state.focused());
+
+        // Nothing is held anymore.
+        events.clear();
+        assert!(state.set_focused(true, &mut events));
+        assert!(state.set_focused(false, &mut events));
+        assert_eq!(
+            events,
+            [Event::WindowFocused(true), Event::WindowFocused(false)]
+        );
+    }
+
+    #[test]
+    fn test_repeated_reports() {
+        let mut state = FocusState::default();
+        let mut events = vec![];
+
+        // Activation and keyboard focus come together.
+        assert!(!state.set_focused(true, &mut events));
+        assert!(state.set_focused(false, &mut events));
+        assert!(!state.set_focused(false, &mut events));
+        assert!(state.set_focused(true, &mut events));
+        assert!(!state.set_focused(true, &mut events));
+        assert_eq!(
+            events,
+            [Event::WindowFocused(false), Event::WindowFocused(true)]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use egui::{Event, ImeEvent, Rect};
+
+pub const WM_IME_STARTCOMPOSITION: u32 = 0x010D;
+pub const WM_IME_ENDCOMPOSITION: u32 = 0x010E;
+pub const WM_IME_COMPOSITION: u32 = 0x010F;
+
+/// `WM_IME_COMPOSITION` flag of a changed composition string.
+pub const GCS_COMPSTR: u32 = 0x0008;
+/// `WM_IME_COMPOSITION` flag of a finished result string.
+pub const GCS_RESULTSTR: u32 = 0x0800;
+
+/// Follows an IMM32 composition to turn its messages into [`Event::Ime`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImeState {
+    /// `Enabled` was sent and no `Commit` or `Disabled` since.
+    enabled: bool,
+    /// The text edit shows a non-empty preedit string.
+    preedit: bool,
+}
+
+impl ImeState {
+    /// Pushes the events of a `WM_IME_*` message. Returns `false` if `umsg` is no composition message.
+    ///
+    /// `read` returns the composition string of a `GCS_*` flag, e.g. from `ImmGetCompositionStringW`.
+    /// Egui ends the composition on `Commit`, so a string that is committed while the next one is
+    /// composed, like Korean syllables, starts another one.
+    pub fn process(
+        &mut self,
+        umsg: u32,
+        lparam: isize,
+        mut read: impl FnMut(u32) -> Option<String>,
+        events: &mut Vec<Event>,
+    ) -> bool {
+        match umsg {
+            WM_IME_STARTCOMPOSITION => self.enable(events),
+            WM_IME_COMPOSITION => {
+                let flags = lparam as u32;
+
+                if flags & GCS_RESULTSTR != 0 {
+                    if let Some(text) = read(GCS_RESULTSTR) {
+                        self.enable(events);
+                        events.push(Event::Ime(ImeEvent::Commit(text)));
+                        self.enabled = false;
+                        self.preedit = false;
+                    }
+                }
+
+                if flags & GCS_COMPSTR != 0 {
+                    if let Some(text) = read(GCS_COMPSTR) {
+                        self.preedit(text, events);
+                    }
+                } else if flags & GCS_RESULTSTR == 0 {
+                    // Cancelled, e.g. with escape.
+                    self.preedit(String::new(), events);
+                }
+            }
+            WM_IME_ENDCOMPOSITION => {
+                if self.preedit {
+                    self.preedit(String::new(), events);
+                }
+                events.push(Event::Ime(ImeEvent::Disabled));
+                self.enabled = false;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn enable(&mut self, events: &mut Vec<Event>) {
+        if !self.enabled {
+            events.push(Event::Ime(ImeEvent::Enabled));
+            self.enabled = true;
+        }
+    }
+
+    fn preedit(&mut self, text: String, events: &mut Vec<Event>) {
+        if text.is_empty() && !self.preedit {
+            return;
+        }
+        self.enable(events);
+        self.preedit = !text.is_empty();
+        events.push(Event::Ime(ImeEvent::Preedit(text)));
+    }
+}
+
+/// Client area in pixels as `[left, top, right, bottom]` for the candidate window to keep clear of,
+/// from egui's `platform_output.ime.cursor_rect`.
+pub fn candidate_area(cursor_rect: Rect, pixels_per_point: f32) -> [i32; 4] {
+    let rect = cursor_rect * pixels_per_point;
+    [
+        rect.left().floor() as i32,
+        rect.top().floor() as i32,
+        rect.right().ceil() as i32,
+        rect.bottom().ceil() as i32,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2};
+
+    fn ime(event: ImeEvent) -> Event {
+        Event::Ime(event)
+    }
+
+    /// Runs messages of `(umsg, flags)` with the composition and result strings as they were at that message.
+    fn run(messages: &[(u32, u32, &str, &str)]) -> Vec<Event> {
+        let mut state = ImeState::default();
+        let mut events = vec![];
+        for &(umsg, flags, composition, result) in messages {
+            let read = |flag| {
+                let text = if flag == GCS_RESULTSTR {
+                    result
+                } else {
+                    composition
+                };
+                Some(text.to_owned())
+            };
+            assert!(state.process(umsg, flags as isize, read, &mut events));
+        }
+        events
+    }
+
+    #[test]
+    fn test_japanese_composition() {
+        let events = run(&[
+            (WM_IME_STARTCOMPOSITION, 0, "", ""),
+            (WM_IME_COMPOSITION, GCS_COMPSTR, "に", ""),
+            (WM_IME_COMPOSITION, GCS_COMPSTR, "にほん", ""),
+            (WM_IME_COMPOSITION, GCS_COMPSTR, "日本", ""),
+            (WM_IME_COMPOSITION, GCS_RESULTSTR, "", "日本"),
+            (WM_IME_ENDCOMPOSITION, 0, "", ""),
+        ]);
+        assert_eq!(
+            events,
+            [
+                ime(ImeEvent::Enabled),
+                ime(ImeEvent::Preedit("に".into())),
+                ime(ImeEvent::Preedit("にほん".into())),
+                ime(ImeEvent::Preedit("日本".into())),
+                ime(ImeEvent::Commit("日本".into())),
+                ime(ImeEvent::Disabled),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_korean_commits_while_composing() {
+        let events = run(&[
+            (WM_IME_STARTCOMPOSITION, 0, "", ""),
+            (WM_IME_COMPOSITION, GCS_COMPSTR, "한", ""),
+            (WM_IME_COMPOSITION, GCS_RESULTSTR | GCS_COMPSTR, "ㄱ", "한"),
+            (WM_IME_COMPOSITION, GCS_RESULTSTR, "", "글"),
+            (WM_IME_ENDCOMPOSITION, 0, "", ""),
+        ]);
+        assert_eq!(
+            events,
+            [
+                ime(ImeEvent::Enabled),
+                ime(ImeEvent::Preedit("한".into())),
+                ime(ImeEvent::Commit("한".into())),
+                ime(ImeEvent::Enabled),
+                ime(ImeEvent::Preedit("ㄱ".into())),
+                ime(ImeEvent::Commit("글".into())),
+                ime(ImeEvent::Disabled),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cancelled_composition() {
+        // Escape clears the composition, then it ends without a result.
+        let events = run(&[
+            (WM_IME_STARTCOMPOSITION, 0, "", ""),
+            (WM_IME_COMPOSITION, GCS_COMPSTR, "ni", ""),
+            (WM_IME_COMPOSITION, 0, "", ""),
+            (WM_IME_ENDCOMPOSITION, 0, "", ""),
+        ]);
+        assert_eq!(
+            events,
+            [
+                ime(ImeEvent::Enabled),
+                ime(ImeEvent::Preedit("ni".into())),
+                ime(ImeEvent::Preedit(String::new())),
+                ime(ImeEvent::Disabled),
+            ]
+        );
+
+        // Or it ends right away and the preedit string has to be removed.
+        let events = run(&[
+            (WM_IME_COMPOSITION, GCS_COMPSTR, "ni", ""),
+            (WM_IME_ENDCOMPOSITION, 0, "", ""),
+        ]);
+        assert_eq!(
+            events,
+            [
+                ime(ImeEvent::Enabled),
+                ime(ImeEvent::Preedit("ni".into())),
+                ime(ImeEvent::Preedit(String::new())),
+                ime(ImeEvent::Disabled),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_messages_and_unreadable_strings() {
+        let mut state = ImeState::default();
+        let mut events = vec![];
+        assert!(!state.process(0x0102, 0, |_| None, &mut events));
+        assert!(state.process(
+            WM_IME_COMPOSITION,
+            GCS_COMPSTR as isize,
+            |_| None,
+            &mut events
+        ));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_candidate_area() {
+        let cursor = Rect::from_min_size(pos2(10.2, 20.5), vec2(1.0, 14.0));
+        assert_eq!(candidate_area(cursor, 1.0), [10, 20, 12, 35]);
+        assert_eq!(candidate_area(cursor, 2.0), [20, 41, 23, 69]);
+    }
+}
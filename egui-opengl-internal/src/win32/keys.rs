@@ -0,0 +1,403 @@
+use egui::Key;
+
+/// Virtual-key codes of keys that mean the same on every layout.
+const VK_KEYS: &[(u16, Key)] = &[
+    (0x08, Key::Backspace),  // VK_BACK
+    (0x09, Key::Tab),        // VK_TAB
+    (0x0D, Key::Enter),      // VK_RETURN
+    (0x1B, Key::Escape),     // VK_ESCAPE
+    (0x20, Key::Space),      // VK_SPACE
+    (0x21, Key::PageUp),     // VK_PRIOR
+    (0x22, Key::PageDown),   // VK_NEXT
+    (0x23, Key::End),        // VK_END
+    (0x24, Key::Home),       // VK_HOME
+    (0x25, Key::ArrowLeft),  // VK_LEFT
+    (0x26, Key::ArrowUp),    // VK_UP
+    (0x27, Key::ArrowRight), // VK_RIGHT
+    (0x28, Key::ArrowDown),  // VK_DOWN
+    (0x2D, Key::Insert),     // VK_INSERT
+    (0x2E, Key::Delete),     // VK_DELETE
+    (0x30, Key::Num0),       // '0'
+    (0x31, Key::Num1),
+    (0x32, Key::Num2),
+    (0x33, Key::Num3),
+    (0x34, Key::Num4),
+    (0x35, Key::Num5),
+    (0x36, Key::Num6),
+    (0x37, Key::Num7),
+    (0x38, Key::Num8),
+    (0x39, Key::Num9),
+    (0x41, Key::A), // 'A'
+    (0x42, Key::B),
+    (0x43, Key::C),
+    (0x44, Key::D),
+    (0x45, Key::E),
+    (0x46, Key::F),
+    (0x47, Key::G),
+    (0x48, Key::H),
+    (0x49, Key::I),
+    (0x4A, Key::J),
+    (0x4B, Key::K),
+    (0x4C, Key::L),
+    (0x4D, Key::M),
+    (0x4E, Key::N),
+    (0x4F, Key::O),
+    (0x50, Key::P),
+    (0x51, Key::Q),
+    (0x52, Key::R),
+    (0x53, Key::S),
+    (0x54, Key::T),
+    (0x55, Key::U),
+    (0x56, Key::V),
+    (0x57, Key::W),
+    (0x58, Key::X),
+    (0x59, Key::Y),
+    (0x5A, Key::Z),
+    (0x60, Key::Num0), // VK_NUMPAD0
+    (0x61, Key::Num1),
+    (0x62, Key::Num2),
+    (0x63, Key::Num3),
+    (0x64, Key::Num4),
+    (0x65, Key::Num5),
+    (0x66, Key::Num6),
+    (0x67, Key::Num7),
+    (0x68, Key::Num8),
+    (0x69, Key::Num9),
+    (0x6B, Key::Plus),   // VK_ADD
+    (0x6D, Key::Minus),  // VK_SUBTRACT
+    (0x6E, Key::Period), // VK_DECIMAL
+    (0x6F, Key::Slash),  // VK_DIVIDE
+    (0x70, Key::F1),     // VK_F1
+    (0x71, Key::F2),
+    (0x72, Key::F3),
+    (0x73, Key::F4),
+    (0x74, Key::F5),
+    (0x75, Key::F6),
+    (0x76, Key::F7),
+    (0x77, Key::F8),
+    (0x78, Key::F9),
+    (0x79, Key::F10),
+    (0x7A, Key::F11),
+    (0x7B, Key::F12),
+    (0x7C, Key::F13),
+    (0x7D, Key::F14),
+    (0x7E, Key::F15),
+    (0x7F, Key::F16),
+    (0x80, Key::F17),
+    (0x81, Key::F18),
+    (0x82, Key::F19),
+    (0x83, Key::F20),
+    (0x84, Key::F21),
+    (0x85, Key::F22),
+    (0x86, Key::F23),
+    (0x87, Key::F24),
+];
+
+/// `VK_OEM_*` codes with what their key types on a US layout, used when the current layout does not type a known character.
+const OEM_KEYS: &[(u16, Key)] = &[
+    (0xBA, Key::Semicolon),    // VK_OEM_1
+    (0xBB, Key::Equals),       // VK_OEM_PLUS
+    (0xBC, Key::Comma),        // VK_OEM_COMMA
+    (0xBD, Key::Minus),        // VK_OEM_MINUS
+    (0xBE, Key::Period),       // VK_OEM_PERIOD
+    (0xBF, Key::Slash),        // VK_OEM_2
+    (0xC0, Key::Backtick),     // VK_OEM_3
+    (0xDB, Key::OpenBracket),  // VK_OEM_4
+    (0xDC, Key::Backslash),    // VK_OEM_5
+    (0xDD, Key::CloseBracket), // VK_OEM_6
+    (0xDE, Key::Quote),        // VK_OEM_7
+    (0xE2, Key::Backslash),    // VK_OEM_102
+];
+
+/// `APPCOMMAND_*` values of `WM_APPCOMMAND`, sent by keyboards with dedicated clipboard keys.
+const APP_COMMAND_KEYS: &[(u16, Key)] = &[
+    (36, Key::Copy),  // APPCOMMAND_COPY
+    (37, Key::Cut),   // APPCOMMAND_CUT
+    (38, Key::Paste), // APPCOMMAND_PASTE
+];
+
+/// Set 1 scan codes of a US keyboard, `0xE0` in the high byte for extended keys.
+const SCAN_CODE_KEYS: &[(u16, Key)] = &[
+    (0x01, Key::Escape),
+    (0x02, Key::Num1),
+    (0x03, Key::Num2),
+    (0x04, Key::Num3),
+    (0x05, Key::Num4),
+    (0x06, Key::Num5),
+    (0x07, Key::Num6),
+    (0x08, Key::Num7),
+    (0x09, Key::Num8),
+    (0x0A, Key::Num9),
+    (0x0B, Key::Num0),
+    (0x0C, Key::Minus),
+    (0x0D, Key::Equals),
+    (0x0E, Key::Backspace),
+    (0x0F, Key::Tab),
+    (0x10, Key::Q),
+    (0x11, Key::W),
+    (0x12, Key::E),
+    (0x13, Key::R),
+    (0x14, Key::T),
+    (0x15, Key::Y),
+    (0x16, Key::U),
+    (0x17, Key::I),
+    (0x18, Key::O),
+    (0x19, Key::P),
+    (0x1A, Key::OpenBracket),
+    (0x1B, Key::CloseBracket),
+    (0x1C, Key::Enter),
+    (0x1E, Key::A),
+    (0x1F, Key::S),
+    (0x20, Key::D),
+    (0x21, Key::F),
+    (0x22, Key::G),
+    (0x23, Key::H),
+    (0x24, Key::J),
+    (0x25, Key::K),
+    (0x26, Key::L),
+    (0x27, Key::Semicolon),
+    (0x28, Key::Quote),
+    (0x29, Key::Backtick),
+    (0x2B, Key::Backslash),
+    (0x2C, Key::Z),
+    (0x2D, Key::X),
+    (0x2E, Key::C),
+    (0x2F, Key::V),
+    (0x30, Key::B),
+    (0x31, Key::N),
+    (0x32, Key::M),
+    (0x33, Key::Comma),
+    (0x34, Key::Period),
+    (0x35, Key::Slash),
+    (0x39, Key::Space),
+    (0x3B, Key::F1),
+    (0x3C, Key::F2),
+    (0x3D, Key::F3),
+    (0x3E, Key::F4),
+    (0x3F, Key::F5),
+    (0x40, Key::F6),
+    (0x41, Key::F7),
+    (0x42, Key::F8),
+    (0x43, Key::F9),
+    (0x44, Key::F10),
+    // The numpad, whatever the state of Num Lock.
+    (0x47, Key::Num7),
+    (0x48, Key::Num8),
+    (0x49, Key::Num9),
+    (0x4A, Key::Minus),
+    (0x4B, Key::Num4),
+    (0x4C, Key::Num5),
+    (0x4D, Key::Num6),
+    (0x4E, Key::Plus),
+    (0x4F, Key::Num1),
+    (0x50, Key::Num2),
+    (0x51, Key::Num3),
+    (0x52, Key::Num0),
+    (0x53, Key::Period),
+    // The key between left shift and Z on ISO keyboards.
+    (0x56, Key::Backslash),
+    (0x57, Key::F11),
+    (0x58, Key::F12),
+    (0x64, Key::F13),
+    (0x65, Key::F14),
+    (0x66, Key::F15),
+    (0x67, Key::F16),
+    (0x68, Key::F17),
+    (0x69, Key::F18),
+    (0x6A, Key::F19),
+    (0x6B, Key::F20),
+    (0x6C, Key::F21),
+    (0x6D, Key::F22),
+    (0x6E, Key::F23),
+    (0x76, Key::F24),
+    (0xE01C, Key::Enter), // numpad enter
+    (0xE035, Key::Slash), // numpad divide
+    (0xE047, Key::Home),
+    (0xE048, Key::ArrowUp),
+    (0xE049, Key::PageUp),
+    (0xE04B, Key::ArrowLeft),
+    (0xE04D, Key::ArrowRight),
+    (0xE04F, Key::End),
+    (0xE050, Key::ArrowDown),
+    (0xE051, Key::PageDown),
+    (0xE052, Key::Insert),
+    (0xE053, Key::Delete),
+];
+
+fn find(table: &[(u16, Key)], code: u16) -> Option<Key> {
+    table
+        .iter()
+        .find(|&&(entry, _)| entry == code)
+        .map(|&(_, key)| key)
+}
+
+/// Whether the meaning of `vk` depends on the keyboard layout.
+pub fn is_oem(vk: u16) -> bool {
+    OEM_KEYS.iter().any(|&(oem, _)| oem == vk)
+}
+
+/// The logical key of a virtual-key code. For `VK_OEM_*` keys `layout_char` gives the character
+/// the key types without modifiers under the current layout, e.g. from `MapVirtualKeyW(vk, MAPVK_VK_TO_CHAR)`.
+pub fn key_from_vk(vk: u16, layout_char: impl FnOnce() -> Option<char>) -> Option<Key> {
+    if is_oem(vk) {
+        return layout_char()
+            .and_then(key_from_char)
+            .or_else(|| find(OEM_KEYS, vk));
+    }
+    find(VK_KEYS, vk)
+}
+
+/// The key that types `c`, for punctuation that moves around between layouts.
+pub fn key_from_char(c: char) -> Option<Key> {
+    let key = match c {
+        ':' => Key::Colon,
+        ',' => Key::Comma,
+        '\\' => Key::Backslash,
+        '/' => Key::Slash,
+        '|' => Key::Pipe,
+        '?' => Key::Questionmark,
+        '[' => Key::OpenBracket,
+        ']' => Key::CloseBracket,
+        '`' => Key::Backtick,
+        '-' => Key::Minus,
+        '.' => Key::Period,
+        '+' => Key::Plus,
+        '=' => Key::Equals,
+        ';' => Key::Semicolon,
+        '\'' => Key::Quote,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The key of a `WM_APPCOMMAND` command, from `GET_APPCOMMAND_LPARAM`.
+pub fn key_from_app_command(command: u16) -> Option<Key> {
+    find(APP_COMMAND_KEYS, command)
+}
+
+/// The physical key of a `WM_KEYDOWN`/`WM_KEYUP`, from the scan code in bits 16..24 and the extended bit 24 of `lparam`.
+pub fn physical_key(lparam: isize) -> Option<Key> {
+    let scan_code = ((lparam >> 16) & 0xFF) as u16;
+    let extended = lparam & (1 << 24) != 0;
+    find(
+        SCAN_CODE_KEYS,
+        if extended {
+            0xE000 | scan_code
+        } else {
+            scan_code
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lparam(scan_code: u16, extended: bool) -> isize {
+        ((scan_code as isize) << 16) | if extended { 1 << 24 } else { 0 } | 1
+    }
+
+    #[test]
+    fn test_every_key_is_reachable() {
+        let mut reachable: Vec<Key> = VK_KEYS
+            .iter()
+            .chain(OEM_KEYS)
+            .chain(APP_COMMAND_KEYS)
+            .map(|&(_, key)| key)
+            .collect();
+        reachable.extend((0..=0x7F).filter_map(|c| key_from_char(char::from(c))));
+
+        // Windows has no virtual-key codes past F24.
+        let missing: Vec<Key> = Key::ALL
+            .iter()
+            .copied()
+            .filter(|key| !reachable.contains(key))
+            .collect();
+        assert_eq!(
+            missing,
+            [
+                Key::F25,
+                Key::F26,
+                Key::F27,
+                Key::F28,
+                Key::F29,
+                Key::F30,
+                Key::F31,
+                Key::F32,
+                Key::F33,
+                Key::F34,
+                Key::F35,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tables_are_unambiguous() {
+        for table in [VK_KEYS, OEM_KEYS, APP_COMMAND_KEYS, SCAN_CODE_KEYS] {
+            for (i, (code, _)) in table.iter().enumerate() {
+                assert!(
+                    table[..i].iter().all(|(other, _)| other != code),
+                    "{code:#x} is listed twice"
+                );
+            }
+        }
+        assert!(VK_KEYS.iter().all(|&(vk, _)| !is_oem(vk)));
+    }
+
+    #[test]
+    fn test_vk_keys() {
+        let no_char = || None;
+        assert_eq!(key_from_vk(0x30, no_char), Some(Key::Num0));
+        assert_eq!(key_from_vk(0x39, no_char), Some(Key::Num9));
+        assert_eq!(key_from_vk(0x41, no_char), Some(Key::A));
+        assert_eq!(key_from_vk(0x5A, no_char), Some(Key::Z));
+        assert_eq!(key_from_vk(0x70, no_char), Some(Key::F1));
+        assert_eq!(key_from_vk(0x87, no_char), Some(Key::F24));
+        assert_eq!(key_from_vk(0x69, no_char), Some(Key::Num9));
+        assert_eq!(key_from_vk(0x6F, no_char), Some(Key::Slash));
+        // Shift, control and the windows keys are modifiers, not keys.
+        assert_eq!(key_from_vk(0x10, no_char), None);
+        assert_eq!(key_from_vk(0x5B, no_char), None);
+        assert_eq!(key_from_vk(0xFF, no_char), None);
+    }
+
+    #[test]
+    fn test_oem_keys_follow_layout() {
+        // US layout.
+        assert_eq!(key_from_vk(0xBB, || Some('=')), Some(Key::Equals));
+        assert_eq!(key_from_vk(0xBF, || None), Some(Key::Slash));
+        // German layout: the `+` key and the `#` key.
+        assert_eq!(key_from_vk(0xBB, || Some('+')), Some(Key::Plus));
+        assert_eq!(key_from_vk(0xBF, || Some('#')), Some(Key::Slash));
+        // Letter keys do not ask the layout.
+        assert_eq!(key_from_vk(0x41, || unreachable!()), Some(Key::A));
+    }
+
+    #[test]
+    fn test_app_commands() {
+        assert_eq!(key_from_app_command(36), Some(Key::Copy));
+        assert_eq!(key_from_app_command(37), Some(Key::Cut));
+        assert_eq!(key_from_app_command(38), Some(Key::Paste));
+        assert_eq!(key_from_app_command(1), None);
+    }
+
+    #[test]
+    fn test_physical_keys() {
+        assert_eq!(physical_key(lparam(0x10, false)), Some(Key::Q));
+        assert_eq!(physical_key(lparam(0x1C, false)), Some(Key::Enter));
+        assert_eq!(physical_key(lparam(0x1C, true)), Some(Key::Enter));
+        // Same scan code, numpad or navigation block.
+        assert_eq!(physical_key(lparam(0x48, false)), Some(Key::Num8));
+        assert_eq!(physical_key(lparam(0x48, true)), Some(Key::ArrowUp));
+        assert_eq!(physical_key(lparam(0x35, true)), Some(Key::Slash));
+        assert_eq!(physical_key(lparam(0x76, false)), Some(Key::F24));
+        // Repeat count and the previous state and transition bits do not matter.
+        assert_eq!(
+            physical_key(lparam(0x1E, false) | 0x7FFF | (1 << 30)),
+            Some(Key::A)
+        );
+        // Control and shift.
+        assert_eq!(physical_key(lparam(0x1D, false)), None);
+        assert_eq!(physical_key(lparam(0x2A, false)), None);
+    }
+}
@@ -0,0 +1,14 @@
+//! Decoding of Win32 input messages into egui terms, kept free of Windows APIs so it builds and is tested on every OS.
+//! [`crate::input::InputCollector`] feeds it the message parameters.
+
+// Only the Windows input collector uses these.
+#![cfg_attr(not(windows), allow(dead_code))]
+
+pub mod focus;
+pub mod ime;
+pub mod keys;
+pub mod modifiers;
+pub mod pointer;
+pub mod raw_input;
+pub mod text;
+pub mod wheel;
@@ -0,0 +1,172 @@
+use egui::Modifiers;
+
+const VK_SHIFT: u16 = 0x10;
+const VK_CONTROL: u16 = 0x11;
+const VK_MENU: u16 = 0x12;
+const VK_LWIN: u16 = 0x5B;
+const VK_RWIN: u16 = 0x5C;
+const VK_LSHIFT: u16 = 0xA0;
+const VK_RSHIFT: u16 = 0xA1;
+const VK_LCONTROL: u16 = 0xA2;
+const VK_RCONTROL: u16 = 0xA3;
+const VK_LMENU: u16 = 0xA4;
+const VK_RMENU: u16 = 0xA5;
+
+/// Scan code of the right shift key, the only way to tell it from the left one in `WM_KEYDOWN`.
+const RIGHT_SHIFT_SCAN_CODE: isize = 0x36;
+
+/// Which modifier keys are down, followed through key messages so either side of a key counts
+/// and releasing one side does not release the other.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ModifierState {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+    pub left_win: bool,
+    pub right_win: bool,
+}
+
+impl ModifierState {
+    /// Reads every key from `is_down`, e.g. `GetKeyState(vk) < 0`, to catch up with changes made while the window was not focused.
+    pub fn sync(is_down: impl Fn(u16) -> bool) -> Self {
+        Self {
+            left_shift: is_down(VK_LSHIFT),
+            right_shift: is_down(VK_RSHIFT),
+            left_ctrl: is_down(VK_LCONTROL),
+            right_ctrl: is_down(VK_RCONTROL),
+            left_alt: is_down(VK_LMENU),
+            right_alt: is_down(VK_RMENU),
+            left_win: is_down(VK_LWIN),
+            right_win: is_down(VK_RWIN),
+        }
+    }
+
+    /// Follows a `WM_(SYS)KEYDOWN` or `WM_(SYS)KEYUP`. Returns `false` if `vk` is not a modifier key.
+    ///
+    /// Windows reports both sides as `VK_SHIFT`, `VK_CONTROL` and `VK_MENU`, so the side comes from
+    /// the scan code for shift and from the extended bit for control and alt.
+    pub fn key(&mut self, vk: u16, lparam: isize, pressed: bool) -> bool {
+        let extended = lparam & (1 << 24) != 0;
+        let key = match vk {
+            VK_SHIFT if (lparam >> 16) & 0xFF == RIGHT_SHIFT_SCAN_CODE => &mut self.right_shift,
+            VK_SHIFT | VK_LSHIFT => &mut self.left_shift,
+            VK_RSHIFT => &mut self.right_shift,
+            VK_CONTROL if extended => &mut self.right_ctrl,
+            VK_CONTROL | VK_LCONTROL => &mut self.left_ctrl,
+            VK_RCONTROL => &mut self.right_ctrl,
+            VK_MENU if extended => &mut self.right_alt,
+            VK_MENU | VK_LMENU => &mut self.left_alt,
+            VK_RMENU => &mut self.right_alt,
+            VK_LWIN => &mut self.left_win,
+            VK_RWIN => &mut self.right_win,
+            _ => return false,
+        };
+        *key = pressed;
+        true
+    }
+
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.left_ctrl || self.right_ctrl
+    }
+
+    pub fn alt(&self) -> bool {
+        self.left_alt || self.right_alt
+    }
+
+    /// Egui has no Windows key outside of macOS, so `left_win` and `right_win` are left out.
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            alt: self.alt(),
+            ctrl: self.ctrl(),
+            shift: self.shift(),
+            mac_cmd: false,
+            command: self.ctrl(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lparam(scan_code: isize, extended: bool) -> isize {
+        (scan_code << 16) | if extended { 1 << 24 } else { 0 } | 1
+    }
+
+    #[test]
+    fn test_sides_are_independent() {
+        let mut state = ModifierState::default();
+
+        assert!(state.key(VK_SHIFT, lparam(0x2A, false), true));
+        assert!(state.key(VK_SHIFT, lparam(0x36, false), true));
+        assert!(state.key(VK_SHIFT, lparam(0x2A, false), false));
+        assert!(state.right_shift && !state.left_shift);
+        assert_eq!(state.modifiers(), Modifiers::SHIFT);
+
+        assert!(state.key(VK_SHIFT, lparam(0x36, false), false));
+        assert_eq!(state.modifiers(), Modifiers::NONE);
+    }
+
+    #[test]
+    fn test_ctrl_alt_and_win() {
+        let mut state = ModifierState::default();
+
+        // Right control and right alt are extended keys.
+        state.key(VK_CONTROL, lparam(0x1D, true), true);
+        assert!(state.right_ctrl);
+        assert_eq!(
+            state.modifiers(),
+            Modifiers {
+                ctrl: true,
+                command: true,
+                ..Modifiers::NONE
+            }
+        );
+        state.key(VK_CONTROL, lparam(0x1D, true), false);
+
+        // Alt arrives as `WM_SYSKEYDOWN`, and is still down for the `WM_KEYUP` of other keys.
+        state.key(VK_MENU, lparam(0x38, false), true);
+        assert!(state.left_alt);
+        assert_eq!(state.modifiers(), Modifiers::ALT);
+        state.key(VK_MENU, lparam(0x38, false), false);
+
+        state.key(VK_RWIN, lparam(0x5C, true), true);
+        assert!(state.right_win);
+        assert_eq!(state.modifiers(), Modifiers::NONE);
+    }
+
+    #[test]
+    fn test_alt_gr() {
+        let mut state = ModifierState::default();
+
+        // Windows presses a fake left control with right alt.
+        state.key(VK_CONTROL, lparam(0x1D, false), true);
+        state.key(VK_MENU, lparam(0x38, true), true);
+        assert!(state.left_ctrl && state.right_alt);
+        assert!(state.ctrl() && state.alt());
+
+        state.key(VK_CONTROL, lparam(0x1D, false), false);
+        state.key(VK_MENU, lparam(0x38, true), false);
+        assert_eq!(state, ModifierState::default());
+    }
+
+    #[test]
+    fn test_other_keys_and_sync() {
+        let mut state = ModifierState::default();
+        assert!(!state.key(0x41, lparam(0x1E, false), true));
+        assert_eq!(state, ModifierState::default());
+
+        // Shift went down while another window had the focus and is released with this one focused.
+        state = ModifierState::sync(|vk| vk == VK_RSHIFT);
+        assert!(state.right_shift);
+        state.key(VK_RSHIFT, 0, false);
+        assert!(!state.shift());
+    }
+}
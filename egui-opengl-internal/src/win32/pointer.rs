@@ -0,0 +1,365 @@
+use egui::{Event, Modifiers, PointerButton, Pos2, TouchDeviceId, TouchId, TouchPhase};
+
+pub const WM_POINTERUPDATE: u32 = 0x0245;
+pub const WM_POINTERDOWN: u32 = 0x0246;
+pub const WM_POINTERUP: u32 = 0x0247;
+
+const POINTER_MESSAGE_FLAG_INCONTACT: u32 = 0x0004;
+const POINTER_MESSAGE_FLAG_PRIMARY: u32 = 0x2000;
+const POINTER_MESSAGE_FLAG_CANCELED: u32 = 0x8000;
+
+/// `GetMessageExtraInfo` of mouse messages promoted from touch and pen input, in its upper 24 bits.
+const MI_WP_SIGNATURE: u32 = 0xFF51_5700;
+const SIGNATURE_MASK: u32 = 0xFFFF_FF00;
+
+/// Pressure of touch and pen input goes up to this.
+const MAX_PRESSURE: f32 = 1024.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerKind {
+    Touch,
+    Pen,
+}
+
+/// What `GetPointerTouchInfo` or `GetPointerPenInfo` report about a pointer message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerInfo {
+    pub kind: PointerKind,
+    /// The `sourceDevice` handle.
+    pub device: u64,
+    /// Position in the client area.
+    pub pos: Pos2,
+    /// From 0 to 1024, if the device reports it.
+    pub pressure: Option<u32>,
+}
+
+/// Turns `WM_POINTER*` messages of touch screens and pens into [`Event::Touch`]. The primary pointer also moves
+/// and clicks egui's pointer, the way Windows promotes it to mouse messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TouchState {
+    /// A pointer message arrived, so promoted mouse messages only repeat it.
+    active: bool,
+    /// The primary pointer is down.
+    pressed: bool,
+}
+
+impl TouchState {
+    /// Whether the promoted mouse messages are to be dropped.
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    /// Pushes the events of a pointer message with its `wparam`. Returns `false` if `umsg` is no pointer message.
+    pub fn process(
+        &mut self,
+        umsg: u32,
+        wparam: usize,
+        info: &PointerInfo,
+        modifiers: Modifiers,
+        events: &mut Vec<Event>,
+    ) -> bool {
+        let id = wparam as u32 & 0xFFFF;
+        let flags = (wparam as u32) >> 16;
+        let in_contact = flags & POINTER_MESSAGE_FLAG_INCONTACT != 0;
+        let canceled = flags & POINTER_MESSAGE_FLAG_CANCELED != 0;
+
+        let phase = match umsg {
+            WM_POINTERDOWN => Some(TouchPhase::Start),
+            WM_POINTERUPDATE if canceled => Some(TouchPhase::Cancel),
+            // A pen hovering above the screen.
+            WM_POINTERUPDATE if !in_contact => None,
+            WM_POINTERUPDATE => Some(TouchPhase::Move),
+            WM_POINTERUP if canceled => Some(TouchPhase::Cancel),
+            WM_POINTERUP => Some(TouchPhase::End),
+            _ => return false,
+        };
+        self.active = true;
+
+        if let Some(phase) = phase {
+            events.push(Event::Touch {
+                device_id: TouchDeviceId(info.device),
+                id: TouchId(id as u64),
+                phase,
+                pos: info.pos,
+                force: info.pressure.map(|pressure| pressure as f32 / MAX_PRESSURE),
+            });
+        }
+
+        if flags & POINTER_MESSAGE_FLAG_PRIMARY != 0 {
+            self.primary(phase, info, modifiers, events);
+        }
+        true
+    }
+
+    fn primary(
+        &mut self,
+        phase: Option<TouchPhase>,
+        info: &PointerInfo,
+        modifiers: Modifiers,
+        events: &mut Vec<Event>,
+    ) {
+        let pos = info.pos;
+        let button = |pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers,
+        };
+
+        match phase {
+            Some(TouchPhase::Start) => {
+                events.push(Event::PointerMoved(pos));
+                events.push(button(true));
+                self.pressed = true;
+            }
+            None | Some(TouchPhase::Move) => events.push(Event::PointerMoved(pos)),
+            Some(TouchPhase::End | TouchPhase::Cancel) => {
+                if self.pressed {
+                    events.push(button(false));
+                    self.pressed = false;
+                }
+                // A pen keeps hovering, a finger is gone.
+                if info.kind == PointerKind::Touch {
+                    events.push(Event::PointerGone);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a mouse message was promoted from touch or pen input, from `GetMessageExtraInfo`.
+pub fn is_promoted(extra_info: isize) -> bool {
+    extra_info as u32 & SIGNATURE_MASK == MI_WP_SIGNATURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    const NEW: u32 = 0x0001;
+    const IN_RANGE: u32 = 0x0002;
+    const FIRST_BUTTON: u32 = 0x0010;
+
+    fn wparam(id: u32, flags: u32) -> usize {
+        (flags << 16 | id) as usize
+    }
+
+    fn touch(x: f32, y: f32) -> PointerInfo {
+        PointerInfo {
+            kind: PointerKind::Touch,
+            device: 7,
+            pos: pos2(x, y),
+            pressure: None,
+        }
+    }
+
+    fn event(id: u64, phase: TouchPhase, x: f32, y: f32) -> Event {
+        Event::Touch {
+            device_id: TouchDeviceId(7),
+            id: TouchId(id),
+            phase,
+            pos: pos2(x, y),
+            force: None,
+        }
+    }
+
+    fn button(x: f32, y: f32, pressed: bool) -> Event {
+        Event::PointerButton {
+            pos: pos2(x, y),
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_tap() {
+        let mut state = TouchState::default();
+        let mut events = vec![];
+        let down = NEW
+            | IN_RANGE
+            | POINTER_MESSAGE_FLAG_INCONTACT
+            | FIRST_BUTTON
+            | POINTER_MESSAGE_FLAG_PRIMARY;
+
+        assert!(!state.active());
+        for (umsg, flags, x) in [
+            (WM_POINTERDOWN, down, 10.0),
+            (WM_POINTERUPDATE, down & !NEW, 12.0),
+            (WM_POINTERUP, POINTER_MESSAGE_FLAG_PRIMARY, 12.0),
+        ] {
+            assert!(state.process(
+                umsg,
+                wparam(3, flags),
+                &touch(x, 20.0),
+                Modifiers::NONE,
+                &mut events
+            ));
+        }
+        assert!(state.active());
+
+        assert_eq!(
+            events,
+            [
+                event(3, TouchPhase::Start, 10.0, 20.0),
+                Event::PointerMoved(pos2(10.0, 20.0)),
+                button(10.0, 20.0, true),
+                event(3, TouchPhase::Move, 12.0, 20.0),
+                Event::PointerMoved(pos2(12.0, 20.0)),
+                event(3, TouchPhase::End, 12.0, 20.0),
+                button(12.0, 20.0, false),
+                Event::PointerGone,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_second_finger_only_touches() {
+        let mut state = TouchState::default();
+        let mut events = vec![];
+        let contact = IN_RANGE | POINTER_MESSAGE_FLAG_INCONTACT | FIRST_BUTTON;
+
+        // The first finger is primary, the second one only adds a touch for pinching and panning.
+        state.process(
+            WM_POINTERDOWN,
+            wparam(1, contact | POINTER_MESSAGE_FLAG_PRIMARY),
+            &touch(0.0, 0.0),
+            Modifiers::NONE,
+            &mut events,
+        );
+        events.clear();
+        state.process(
+            WM_POINTERDOWN,
+            wparam(2, contact),
+            &touch(50.0, 0.0),
+            Modifiers::NONE,
+            &mut events,
+        );
+        state.process(
+            WM_POINTERUPDATE,
+            wparam(2, contact),
+            &touch(80.0, 0.0),
+            Modifiers::NONE,
+            &mut events,
+        );
+        state.process(
+            WM_POINTERUP,
+            wparam(2, POINTER_MESSAGE_FLAG_CANCELED),
+            &touch(80.0, 0.0),
+            Modifiers::NONE,
+            &mut events,
+        );
+        assert_eq!(
+            events,
+            [
+                event(2, TouchPhase::Start, 50.0, 0.0),
+                event(2, TouchPhase::Move, 80.0, 0.0),
+                event(2, TouchPhase::Cancel, 80.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pinch() {
+        let mut state = TouchState::default();
+        let contact = IN_RANGE | POINTER_MESSAGE_FLAG_INCONTACT | FIRST_BUTTON;
+        let primary = contact | POINTER_MESSAGE_FLAG_PRIMARY;
+        let ctx = egui::Context::default();
+        let mut frame = |messages: &[(u32, usize, f32)]| {
+            let mut events = vec![];
+            for &(umsg, wparam, x) in messages {
+                state.process(umsg, wparam, &touch(x, 100.0), Modifiers::NONE, &mut events);
+            }
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            let mut zoom = None;
+            let _ = ctx.run(input, |ctx| {
+                zoom = ctx.input(|i| i.multi_touch()).map(|touch| touch.zoom_delta)
+            });
+            zoom
+        };
+
+        // Two fingers go down, then spread to twice the distance.
+        // Egui starts a gesture where the pointer was the frame before.
+        frame(&[(WM_POINTERDOWN, wparam(1, primary), 100.0)]);
+        frame(&[(WM_POINTERDOWN, wparam(2, contact), 200.0)]);
+        let zoom = frame(&[
+            (WM_POINTERUPDATE, wparam(1, primary), 50.0),
+            (WM_POINTERUPDATE, wparam(2, contact), 250.0),
+        ]);
+        assert_eq!(zoom, Some(2.0));
+    }
+
+    #[test]
+    fn test_pen() {
+        let mut state = TouchState::default();
+        let mut events = vec![];
+        let pen = |x, pressure| PointerInfo {
+            kind: PointerKind::Pen,
+            device: 9,
+            pos: pos2(x, 5.0),
+            pressure,
+        };
+        let hover = IN_RANGE | POINTER_MESSAGE_FLAG_PRIMARY;
+        let contact = hover | POINTER_MESSAGE_FLAG_INCONTACT | FIRST_BUTTON;
+
+        state.process(
+            WM_POINTERUPDATE,
+            wparam(4, hover),
+            &pen(1.0, Some(0)),
+            Modifiers::NONE,
+            &mut events,
+        );
+        state.process(
+            WM_POINTERDOWN,
+            wparam(4, contact),
+            &pen(2.0, Some(512)),
+            Modifiers::NONE,
+            &mut events,
+        );
+        state.process(
+            WM_POINTERUP,
+            wparam(4, hover),
+            &pen(2.0, Some(0)),
+            Modifiers::NONE,
+            &mut events,
+        );
+
+        let touch = |phase, x, force| Event::Touch {
+            device_id: TouchDeviceId(9),
+            id: TouchId(4),
+            phase,
+            pos: pos2(x, 5.0),
+            force: Some(force),
+        };
+        assert_eq!(
+            events,
+            [
+                Event::PointerMoved(pos2(1.0, 5.0)),
+                touch(TouchPhase::Start, 2.0, 0.5),
+                Event::PointerMoved(pos2(2.0, 5.0)),
+                button(2.0, 5.0, true),
+                touch(TouchPhase::End, 2.0, 0.0),
+                button(2.0, 5.0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_promoted_mouse_messages() {
+        assert!(is_promoted(0xFF51_5700u32 as isize));
+        assert!(is_promoted(0xFF51_5780u32 as i32 as isize));
+        assert!(!is_promoted(0));
+        assert!(!is_promoted(0x1234));
+        assert!(!TouchState::default().process(
+            0x0200,
+            0,
+            &touch(0.0, 0.0),
+            Modifiers::NONE,
+            &mut vec![]
+        ));
+    }
+}
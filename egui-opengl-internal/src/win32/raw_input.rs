@@ -0,0 +1,463 @@
+use super::modifiers::ModifierState;
+use super::wheel;
+use egui::{Event, Modifiers, MouseWheelUnit, PointerButton, Pos2, Rect, Vec2};
+
+/// Size of `RAWINPUTHEADER`, a handle and a `WPARAM` follow `dwType` and `dwSize`.
+pub const HEADER_SIZE: usize = 8 + 2 * std::mem::size_of::<usize>();
+
+const RIM_TYPEMOUSE: u32 = 0;
+const RIM_TYPEKEYBOARD: u32 = 1;
+
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+const MOUSE_VIRTUAL_DESKTOP: u16 = 0x02;
+
+const RI_MOUSE_WHEEL: u16 = 0x0400;
+const RI_MOUSE_HWHEEL: u16 = 0x0800;
+
+/// Down and up flags of every button in `usButtonFlags`.
+const BUTTON_FLAGS: [(PointerButton, u16, u16); 5] = [
+    (PointerButton::Primary, 0x0001, 0x0002),
+    (PointerButton::Secondary, 0x0004, 0x0008),
+    (PointerButton::Middle, 0x0010, 0x0020),
+    (PointerButton::Extra1, 0x0040, 0x0080),
+    (PointerButton::Extra2, 0x0100, 0x0200),
+];
+
+const RI_KEY_BREAK: u16 = 0x01;
+const RI_KEY_E0: u16 = 0x02;
+
+/// Virtual key of the fake keys some sequences, e.g. pause, begin with.
+const VK_FAKE: u16 = 0xFF;
+
+/// Absolute coordinates are normalized to this over the desktop.
+const ABSOLUTE_MAX: f32 = 65535.0;
+
+/// The payload of a `WM_INPUT` as read by `GetRawInputData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawInputData {
+    Mouse(RawMouse),
+    Keyboard(RawKeyboard),
+}
+
+/// `RAWMOUSE`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RawMouse {
+    pub flags: u16,
+    pub button_flags: u16,
+    pub button_data: u16,
+    pub last_x: i32,
+    pub last_y: i32,
+}
+
+/// `RAWKEYBOARD`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RawKeyboard {
+    pub make_code: u16,
+    pub flags: u16,
+    pub vkey: u16,
+}
+
+/// Decodes a `RAWINPUT` in the layout of this process. Returns `None` for HID devices and short buffers.
+pub fn parse(buf: &[u8]) -> Option<RawInputData> {
+    let u16_at = |offset: usize| {
+        Some(u16::from_ne_bytes(
+            buf.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let u32_at = |offset: usize| {
+        Some(u32::from_ne_bytes(
+            buf.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    match u32_at(0)? {
+        RIM_TYPEMOUSE => Some(RawInputData::Mouse(RawMouse {
+            flags: u16_at(HEADER_SIZE)?,
+            button_flags: u16_at(HEADER_SIZE + 4)?,
+            button_data: u16_at(HEADER_SIZE + 6)?,
+            last_x: u32_at(HEADER_SIZE + 12)? as i32,
+            last_y: u32_at(HEADER_SIZE + 16)? as i32,
+        })),
+        RIM_TYPEKEYBOARD => Some(RawInputData::Keyboard(RawKeyboard {
+            make_code: u16_at(HEADER_SIZE)?,
+            flags: u16_at(HEADER_SIZE + 2)?,
+            vkey: u16_at(HEADER_SIZE + 6)?,
+        })),
+        _ => None,
+    }
+}
+
+impl RawMouse {
+    /// Moves `cursor` within `client` and pushes the pointer events of this report.
+    ///
+    /// Relative motion is in mickeys, which are taken as pixels, like with the default pointer speed.
+    /// `absolute` maps the normalized position of tablets and remote desktops to the client area,
+    /// see [`normalized_to_desktop`]. Its flag is set if the position covers all monitors.
+    pub fn events(
+        &self,
+        cursor: &mut Pos2,
+        client: Rect,
+        absolute: impl FnOnce(i32, i32, bool) -> Pos2,
+        wheel_unit: MouseWheelUnit,
+        modifiers: Modifiers,
+        events: &mut Vec<Event>,
+    ) {
+        let pos = if self.flags & MOUSE_MOVE_ABSOLUTE != 0 {
+            absolute(
+                self.last_x,
+                self.last_y,
+                self.flags & MOUSE_VIRTUAL_DESKTOP != 0,
+            )
+        } else {
+            *cursor + Vec2::new(self.last_x as f32, self.last_y as f32)
+        };
+        let pos = client.clamp(pos);
+        if pos != *cursor {
+            *cursor = pos;
+            events.push(Event::PointerMoved(pos));
+        }
+
+        for (button, down, up) in BUTTON_FLAGS {
+            for (flag, pressed) in [(down, true), (up, false)] {
+                if self.button_flags & flag != 0 {
+                    events.push(Event::PointerButton {
+                        pos,
+                        button,
+                        pressed,
+                        modifiers,
+                    });
+                }
+            }
+        }
+
+        for (flag, horizontal) in [(RI_MOUSE_WHEEL, false), (RI_MOUSE_HWHEEL, true)] {
+            if self.button_flags & flag != 0 {
+                let delta = self.button_data as i16;
+                events.push(wheel::wheel_event(delta, horizontal, wheel_unit, modifiers));
+            }
+        }
+    }
+}
+
+/// Position of absolute coordinates on a desktop in screen coordinates.
+pub fn normalized_to_desktop(x: i32, y: i32, desktop: Rect) -> Pos2 {
+    desktop.min + Vec2::new(x as f32, y as f32) / ABSOLUTE_MAX * desktop.size()
+}
+
+impl RawKeyboard {
+    pub fn pressed(&self) -> bool {
+        self.flags & RI_KEY_BREAK == 0
+    }
+
+    /// The `wparam` and `lparam` of the `WM_KEYDOWN` or `WM_KEYUP` the report stands for, `None` for fake keys.
+    /// Raw input does not tell repeats apart, so `was_down` is whether the key was already held.
+    pub fn legacy_message(&self, was_down: bool) -> Option<(usize, isize)> {
+        if self.vkey == VK_FAKE || self.vkey == 0 {
+            return None;
+        }
+
+        let mut lparam = 1 | (self.make_code as u32 & 0xFF) << 16;
+        if self.flags & RI_KEY_E0 != 0 {
+            lparam |= 1 << 24;
+        }
+        if was_down {
+            lparam |= 1 << 30;
+        }
+        if !self.pressed() {
+            lparam |= 1 << 31;
+        }
+        Some((self.vkey as usize, lparam as isize))
+    }
+}
+
+/// Keys held according to raw input, which gives neither repeats nor a key state table.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RawKeys {
+    down: [u64; 4],
+}
+
+impl RawKeys {
+    /// Updates `vk` and returns whether it was held before.
+    pub fn key(&mut self, vk: u16, pressed: bool) -> bool {
+        let vk = vk as u8;
+        let (word, bit) = (vk as usize / 64, 1 << (vk % 64));
+        let was_down = self.down[word] & bit != 0;
+        if pressed {
+            self.down[word] |= bit;
+        } else {
+            self.down[word] &= !bit;
+        }
+        was_down
+    }
+}
+
+/// The key state table for `ToUnicodeEx`, which would be stale without legacy key messages.
+pub fn keyboard_state(modifiers: &ModifierState, caps_lock: bool) -> [u8; 256] {
+    const DOWN: u8 = 0x80;
+    let mut state = [0; 256];
+    let mut set = |vk: usize, down: bool| {
+        if down {
+            state[vk] = DOWN;
+        }
+    };
+
+    set(0x10, modifiers.shift());
+    set(0x11, modifiers.ctrl());
+    set(0x12, modifiers.alt());
+    set(0xA0, modifiers.left_shift);
+    set(0xA1, modifiers.right_shift);
+    set(0xA2, modifiers.left_ctrl);
+    set(0xA3, modifiers.right_ctrl);
+    set(0xA4, modifiers.left_alt);
+    set(0xA5, modifiers.right_alt);
+    // The low bit is the toggle.
+    state[0x14] = caps_lock as u8;
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2};
+
+    fn header(kind: u32, size: usize) -> Vec<u8> {
+        let mut buf = vec![0; HEADER_SIZE + size];
+        buf[0..4].copy_from_slice(&kind.to_ne_bytes());
+        buf[4..8].copy_from_slice(&((HEADER_SIZE + size) as u32).to_ne_bytes());
+        buf
+    }
+
+    fn mouse_buffer(flags: u16, button_flags: u16, button_data: i16, x: i32, y: i32) -> Vec<u8> {
+        let mut buf = header(RIM_TYPEMOUSE, 24);
+        let data = &mut buf[HEADER_SIZE..];
+        data[0..2].copy_from_slice(&flags.to_ne_bytes());
+        data[4..6].copy_from_slice(&button_flags.to_ne_bytes());
+        data[6..8].copy_from_slice(&button_data.to_ne_bytes());
+        data[12..16].copy_from_slice(&x.to_ne_bytes());
+        data[16..20].copy_from_slice(&y.to_ne_bytes());
+        buf
+    }
+
+    fn keyboard_buffer(make_code: u16, flags: u16, vkey: u16) -> Vec<u8> {
+        let mut buf = header(RIM_TYPEKEYBOARD, 16);
+        let data = &mut buf[HEADER_SIZE..];
+        data[0..2].copy_from_slice(&make_code.to_ne_bytes());
+        data[2..4].copy_from_slice(&flags.to_ne_bytes());
+        data[6..8].copy_from_slice(&vkey.to_ne_bytes());
+        // Message
+        data[8..12].copy_from_slice(&0x0100u32.to_ne_bytes());
+        buf
+    }
+
+    fn mouse(buf: &[u8]) -> RawMouse {
+        match parse(buf) {
+            Some(RawInputData::Mouse(mouse)) => mouse,
+            other => panic!("not a mouse: {other:?}"),
+        }
+    }
+
+    fn no_absolute(_: i32, _: i32, _: bool) -> Pos2 {
+        panic!("relative motion")
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            mouse(&mouse_buffer(0, 0x0400, -120, -3, 7)),
+            RawMouse {
+                flags: 0,
+                button_flags: 0x0400,
+                button_data: -120i16 as u16,
+                last_x: -3,
+                last_y: 7,
+            }
+        );
+        assert_eq!(
+            parse(&keyboard_buffer(0x1D, RI_KEY_E0 | RI_KEY_BREAK, 0x11)),
+            Some(RawInputData::Keyboard(RawKeyboard {
+                make_code: 0x1D,
+                flags: RI_KEY_E0 | RI_KEY_BREAK,
+                vkey: 0x11,
+            }))
+        );
+
+        // HID devices and truncated buffers.
+        assert_eq!(parse(&header(2, 32)), None);
+        assert_eq!(
+            parse(&mouse_buffer(0, 0, 0, 1, 1)[..HEADER_SIZE + 12]),
+            None
+        );
+        assert_eq!(parse(&[]), None);
+    }
+
+    #[test]
+    fn test_relative_motion_is_clamped() {
+        let client = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+        let mut cursor = pos2(400.0, 300.0);
+        let mut events = vec![];
+
+        let report = mouse(&mouse_buffer(0, 0, 0, 10, -20));
+        report.events(
+            &mut cursor,
+            client,
+            no_absolute,
+            MouseWheelUnit::Line,
+            Modifiers::NONE,
+            &mut events,
+        );
+        assert_eq!(events, [Event::PointerMoved(pos2(410.0, 280.0))]);
+
+        events.clear();
+        let report = mouse(&mouse_buffer(0, 0, 0, 5000, 5000));
+        report.events(
+            &mut cursor,
+            client,
+            no_absolute,
+            MouseWheelUnit::Line,
+            Modifiers::NONE,
+            &mut events,
+        );
+        assert_eq!(events, [Event::PointerMoved(pos2(800.0, 600.0))]);
+
+        // Pushing against the edge does not move.
+        events.clear();
+        report.events(
+            &mut cursor,
+            client,
+            no_absolute,
+            MouseWheelUnit::Line,
+            Modifiers::NONE,
+            &mut events,
+        );
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_buttons_and_wheel() {
+        let client = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+        let mut cursor = pos2(10.0, 10.0);
+        let mut events = vec![];
+
+        // Left down and right up in one report, then the horizontal wheel.
+        let report = mouse(&mouse_buffer(0, 0x0001 | 0x0008, 0, 0, 0));
+        report.events(
+            &mut cursor,
+            client,
+            no_absolute,
+            MouseWheelUnit::Line,
+            Modifiers::SHIFT,
+            &mut events,
+        );
+        let report = mouse(&mouse_buffer(0, RI_MOUSE_HWHEEL, 120, 0, 0));
+        report.events(
+            &mut cursor,
+            client,
+            no_absolute,
+            MouseWheelUnit::Line,
+            Modifiers::SHIFT,
+            &mut events,
+        );
+
+        let button = |button, pressed| Event::PointerButton {
+            pos: pos2(10.0, 10.0),
+            button,
+            pressed,
+            modifiers: Modifiers::SHIFT,
+        };
+        assert_eq!(
+            events,
+            [
+                button(PointerButton::Primary, true),
+                button(PointerButton::Secondary, false),
+                Event::MouseWheel {
+                    unit: MouseWheelUnit::Line,
+                    delta: vec2(-1.0, 0.0),
+                    modifiers: Modifiers::SHIFT,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_absolute_motion() {
+        let client = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+        let desktop = Rect::from_min_size(pos2(-1920.0, 0.0), vec2(3840.0, 1080.0));
+        let mut cursor = Pos2::ZERO;
+        let mut events = vec![];
+
+        let report = mouse(&mouse_buffer(
+            MOUSE_MOVE_ABSOLUTE | MOUSE_VIRTUAL_DESKTOP,
+            0,
+            0,
+            32768,
+            16384,
+        ));
+        report.events(
+            &mut cursor,
+            client,
+            |x, y, virtual_desktop| {
+                assert!(virtual_desktop);
+                // The window is at the center of the desktop.
+                normalized_to_desktop(x, y, desktop) - vec2(-400.0, 0.0)
+            },
+            MouseWheelUnit::Line,
+            Modifiers::NONE,
+            &mut events,
+        );
+        let Event::PointerMoved(pos) = events[0] else {
+            panic!("not moved");
+        };
+        assert!((pos - pos2(400.0, 270.0)).length() < 0.1, "{pos:?}");
+
+        assert_eq!(normalized_to_desktop(65535, 0, desktop), pos2(1920.0, 0.0));
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let mut keys = RawKeys::default();
+
+        // Right control goes down, repeats and goes up.
+        let down = RawKeyboard {
+            make_code: 0x1D,
+            flags: RI_KEY_E0,
+            vkey: 0x11,
+        };
+        assert!(!keys.key(down.vkey, down.pressed()));
+        assert_eq!(down.legacy_message(false), Some((0x11, 0x011D_0001)));
+        assert!(keys.key(down.vkey, down.pressed()));
+        assert_eq!(down.legacy_message(true), Some((0x11, 0x411D_0001)));
+
+        let up = RawKeyboard {
+            flags: RI_KEY_E0 | RI_KEY_BREAK,
+            ..down
+        };
+        assert!(keys.key(up.vkey, up.pressed()));
+        assert_eq!(
+            up.legacy_message(true),
+            Some((0x11, 0xC11D_0001u32 as isize))
+        );
+        assert!(!keys.key(up.vkey, true));
+
+        let fake = RawKeyboard {
+            make_code: 0x2A,
+            flags: RI_KEY_E0,
+            vkey: VK_FAKE,
+        };
+        assert_eq!(fake.legacy_message(false), None);
+    }
+
+    #[test]
+    fn test_keyboard_state() {
+        let modifiers = ModifierState {
+            right_alt: true,
+            left_ctrl: true,
+            ..Default::default()
+        };
+        let state = keyboard_state(&modifiers, true);
+        assert_eq!(state[0x11], 0x80);
+        assert_eq!(state[0x12], 0x80);
+        assert_eq!(state[0xA5], 0x80);
+        assert_eq!(state[0x10], 0);
+        assert_eq!(state[0x14], 1);
+    }
+}
@@ -0,0 +1,124 @@
+pub const WM_CHAR: u32 = 0x0102;
+pub const WM_DEADCHAR: u32 = 0x0103;
+pub const WM_SYSDEADCHAR: u32 = 0x0107;
+pub const WM_UNICHAR: u32 = 0x0109;
+
+/// `WM_UNICHAR` probe asking whether the window takes UTF-32 characters.
+pub const UNICODE_NOCHAR: u32 = 0xFFFF;
+
+/// Follows character messages to join the UTF-16 surrogate pairs of `WM_CHAR`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextInput {
+    high_surrogate: Option<u16>,
+}
+
+impl TextInput {
+    /// Returns the character typed by a `WM_CHAR` or `WM_UNICHAR`, `None` if it types nothing.
+    ///
+    /// `TranslateMessage` composes dead keys by itself: a `WM_DEADCHAR` is followed by the `WM_CHAR` of the
+    /// accented character, or by the accent and the character separately if they do not combine.
+    /// So the dead character is never typed, and `WM_SYSDEADCHAR` comes with alt, which types nothing.
+    ///
+    /// The `UNICODE_NOCHAR` probe types nothing either. Its answer is the return value of the window procedure,
+    /// where `TRUE` tells the sender that `WM_UNICHAR` is handled here.
+    pub fn process(&mut self, umsg: u32, wparam: usize) -> Option<char> {
+        let ch = match umsg {
+            WM_CHAR => self.utf16(wparam as u16)?,
+            WM_UNICHAR if wparam as u32 == UNICODE_NOCHAR => return None,
+            WM_UNICHAR => {
+                self.high_surrogate = None;
+                char::from_u32(wparam as u32)?
+            }
+            WM_DEADCHAR | WM_SYSDEADCHAR => {
+                self.high_surrogate = None;
+                return None;
+            }
+            _ => return None,
+        };
+        Some(ch).filter(|ch| !ch.is_control())
+    }
+
+    /// Keeps a high surrogate until the low one arrives, dropping surrogates without their other half.
+    fn utf16(&mut self, unit: u16) -> Option<char> {
+        match unit {
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(unit);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                char::decode_utf16([high, unit]).next()?.ok()
+            }
+            _ => {
+                self.high_surrogate = None;
+                char::from_u32(unit as u32)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(messages: &[(u32, usize)]) -> String {
+        let mut input = TextInput::default();
+        messages
+            .iter()
+            .filter_map(|&(umsg, wparam)| input.process(umsg, wparam))
+            .collect()
+    }
+
+    fn chars(units: &[u16]) -> Vec<(u32, usize)> {
+        units.iter().map(|&unit| (WM_CHAR, unit as usize)).collect()
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        let units: Vec<u16> = "a😀é𝄞".encode_utf16().collect();
+        assert_eq!(type_text(&chars(&units)), "a😀é𝄞");
+
+        // Halves without their other half are dropped, the rest is kept.
+        assert_eq!(type_text(&chars(&[0xDE00, 0x61, 0xD83D, 0x62])), "ab");
+        assert_eq!(type_text(&chars(&[0xD83D, 0xD83D, 0xDE00])), "😀");
+    }
+
+    #[test]
+    fn test_dead_keys() {
+        // ^ then e on a French layout, then ^ and x which do not combine.
+        let messages = [
+            (WM_DEADCHAR, '^' as usize),
+            (WM_CHAR, 'ê' as usize),
+            (WM_DEADCHAR, '^' as usize),
+            (WM_CHAR, '^' as usize),
+            (WM_CHAR, 'x' as usize),
+        ];
+        assert_eq!(type_text(&messages), "ê^x");
+
+        // A dead key in between drops an unfinished pair.
+        let messages = [
+            (WM_CHAR, 0xD83D),
+            (WM_SYSDEADCHAR, '`' as usize),
+            (WM_CHAR, 0xDE00),
+        ];
+        assert_eq!(type_text(&messages), "");
+    }
+
+    #[test]
+    fn test_unichar() {
+        let messages = [
+            (WM_UNICHAR, UNICODE_NOCHAR as usize),
+            (WM_UNICHAR, 0x1F600),
+            (WM_UNICHAR, 0xD800),
+            (WM_UNICHAR, 'z' as usize),
+        ];
+        assert_eq!(type_text(&messages), "😀z");
+    }
+
+    #[test]
+    fn test_control_characters() {
+        // Backspace, tab, enter and ctrl+a come as keys instead.
+        assert_eq!(type_text(&chars(&[0x08, 0x09, 0x0D, 0x01, 0x7F])), "");
+        assert_eq!(TextInput::default().process(0x0100, 0x41), None);
+    }
+}
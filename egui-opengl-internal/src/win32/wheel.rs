@@ -0,0 +1,126 @@
+use egui::{Event, Modifiers, MouseWheelUnit, Vec2};
+
+/// Distance of one wheel notch in `WM_MOUSEWHEEL`. Precision touchpads send fractions of it.
+pub const WHEEL_DELTA: f32 = 120.0;
+
+/// Distance of one notch in [`MouseWheelUnit::Point`], three lines of body text.
+pub const POINTS_PER_NOTCH: f32 = 50.0;
+
+/// Zoom factor of one notch of Ctrl+wheel.
+pub const ZOOM_PER_NOTCH: f32 = 1.1;
+
+/// The event of a `WM_MOUSEWHEEL` or `WM_MOUSEHWHEEL` with `delta` from the high word of `wparam`.
+///
+/// Ctrl with the vertical wheel zooms by [`ZOOM_PER_NOTCH`] for every notch, so half a notch on a touchpad zooms half as much.
+pub fn wheel_event(
+    delta: i16,
+    horizontal: bool,
+    unit: MouseWheelUnit,
+    modifiers: Modifiers,
+) -> Event {
+    let notches = delta as f32 / WHEEL_DELTA;
+
+    if modifiers.ctrl && !horizontal {
+        return Event::Zoom(ZOOM_PER_NOTCH.powf(notches));
+    }
+
+    let distance = match unit {
+        MouseWheelUnit::Point => notches * POINTS_PER_NOTCH,
+        MouseWheelUnit::Line | MouseWheelUnit::Page => notches,
+    };
+    // Windows counts tilting right as positive, egui moves the content right.
+    let delta = if horizontal {
+        Vec2::new(-distance, 0.0)
+    } else {
+        Vec2::new(0.0, distance)
+    };
+
+    Event::MouseWheel {
+        unit,
+        delta,
+        modifiers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wheel(unit: MouseWheelUnit, x: f32, y: f32, modifiers: Modifiers) -> Event {
+        Event::MouseWheel {
+            unit,
+            delta: Vec2::new(x, y),
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn test_notches() {
+        use MouseWheelUnit::*;
+
+        let none = Modifiers::NONE;
+        assert_eq!(
+            wheel_event(120, false, Line, none),
+            wheel(Line, 0.0, 1.0, none)
+        );
+        assert_eq!(
+            wheel_event(-240, false, Line, none),
+            wheel(Line, 0.0, -2.0, none)
+        );
+        assert_eq!(
+            wheel_event(120, true, Line, none),
+            wheel(Line, -1.0, 0.0, none)
+        );
+        assert_eq!(
+            wheel_event(-120, false, Point, none),
+            wheel(Point, 0.0, -POINTS_PER_NOTCH, none)
+        );
+    }
+
+    #[test]
+    fn test_touchpad_fractions() {
+        let Event::MouseWheel { delta, .. } =
+            wheel_event(12, false, MouseWheelUnit::Line, Modifiers::NONE)
+        else {
+            panic!("not a wheel event");
+        };
+        assert_eq!(delta, Vec2::new(0.0, 0.1));
+
+        // Ten small steps scroll as far as a notch.
+        let total: f32 = (0..10)
+            .map(
+                |_| match wheel_event(-12, true, MouseWheelUnit::Point, Modifiers::NONE) {
+                    Event::MouseWheel { delta, .. } => delta.x,
+                    _ => 0.0,
+                },
+            )
+            .sum();
+        assert!((total - POINTS_PER_NOTCH).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            command: true,
+            ..Modifiers::NONE
+        };
+        assert_eq!(
+            wheel_event(120, false, MouseWheelUnit::Line, ctrl),
+            Event::Zoom(1.1)
+        );
+        assert_eq!(
+            wheel_event(-60, false, MouseWheelUnit::Line, ctrl),
+            Event::Zoom(1.1f32.powf(-0.5))
+        );
+        // Only the vertical wheel zooms, and other modifiers are passed on.
+        assert_eq!(
+            wheel_event(120, true, MouseWheelUnit::Line, ctrl),
+            wheel(MouseWheelUnit::Line, -1.0, 0.0, ctrl)
+        );
+        assert_eq!(
+            wheel_event(120, false, MouseWheelUnit::Line, Modifiers::SHIFT),
+            wheel(MouseWheelUnit::Line, 0.0, 1.0, Modifiers::SHIFT)
+        );
+    }
+}
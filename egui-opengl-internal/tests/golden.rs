@@ -0,0 +1,400 @@
+//! Paints egui frames with the real [`Painter`] on a headless Mesa context (llvmpipe is enough, no GPU needed)
+//! and compares the result with the PNGs in `tests/golden`.
+//!
+//! Every scene is drawn on a GL 3.3 core and a GLES 3.0 context against the same golden. A child process then draws it
+//! on a GL 2.1 and a GLES 2.0 context, whose `#version 120` and `#version 100` shaders blend in linear space,
+//! against the golden in `tests/golden/legacy`.
+//! Scenes are skipped with a message when EGL with `EGL_MESA_platform_surfaceless` is unavailable.
+//! After an intended rendering change, rewrite the goldens with `UPDATE_GOLDENS=1 cargo test --test golden`.
+#![cfg(target_os = "linux")]
+
+mod support;
+
+use egui::{
+    epaint::TextureHandle, pos2, vec2, Align2, Color32, ColorImage, Context, FontId, LayerId, Mesh,
+    Rect, Rounding, Shape, TextureOptions,
+};
+use egui_opengl_internal::{Painter, Renderer, StateBackup};
+use std::{ffi::CStr, process::Command};
+use support::{Api, HeadlessContext, Image, OffscreenTarget};
+
+const SIZE: [u32; 2] = [128, 96];
+
+struct Harness {
+    api: Api,
+    painter: Painter,
+    target: OffscreenTarget,
+    ctx: Context,
+    _context: HeadlessContext,
+}
+
+impl Harness {
+    fn new(api: Api) -> Option<Self> {
+        let Some(context) = HeadlessContext::new(api) else {
+            eprintln!("Skipping {api:?}: no headless EGL context available");
+            return None;
+        };
+        let target = OffscreenTarget::new(SIZE);
+        let painter = Painter::new(StateBackup::Full).expect("Painter should initialize");
+
+        Some(Self {
+            api,
+            painter,
+            target,
+            ctx: Context::default(),
+            _context: context,
+        })
+    }
+
+    /// The legacy shaders have goldens of their own.
+    fn assert_matches_golden(&self, name: &str, image: &Image) {
+        match self.api {
+            Api::GlCore | Api::Gles3 => support::assert_matches_golden(name, image),
+            Api::Gl21 | Api::Gles2 => {
+                support::assert_matches_golden(&format!("legacy/{name}"), image)
+            }
+        }
+    }
+
+    /// Runs one egui frame and paints it over a freshly cleared target.
+    fn frame(&mut self, mut ui: impl FnMut(&Context)) -> Image {
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                pos2(0.0, 0.0),
+                vec2(SIZE[0] as f32, SIZE[1] as f32),
+            )),
+            ..Default::default()
+        };
+        let output = self.ctx.run(input, |ctx| ui(ctx));
+        let clipped_primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+
+        self.target.clear();
+        self.painter.resize((SIZE[0], SIZE[1]));
+        self.painter
+            .render_frame(
+                output.pixels_per_point,
+                &clipped_primitives,
+                &output.textures_delta,
+            )
+            .expect("Frame should paint");
+        // The host's error queue is its own, the painter must not add to it.
+        assert_eq!(
+            unsafe { gl::GetError() },
+            gl::NO_ERROR,
+            "{:?} frame left a GL error",
+            self.api
+        );
+
+        self.target.read()
+    }
+}
+
+/// Set in the child process that runs a test on the legacy contexts.
+const LEGACY_CONTEXTS: &str = "GOLDEN_LEGACY_CONTEXTS";
+
+fn for_each_api(mut test: impl FnMut(Harness)) {
+    let legacy = std::env::var_os(LEGACY_CONTEXTS).is_some();
+    let apis = if legacy {
+        [Api::Gl21, Api::Gles2]
+    } else {
+        [Api::GlCore, Api::Gles3]
+    };
+
+    for api in apis {
+        if let Some(harness) = Harness::new(api) {
+            if legacy {
+                let version = unsafe { CStr::from_ptr(gl::GetString(gl::VERSION) as *const _) };
+                let version = version.to_string_lossy();
+                assert!(
+                    version.starts_with("2.1") || version.starts_with("OpenGL ES 2.0"),
+                    "{api:?} is {version}, the version override was ignored"
+                );
+            }
+            test(harness);
+        }
+    }
+
+    if !legacy {
+        run_on_legacy_contexts();
+    }
+}
+
+/// Runs the current test again in a child process with Mesa's version overrides,
+/// which it reads once per process and would otherwise also apply to the other contexts.
+fn run_on_legacy_contexts() {
+    let thread = std::thread::current();
+    let test = thread
+        .name()
+        .expect("libtest names test threads after the test");
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture"])
+        .env(LEGACY_CONTEXTS, "1")
+        .env("MESA_GL_VERSION_OVERRIDE", "2.1")
+        .env("MESA_GLSL_VERSION_OVERRIDE", "120")
+        .env("MESA_GLES_VERSION_OVERRIDE", "2.0")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{test} failed on the legacy contexts:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Translucent shapes over opaque ones and anti-aliased text, which only come out right with
+/// premultiplied alpha blending in gamma space.
+#[test]
+fn test_blending() {
+    for_each_api(|mut harness| {
+        let image = harness.frame(|ctx| {
+            let painter = ctx.layer_painter(LayerId::background());
+            painter.rect_filled(
+                Rect::from_min_size(pos2(8.0, 8.0), vec2(72.0, 56.0)),
+                Rounding::ZERO,
+                Color32::from_rgb(200, 40, 40),
+            );
+            painter.rect_filled(
+                Rect::from_min_size(pos2(40.0, 28.0), vec2(72.0, 56.0)),
+                Rounding::same(8.0),
+                Color32::from_rgba_unmultiplied(40, 120, 220, 128),
+            );
+            painter.circle_filled(
+                pos2(64.0, 48.0),
+                24.0,
+                Color32::from_rgba_unmultiplied(255, 255, 255, 64),
+            );
+            painter.text(
+                pos2(8.0, 72.0),
+                Align2::LEFT_TOP,
+                "egui",
+                FontId::proportional(16.0),
+                Color32::WHITE,
+            );
+        });
+
+        harness.assert_matches_golden("blending", &image);
+    });
+}
+
+/// Shapes reaching past their clip rects, in two differently clipped runs of meshes.
+#[test]
+fn test_scissor() {
+    for_each_api(|mut harness| {
+        let image = harness.frame(|ctx| {
+            let painter = ctx.layer_painter(LayerId::background());
+
+            painter
+                .with_clip_rect(Rect::from_min_max(pos2(16.0, 16.0), pos2(64.0, 80.0)))
+                .circle_filled(pos2(64.0, 48.0), 40.0, Color32::YELLOW);
+            painter
+                .with_clip_rect(Rect::from_min_max(pos2(72.0, 8.0), pos2(120.0, 40.0)))
+                .rect_filled(
+                    ctx.screen_rect(),
+                    Rounding::ZERO,
+                    Color32::from_rgba_unmultiplied(0, 255, 0, 160),
+                );
+        });
+
+        harness.assert_matches_golden("scissor", &image);
+    });
+}
+
+/// Opaque ramps over every other channel value, one texel or vertex color per pixel column.
+fn ramps() -> [Vec<Color32>; 2] {
+    let v = |x: usize| (x * 2) as u8;
+    [
+        (0..SIZE[0] as usize)
+            .map(|x| Color32::from_gray(v(x)))
+            .collect(),
+        (0..SIZE[0] as usize)
+            .map(|x| Color32::from_rgb(v(x), 255 - v(x), v(x) / 3))
+            .collect(),
+    ]
+}
+
+/// Textures and vertex colors of every value, which egui expects to come out exactly as they are
+/// when drawn untinted, and multiplied in gamma space when tinted.
+#[test]
+fn test_srgb() {
+    for_each_api(|mut harness| {
+        let ramps = ramps();
+        let mut texture = None;
+
+        let image = harness.frame(|ctx| {
+            let texture = texture.get_or_insert_with(|| {
+                let image = ColorImage {
+                    size: [SIZE[0] as usize, 2],
+                    pixels: ramps.concat(),
+                };
+                ctx.load_texture("ramps", image, TextureOptions::NEAREST)
+            });
+
+            // Rows of 12 pixels: the texture untinted, the texture tinted, and the same colors as vertex colors.
+            let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+            let painter = ctx.layer_painter(LayerId::background());
+            painter.image(
+                texture.id(),
+                Rect::from_min_size(pos2(0.0, 0.0), vec2(SIZE[0] as f32, 24.0)),
+                uv,
+                Color32::WHITE,
+            );
+            painter.image(
+                texture.id(),
+                Rect::from_min_size(pos2(0.0, 24.0), vec2(SIZE[0] as f32, 24.0)),
+                uv,
+                Color32::from_rgba_premultiplied(128, 128, 128, 128),
+            );
+
+            // A mesh of its own, as shapes would be feathered along their edges.
+            let mut mesh = Mesh::default();
+            for (row, ramp) in ramps.iter().enumerate() {
+                for (x, &color) in ramp.iter().enumerate() {
+                    let min = pos2(x as f32, 48.0 + row as f32 * 12.0);
+                    mesh.add_colored_rect(Rect::from_min_size(min, vec2(1.0, 12.0)), color);
+                }
+            }
+            painter.add(Shape::mesh(mesh));
+        });
+
+        // The goldens of the legacy shaders record their approximation, the others have to be exact.
+        if matches!(harness.api, Api::GlCore | Api::Gles3) {
+            for (row, ramp) in ramps.iter().enumerate() {
+                for (x, &color) in ramp.iter().enumerate() {
+                    for y in [row as u32 * 12 + 6, 48 + row as u32 * 12 + 6] {
+                        assert_eq!(
+                            image.pixel(x as u32, y),
+                            color.to_array(),
+                            "{:?} at ({x}, {y})",
+                            harness.api
+                        );
+                    }
+                }
+            }
+        }
+
+        harness.assert_matches_golden("srgb", &image);
+    });
+}
+
+fn checkerboard(size: usize, a: Color32, b: Color32) -> ColorImage {
+    let pixels = (0..size * size)
+        .map(|i| if (i % size + i / size) % 2 == 0 { a } else { b })
+        .collect();
+    ColorImage {
+        size: [size, size],
+        pixels,
+    }
+}
+
+/// Draws the textures scene twice, the second time with a partial update of the checkerboard,
+/// and returns the second frame. `host` runs in between, like a game drawing before the overlay.
+fn textures_scene(harness: &mut Harness, host: impl FnOnce()) -> Image {
+    let mut textures: Option<[TextureHandle; 2]> = None;
+    let mut frame = 0;
+
+    let mut scene = |ctx: &Context| {
+        let [checker, gradient] = textures.get_or_insert_with(|| {
+            let gradient = ColorImage {
+                size: [4, 1],
+                pixels: vec![
+                    Color32::BLACK,
+                    Color32::RED,
+                    Color32::from_rgb(255, 200, 0),
+                    Color32::WHITE,
+                ],
+            };
+            [
+                ctx.load_texture(
+                    "checker",
+                    checkerboard(8, Color32::WHITE, Color32::from_rgb(30, 30, 200)),
+                    TextureOptions::NEAREST,
+                ),
+                ctx.load_texture("gradient", gradient, TextureOptions::LINEAR),
+            ]
+        });
+        if frame == 1 {
+            checker.set_partial(
+                [2, 2],
+                checkerboard(4, Color32::RED, Color32::GREEN),
+                TextureOptions::NEAREST,
+            );
+        }
+        frame += 1;
+
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let painter = ctx.layer_painter(LayerId::background());
+        painter.image(
+            checker.id(),
+            Rect::from_min_size(pos2(8.0, 8.0), vec2(48.0, 48.0)),
+            uv,
+            Color32::WHITE,
+        );
+        painter.image(
+            checker.id(),
+            Rect::from_min_size(pos2(64.0, 8.0), vec2(48.0, 48.0)),
+            uv,
+            Color32::from_rgba_premultiplied(128, 128, 128, 128),
+        );
+        painter.image(
+            gradient.id(),
+            Rect::from_min_size(pos2(8.0, 64.0), vec2(112.0, 24.0)),
+            uv,
+            Color32::WHITE,
+        );
+    };
+
+    harness.frame(&mut scene);
+    host();
+    harness.frame(&mut scene)
+}
+
+/// User textures with nearest and linear filtering, a tint, and a partial update after the first upload.
+#[test]
+fn test_textures() {
+    for_each_api(|mut harness| {
+        let image = textures_scene(&mut harness, || {});
+
+        harness.assert_matches_golden("textures", &image);
+    });
+}
+
+/// The partial update of an uploaded texture with the host's pixel unpack buffer and row length still set,
+/// which would read the pixels from that buffer and skip into them.
+#[test]
+fn test_textures_with_host_unpack_state() {
+    for_each_api(|mut harness| {
+        let mut buffer = 0;
+        let image = textures_scene(&mut harness, || unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, buffer);
+            gl::BufferData(
+                gl::PIXEL_UNPACK_BUFFER,
+                16,
+                std::ptr::null(),
+                gl::STREAM_DRAW,
+            );
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 64);
+            gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, 3);
+            gl::PixelStorei(gl::UNPACK_SKIP_ROWS, 2);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 8);
+        });
+
+        harness.assert_matches_golden("textures", &image);
+
+        // The host gets its state back.
+        let get = |pname| {
+            let mut value = 0;
+            unsafe { gl::GetIntegerv(pname, &mut value) };
+            value
+        };
+        assert_eq!(get(gl::PIXEL_UNPACK_BUFFER_BINDING), buffer as i32);
+        assert_eq!(get(gl::UNPACK_ROW_LENGTH), 64);
+        assert_eq!(get(gl::UNPACK_ALIGNMENT), 8);
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            gl::DeleteBuffers(1, &buffer);
+        }
+    });
+}
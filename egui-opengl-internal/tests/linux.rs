@@ -0,0 +1,196 @@
+//! Runs [`LinuxApp`] the way a swap buffers hook would: with the host's context current on its surface.
+//!
+//! The EGL tests use Mesa's surfaceless platform with a pbuffer standing in for the game window.
+//! The GLX test needs an X server, e.g. `xvfb-run cargo test --test linux`, and is skipped without `DISPLAY`.
+#![cfg(target_os = "linux")]
+
+mod support;
+
+use egui::{pos2, vec2, Color32, Context, LayerId, Rect, Rounding};
+use egui_opengl_internal::{
+    linux::{ContextMode, EglSurface, GlxSurface},
+    EglApp, GlxApp, GlxLoader,
+};
+use support::{clear_framebuffer, read_framebuffer, Api, HeadlessContext, Image, BACKGROUND};
+
+const SIZE: [u32; 2] = [128, 96];
+const RED: [u8; 4] = [200, 40, 40, 255];
+
+/// Paints a red rect and records the screen rect egui was given.
+fn scene(ctx: &Context, screen_rect: &mut Option<Rect>) {
+    *screen_rect = Some(ctx.screen_rect());
+    ctx.layer_painter(LayerId::background()).rect_filled(
+        Rect::from_min_size(pos2(16.0, 16.0), vec2(48.0, 32.0)),
+        Rounding::ZERO,
+        Color32::from_rgb(RED[0], RED[1], RED[2]),
+    );
+}
+
+fn assert_scene(image: &Image) {
+    let near = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).all(|(a, b)| a.abs_diff(b) <= 3);
+    assert!(near(image.pixel(40, 32), RED), "{:?}", image.pixel(40, 32));
+    assert!(
+        near(image.pixel(100, 80), BACKGROUND),
+        "{:?}",
+        image.pixel(100, 80)
+    );
+}
+
+fn current_program() -> i32 {
+    let mut program = 0;
+    unsafe { gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program) };
+    program
+}
+
+fn test_egl(mode: ContextMode) {
+    for api in [Api::GlCore, Api::Gles3] {
+        let Some(host) = HeadlessContext::with_pbuffer(api, SIZE) else {
+            eprintln!("Skipping {api:?}: no headless EGL context available");
+            continue;
+        };
+        let surface = EglSurface {
+            display: host.display(),
+            surface: host.surface(),
+        };
+
+        let app = EglApp::<Option<Rect>>::new();
+        app.init_with_mode(surface, scene, None, Context::default(), mode)
+            .expect("App should initialize");
+        assert!(app.is_ready());
+        assert!(host.is_current());
+
+        for _ in 0..2 {
+            clear_framebuffer(0, SIZE);
+            app.render(surface).expect("Frame should render");
+
+            assert!(host.is_current());
+            assert_eq!(current_program(), 0);
+            assert_scene(&read_framebuffer(0, SIZE));
+        }
+        assert_eq!(
+            *app.lock_state(),
+            Some(Rect::from_min_size(
+                pos2(0.0, 0.0),
+                vec2(SIZE[0] as f32, SIZE[1] as f32)
+            ))
+        );
+
+        app.destroy().expect("App should be destroyed");
+        assert!(!app.is_ready());
+        assert!(host.is_current());
+    }
+}
+
+#[test]
+fn test_egl_dedicated_context() {
+    test_egl(ContextMode::Dedicated);
+}
+
+#[test]
+fn test_egl_host_context() {
+    test_egl(ContextMode::Host);
+}
+
+#[test]
+fn test_glx_dedicated_context() {
+    use std::ptr;
+    use x11_dl::{glx, xlib};
+
+    let (Ok(xlib), Ok(glx)) = (xlib::Xlib::open(), glx::Glx::open()) else {
+        eprintln!("Skipping: libX11 or libGL not found");
+        return;
+    };
+
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            eprintln!("Skipping: no X server, run under xvfb-run");
+            return;
+        }
+
+        let attribs = [
+            glx::GLX_DRAWABLE_TYPE,
+            glx::GLX_WINDOW_BIT,
+            glx::GLX_RENDER_TYPE,
+            glx::GLX_RGBA_BIT,
+            glx::GLX_DOUBLEBUFFER,
+            xlib::True,
+            glx::GLX_RED_SIZE,
+            8,
+            glx::GLX_GREEN_SIZE,
+            8,
+            glx::GLX_BLUE_SIZE,
+            8,
+            0,
+        ];
+        let mut count = 0;
+        let configs = (glx.glXChooseFBConfig)(
+            display,
+            (xlib.XDefaultScreen)(display),
+            attribs.as_ptr(),
+            &mut count,
+        );
+        assert!(!configs.is_null() && count > 0, "no GLX config");
+        let config = *configs;
+        (xlib.XFree)(configs as *mut _);
+
+        let visual = (glx.glXGetVisualFromFBConfig)(display, config);
+        let root = (xlib.XDefaultRootWindow)(display);
+        let mut window_attribs: xlib::XSetWindowAttributes = std::mem::zeroed();
+        window_attribs.colormap =
+            (xlib.XCreateColormap)(display, root, (*visual).visual, xlib::AllocNone);
+        let window = (xlib.XCreateWindow)(
+            display,
+            root,
+            0,
+            0,
+            SIZE[0],
+            SIZE[1],
+            0,
+            (*visual).depth,
+            xlib::InputOutput as u32,
+            (*visual).visual,
+            xlib::CWColormap,
+            &mut window_attribs,
+        );
+        (xlib.XMapWindow)(display, window);
+        (xlib.XSync)(display, xlib::False);
+
+        let host = (glx.glXCreateNewContext)(
+            display,
+            config,
+            glx::GLX_RGBA_TYPE,
+            ptr::null_mut(),
+            xlib::True,
+        );
+        assert_ne!(
+            (glx.glXMakeContextCurrent)(display, window, window, host),
+            0
+        );
+        let loader = GlxLoader::new().unwrap();
+        assert!(egui_opengl_internal::load_gl(&loader).is_complete());
+
+        let surface = GlxSurface {
+            display,
+            drawable: window,
+        };
+        let app = GlxApp::<Option<Rect>>::new();
+        app.init_with_state(surface, scene, None)
+            .expect("App should initialize");
+
+        clear_framebuffer(0, SIZE);
+        app.render(surface).expect("Frame should render");
+
+        assert_eq!((glx.glXGetCurrentContext)(), host);
+        assert_eq!(current_program(), 0);
+        assert_scene(&read_framebuffer(0, SIZE));
+
+        app.destroy().expect("App should be destroyed");
+        (glx.glXMakeContextCurrent)(display, 0, 0, ptr::null_mut());
+        (glx.glXDestroyContext)(display, host);
+        (xlib.XDestroyWindow)(display, window);
+        (xlib.XFreeColormap)(display, window_attribs.colormap);
+        (xlib.XFree)(visual as *mut _);
+        (xlib.XCloseDisplay)(display);
+    }
+}
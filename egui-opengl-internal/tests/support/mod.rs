@@ -0,0 +1,509 @@
+//! Headless Mesa contexts and golden-image comparison shared by the integration tests.
+// Every test binary uses a different part of the support code.
+#![allow(dead_code)]
+
+use egui_opengl_internal::{load_gl, EglLoader};
+use gl::types::{GLsizei, GLuint};
+use std::{
+    ffi::{c_char, c_void, CStr},
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+type EglDisplay = *mut c_void;
+type EglConfig = *mut c_void;
+type EglContext = *mut c_void;
+type EglSurface = *mut c_void;
+type EglBoolean = u32;
+type EglInt = i32;
+
+const EGL_NONE: EglInt = 0x3038;
+const EGL_SURFACE_TYPE: EglInt = 0x3033;
+const EGL_PBUFFER_BIT: EglInt = 0x0001;
+const EGL_RED_SIZE: EglInt = 0x3024;
+const EGL_GREEN_SIZE: EglInt = 0x3023;
+const EGL_BLUE_SIZE: EglInt = 0x3022;
+const EGL_ALPHA_SIZE: EglInt = 0x3021;
+const EGL_WIDTH: EglInt = 0x3057;
+const EGL_HEIGHT: EglInt = 0x3056;
+const EGL_RENDERABLE_TYPE: EglInt = 0x3040;
+const EGL_OPENGL_BIT: EglInt = 0x0008;
+const EGL_OPENGL_ES2_BIT: EglInt = 0x0004;
+const EGL_OPENGL_ES3_BIT: EglInt = 0x0040;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_OPENGL_ES_API: u32 = 0x30A0;
+const EGL_CONTEXT_MAJOR_VERSION: EglInt = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: EglInt = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: EglInt = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EglInt = 0x0001;
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+
+/// The EGL entry points the tests need, resolved at runtime so a missing `libEGL` skips instead of failing to link.
+struct Egl {
+    get_platform_display: unsafe extern "C" fn(u32, *mut c_void, *const EglInt) -> EglDisplay,
+    initialize: unsafe extern "C" fn(EglDisplay, *mut EglInt, *mut EglInt) -> EglBoolean,
+    bind_api: unsafe extern "C" fn(u32) -> EglBoolean,
+    choose_config: unsafe extern "C" fn(
+        EglDisplay,
+        *const EglInt,
+        *mut EglConfig,
+        EglInt,
+        *mut EglInt,
+    ) -> EglBoolean,
+    create_context:
+        unsafe extern "C" fn(EglDisplay, EglConfig, EglContext, *const EglInt) -> EglContext,
+    make_current:
+        unsafe extern "C" fn(EglDisplay, *mut c_void, *mut c_void, EglContext) -> EglBoolean,
+    destroy_context: unsafe extern "C" fn(EglDisplay, EglContext) -> EglBoolean,
+    create_pbuffer_surface:
+        unsafe extern "C" fn(EglDisplay, EglConfig, *const EglInt) -> EglSurface,
+    destroy_surface: unsafe extern "C" fn(EglDisplay, EglSurface) -> EglBoolean,
+    get_current_context: unsafe extern "C" fn() -> EglContext,
+    display: EglDisplay,
+}
+
+unsafe impl Send for Egl {}
+unsafe impl Sync for Egl {}
+
+impl Egl {
+    /// Opens `libEGL` and initializes the surfaceless display once per test binary.
+    fn get() -> Option<&'static Egl> {
+        static EGL: OnceLock<Option<Egl>> = OnceLock::new();
+        EGL.get_or_init(|| unsafe { Self::load() }).as_ref()
+    }
+
+    unsafe fn load() -> Option<Egl> {
+        let library = libc::dlopen(c"libEGL.so.1".as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL);
+        if library.is_null() {
+            return None;
+        }
+        let get_proc_address: unsafe extern "C" fn(*const c_char) -> *mut c_void =
+            function(symbol(library, c"eglGetProcAddress")?);
+        let proc = |name: &CStr| {
+            let f = get_proc_address(name.as_ptr());
+            (!f.is_null()).then_some(f)
+        };
+
+        let mut egl = Egl {
+            get_platform_display: function(proc(c"eglGetPlatformDisplayEXT")?),
+            initialize: function(symbol(library, c"eglInitialize")?),
+            bind_api: function(symbol(library, c"eglBindAPI")?),
+            choose_config: function(symbol(library, c"eglChooseConfig")?),
+            create_context: function(symbol(library, c"eglCreateContext")?),
+            make_current: function(symbol(library, c"eglMakeCurrent")?),
+            destroy_context: function(symbol(library, c"eglDestroyContext")?),
+            create_pbuffer_surface: function(symbol(library, c"eglCreatePbufferSurface")?),
+            destroy_surface: function(symbol(library, c"eglDestroySurface")?),
+            get_current_context: function(symbol(library, c"eglGetCurrentContext")?),
+            display: std::ptr::null_mut(),
+        };
+
+        let display = (egl.get_platform_display)(
+            EGL_PLATFORM_SURFACELESS_MESA,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+        );
+        if display.is_null()
+            || (egl.initialize)(display, std::ptr::null_mut(), std::ptr::null_mut()) == 0
+        {
+            return None;
+        }
+        egl.display = display;
+
+        Some(egl)
+    }
+}
+
+unsafe fn symbol(library: *mut c_void, name: &CStr) -> Option<*mut c_void> {
+    let f = libc::dlsym(library, name.as_ptr());
+    (!f.is_null()).then_some(f)
+}
+
+/// Casts a symbol to the function pointer type of the field it is stored in.
+unsafe fn function<F: Copy>(f: *mut c_void) -> F {
+    assert_eq!(std::mem::size_of::<F>(), std::mem::size_of::<*mut c_void>());
+    std::mem::transmute_copy(&f)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    /// Desktop GL 3.3 core profile.
+    GlCore,
+    /// OpenGL ES 3.0.
+    Gles3,
+    /// Desktop GL 2.1 compatibility profile. Mesa only reports that with `MESA_GL_VERSION_OVERRIDE=2.1`
+    /// and `MESA_GLSL_VERSION_OVERRIDE=120`, and otherwise hands out its newest compatibility profile.
+    Gl21,
+    /// OpenGL ES 2.0. Mesa only reports that with `MESA_GLES_VERSION_OVERRIDE=2.0`, and otherwise hands out ES 3.2.
+    Gles2,
+}
+
+/// A context with no surface or an RGBA8 pbuffer, current on the calling thread until dropped.
+pub struct HeadlessContext {
+    egl: &'static Egl,
+    context: EglContext,
+    surface: EglSurface,
+}
+
+impl HeadlessContext {
+    /// `None` if this machine has no EGL with `EGL_MESA_platform_surfaceless` for `api`.
+    pub fn new(api: Api) -> Option<Self> {
+        Self::create(api, None)
+    }
+
+    /// Same as [`Self::new`], but draws to a pbuffer the way a game draws to its window.
+    pub fn with_pbuffer(api: Api, size: [u32; 2]) -> Option<Self> {
+        Self::create(api, Some(size))
+    }
+
+    pub fn display(&self) -> *mut c_void {
+        self.egl.display
+    }
+
+    /// The pbuffer, null without one.
+    pub fn surface(&self) -> *mut c_void {
+        self.surface
+    }
+
+    pub fn is_current(&self) -> bool {
+        unsafe { (self.egl.get_current_context)() == self.context }
+    }
+
+    fn create(api: Api, pbuffer: Option<[u32; 2]>) -> Option<Self> {
+        let egl = Egl::get()?;
+
+        let (api_enum, renderable, context_attribs) = match api {
+            Api::GlCore => (
+                EGL_OPENGL_API,
+                EGL_OPENGL_BIT,
+                vec![
+                    EGL_CONTEXT_MAJOR_VERSION,
+                    3,
+                    EGL_CONTEXT_MINOR_VERSION,
+                    3,
+                    EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                    EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                    EGL_NONE,
+                ],
+            ),
+            Api::Gles3 => (
+                EGL_OPENGL_ES_API,
+                EGL_OPENGL_ES3_BIT,
+                vec![EGL_CONTEXT_MAJOR_VERSION, 3, EGL_NONE],
+            ),
+            Api::Gl21 => (
+                EGL_OPENGL_API,
+                EGL_OPENGL_BIT,
+                vec![
+                    EGL_CONTEXT_MAJOR_VERSION,
+                    2,
+                    EGL_CONTEXT_MINOR_VERSION,
+                    1,
+                    EGL_NONE,
+                ],
+            ),
+            Api::Gles2 => (
+                EGL_OPENGL_ES_API,
+                EGL_OPENGL_ES2_BIT,
+                vec![EGL_CONTEXT_MAJOR_VERSION, 2, EGL_NONE],
+            ),
+        };
+
+        unsafe {
+            if (egl.bind_api)(api_enum) == 0 {
+                return None;
+            }
+
+            // The surfaceless platform only has pbuffer configs, the default would ask for windows.
+            let config_attribs = [
+                EGL_SURFACE_TYPE,
+                EGL_PBUFFER_BIT,
+                EGL_RENDERABLE_TYPE,
+                renderable,
+                EGL_RED_SIZE,
+                8,
+                EGL_GREEN_SIZE,
+                8,
+                EGL_BLUE_SIZE,
+                8,
+                EGL_ALPHA_SIZE,
+                8,
+                EGL_NONE,
+            ];
+            let mut config = std::ptr::null_mut();
+            let mut configs = 0;
+            if (egl.choose_config)(
+                egl.display,
+                config_attribs.as_ptr(),
+                &mut config,
+                1,
+                &mut configs,
+            ) == 0
+                || configs == 0
+            {
+                return None;
+            }
+
+            let context = (egl.create_context)(
+                egl.display,
+                config,
+                std::ptr::null_mut(),
+                context_attribs.as_ptr(),
+            );
+            if context.is_null() {
+                return None;
+            }
+            let surface = match pbuffer {
+                Some([width, height]) => {
+                    let attribs = [
+                        EGL_WIDTH,
+                        width as EglInt,
+                        EGL_HEIGHT,
+                        height as EglInt,
+                        EGL_NONE,
+                    ];
+                    (egl.create_pbuffer_surface)(egl.display, config, attribs.as_ptr())
+                }
+                None => std::ptr::null_mut(),
+            };
+            let context = Self {
+                egl,
+                context,
+                surface,
+            };
+            if pbuffer.is_some() && surface.is_null()
+                || (egl.make_current)(egl.display, surface, surface, context.context) == 0
+            {
+                return None;
+            }
+
+            // Mesa hands out the same dispatch stubs for every context, so loading once is enough.
+            static LOADED: OnceLock<bool> = OnceLock::new();
+            let loaded = *LOADED.get_or_init(|| {
+                EglLoader::new().is_some_and(|loader| load_gl(&loader).is_complete())
+            });
+            loaded.then_some(context)
+        }
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            (self.egl.make_current)(
+                self.egl.display,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            (self.egl.destroy_context)(self.egl.display, self.context);
+            if !self.surface.is_null() {
+                (self.egl.destroy_surface)(self.egl.display, self.surface);
+            }
+        }
+    }
+}
+
+/// The opaque background a game frame would leave in the framebuffer before the overlay draws.
+pub const BACKGROUND: [u8; 4] = [26, 26, 31, 255];
+
+/// Binds `framebuffer`, `0` for the surface, and fills it with [`BACKGROUND`].
+pub fn clear_framebuffer(framebuffer: GLuint, [width, height]: [u32; 2]) {
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::Viewport(0, 0, width as GLsizei, height as GLsizei);
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+}
+
+/// Reads `framebuffer` back with the top row first, as PNGs store it.
+pub fn read_framebuffer(framebuffer: GLuint, [width, height]: [u32; 2]) -> Image {
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+    }
+
+    let row = width as usize * 4;
+    let pixels = pixels.chunks_exact(row).rev().flatten().copied().collect();
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// An RGBA8 framebuffer object painted into instead of a window.
+pub struct OffscreenTarget {
+    framebuffer: GLuint,
+    renderbuffer: GLuint,
+    pub size: [u32; 2],
+}
+
+impl OffscreenTarget {
+    pub fn new(size: [u32; 2]) -> Self {
+        let mut framebuffer = 0;
+        let mut renderbuffer = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::GenRenderbuffers(1, &mut renderbuffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::RGBA8,
+                size[0] as GLsizei,
+                size[1] as GLsizei,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                renderbuffer,
+            );
+            assert_eq!(
+                gl::CheckFramebufferStatus(gl::FRAMEBUFFER),
+                gl::FRAMEBUFFER_COMPLETE
+            );
+        }
+
+        Self {
+            framebuffer,
+            renderbuffer,
+            size,
+        }
+    }
+
+    pub fn clear(&self) {
+        clear_framebuffer(self.framebuffer, self.size);
+    }
+
+    pub fn read(&self) -> Image {
+        read_framebuffer(self.framebuffer, self.size)
+    }
+}
+
+impl Drop for OffscreenTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteRenderbuffers(1, &self.renderbuffer);
+        }
+    }
+}
+
+/// Tightly packed RGBA8 pixels, top row first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    fn load(path: &PathBuf) -> Image {
+        let file = std::fs::File::open(path).unwrap_or_else(|err| {
+            panic!(
+                "Missing golden {}: {err}. Run with UPDATE_GOLDENS=1 to create it.",
+                path.display()
+            )
+        });
+        let mut reader = png::Decoder::new(file).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(info.bit_depth, png::BitDepth::Eight);
+        pixels.truncate(info.buffer_size());
+
+        Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        }
+    }
+
+    fn save(&self, path: &PathBuf) {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&self.pixels)
+            .unwrap();
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    /// Pixels where any channel differs from `other` by more than `tolerance`.
+    fn mismatched_pixels(&self, other: &Image, tolerance: u8) -> usize {
+        self.pixels
+            .as_chunks::<4>()
+            .0
+            .iter()
+            .zip(other.pixels.as_chunks::<4>().0)
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(a, b)| a.abs_diff(*b) > tolerance)
+            })
+            .count()
+    }
+}
+
+/// Per-channel difference tolerated for rasterizer and driver version differences.
+const CHANNEL_TOLERANCE: u8 = 3;
+/// Share of pixels allowed beyond [`CHANNEL_TOLERANCE`], e.g. along anti-aliased glyph edges.
+const MAX_MISMATCHED: f64 = 0.002;
+
+/// Compares `image` with `tests/golden/<name>.png`, or rewrites the golden when `UPDATE_GOLDENS` is set.
+/// On failure the rendered image is written next to the test binary for inspection.
+pub fn assert_matches_golden(name: &str, image: &Image) {
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"));
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        image.save(&golden_path);
+        return;
+    }
+
+    let golden = Image::load(&golden_path);
+    assert_eq!(
+        [image.width, image.height],
+        [golden.width, golden.height],
+        "{name}: size differs from the golden"
+    );
+
+    let mismatched = image.mismatched_pixels(&golden, CHANNEL_TOLERANCE);
+    let allowed = (image.pixels.len() / 4) as f64 * MAX_MISMATCHED;
+    if mismatched as f64 > allowed {
+        let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+            .join("golden-failures")
+            .join(format!("{name}.png"));
+        image.save(&actual_path);
+        panic!(
+            "{name}: {mismatched} pixels differ from {} by more than {CHANNEL_TOLERANCE}, rendered image saved to {}",
+            golden_path.display(),
+            actual_path.display()
+        );
+    }
+}
@@ -0,0 +1,545 @@
+//! Linux counterpart of `example-wnd`, loaded into a program with `LD_PRELOAD` instead of injected:
+//!
+//! `LD_PRELOAD=target/debug/libexample_preload.so glxgears`
+//!
+//! The library exports `glXSwapBuffers`, `eglSwapBuffers`, `XPending` and the Xlib functions that take events off
+//! the queue itself, so the dynamic linker binds the program to them instead of to `libGL`, `libEGL` and `libX11`.
+//! Programs that load those libraries at runtime (SDL, GLFW) look the functions up instead, so `dlsym` and the
+//! `GetProcAddress` functions are replaced too.
+#![cfg(target_os = "linux")]
+// The exported functions keep the names and contracts of the ones they replace.
+#![allow(non_snake_case, static_mut_refs, clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, c_int, c_long, c_uint, c_void, CStr};
+use std::mem::transmute_copy;
+use std::ops::DerefMut;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+
+use egui::{Color32, Context, Key, Modifiers, RichText, ScrollArea, Slider, Widget};
+use once_cell::sync::Lazy;
+use x11_dl::{glx::GLXDrawable, xlib};
+
+use egui_opengl_internal::linux::{EglSurface, GlxSurface, Platform};
+use egui_opengl_internal::{EglApp, GlxApp, LinuxApp};
+
+struct UIState {
+    ui_check: bool,
+    text: String,
+    value: f32,
+    color: [f32; 3],
+}
+
+impl UIState {
+    fn new() -> Self {
+        Self {
+            ui_check: true,
+            text: String::from("Test"),
+            value: 0.0,
+            color: [0.0, 0.0, 0.0],
+        }
+    }
+}
+static STATE: Lazy<Arc<Mutex<UIState>>> = Lazy::new(|| Arc::new(Mutex::new(UIState::new())));
+
+static mut GLX_APP: GlxApp<i32> = GlxApp::new();
+static mut EGL_APP: EglApp<i32> = EglApp::new();
+static EXITING: AtomicBool = AtomicBool::new(false);
+/// Window the overlay draws to, `0` for any window when it is not known.
+static WINDOW: AtomicU64 = AtomicU64::new(0);
+/// Events in the queue of each `Display*` that egui has already seen, see [`XPending`]. They are told apart by content,
+/// as the program may take any of them first, e.g. with `XCheckIfEvent`.
+static FED_EVENTS: Mutex<Vec<(usize, Vec<[c_long; 24]>)>> = Mutex::new(Vec::new());
+
+type FnDlsym = unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_void;
+type FnGetProcAddress = unsafe extern "C" fn(*const c_char) -> *mut c_void;
+type FnGlxSwapBuffers = unsafe extern "C" fn(*mut xlib::Display, GLXDrawable);
+type FnEglSwapBuffers = unsafe extern "C" fn(*mut c_void, *mut c_void) -> c_uint;
+type FnXNextEvent = unsafe extern "C" fn(*mut xlib::Display, *mut xlib::XEvent) -> c_int;
+type FnXPending = unsafe extern "C" fn(*mut xlib::Display) -> c_int;
+type FnXPutBackEvent = unsafe extern "C" fn(*mut xlib::Display, *mut xlib::XEvent) -> c_int;
+type Predicate =
+    Option<unsafe extern "C" fn(*mut xlib::Display, *mut xlib::XEvent, *mut c_char) -> c_int>;
+type FnXIfEvent =
+    unsafe extern "C" fn(*mut xlib::Display, *mut xlib::XEvent, Predicate, *mut c_char) -> c_int;
+type FnXWindowEvent =
+    unsafe extern "C" fn(*mut xlib::Display, xlib::Window, c_long, *mut xlib::XEvent) -> c_int;
+type FnXMaskEvent = unsafe extern "C" fn(*mut xlib::Display, c_long, *mut xlib::XEvent) -> c_int;
+type FnXCheckTypedEvent =
+    unsafe extern "C" fn(*mut xlib::Display, c_int, *mut xlib::XEvent) -> c_int;
+type FnXCheckTypedWindowEvent =
+    unsafe extern "C" fn(*mut xlib::Display, xlib::Window, c_int, *mut xlib::XEvent) -> c_int;
+
+/// A function this library replaces, and the original it forwards to.
+struct Hook {
+    name: &'static CStr,
+    next: AtomicPtr<c_void>,
+}
+
+impl Hook {
+    const fn new(name: &'static CStr) -> Self {
+        Self {
+            name,
+            next: AtomicPtr::new(null_mut()),
+        }
+    }
+
+    /// The original function: the one the program looked up last, or else whichever library after this one exports it.
+    unsafe fn next<F: Copy>(&self) -> F {
+        let mut f = self.next.load(Ordering::Acquire);
+        if f.is_null() {
+            f = real_dlsym()(libc::RTLD_NEXT, self.name.as_ptr());
+            if f.is_null() {
+                panic!("{:?} not found", self.name);
+            }
+            self.next.store(f, Ordering::Release);
+        }
+
+        assert_eq!(size_of::<F>(), size_of::<*mut c_void>());
+        transmute_copy(&f)
+    }
+}
+
+static DLSYM: Hook = Hook::new(c"dlsym");
+static GLX_GET_PROC_ADDRESS: Hook = Hook::new(c"glXGetProcAddress");
+static GLX_GET_PROC_ADDRESS_ARB: Hook = Hook::new(c"glXGetProcAddressARB");
+static EGL_GET_PROC_ADDRESS: Hook = Hook::new(c"eglGetProcAddress");
+static GLX_SWAP_BUFFERS: Hook = Hook::new(c"glXSwapBuffers");
+static EGL_SWAP_BUFFERS: Hook = Hook::new(c"eglSwapBuffers");
+static X_NEXT_EVENT: Hook = Hook::new(c"XNextEvent");
+static X_PENDING: Hook = Hook::new(c"XPending");
+static X_PUT_BACK_EVENT: Hook = Hook::new(c"XPutBackEvent");
+static X_IF_EVENT: Hook = Hook::new(c"XIfEvent");
+static X_CHECK_IF_EVENT: Hook = Hook::new(c"XCheckIfEvent");
+static X_WINDOW_EVENT: Hook = Hook::new(c"XWindowEvent");
+static X_CHECK_WINDOW_EVENT: Hook = Hook::new(c"XCheckWindowEvent");
+static X_MASK_EVENT: Hook = Hook::new(c"XMaskEvent");
+static X_CHECK_MASK_EVENT: Hook = Hook::new(c"XCheckMaskEvent");
+static X_CHECK_TYPED_EVENT: Hook = Hook::new(c"XCheckTypedEvent");
+static X_CHECK_TYPED_WINDOW_EVENT: Hook = Hook::new(c"XCheckTypedWindowEvent");
+
+/// The `dlsym` of libc. Its own symbol is taken by [`dlsym`], so it is looked up by version.
+unsafe fn real_dlsym() -> FnDlsym {
+    let mut f = DLSYM.next.load(Ordering::Acquire);
+    if f.is_null() {
+        // glibc 2.34 moved it from libdl into libc, older versions differ by architecture.
+        for version in [c"GLIBC_2.34", c"GLIBC_2.2.5", c"GLIBC_2.17", c"GLIBC_2.0"] {
+            f = libc::dlvsym(libc::RTLD_NEXT, c"dlsym".as_ptr(), version.as_ptr());
+            if !f.is_null() {
+                break;
+            }
+        }
+        if f.is_null() {
+            // Unwinding out of `dlsym` is not possible, and there is nothing to forward to.
+            std::process::abort();
+        }
+        DLSYM.next.store(f, Ordering::Release);
+    }
+
+    transmute_copy(&f)
+}
+
+/// Swaps functions the program looks up for their hooks, remembering the originals to forward to.
+fn replace(name: *const c_char, f: *mut c_void) -> *mut c_void {
+    if name.is_null() || f.is_null() {
+        return f;
+    }
+
+    let (hook, replacement) = match unsafe { CStr::from_ptr(name) }.to_bytes() {
+        b"glXGetProcAddress" => (&GLX_GET_PROC_ADDRESS, glXGetProcAddress as *mut c_void),
+        b"glXGetProcAddressARB" => (
+            &GLX_GET_PROC_ADDRESS_ARB,
+            glXGetProcAddressARB as *mut c_void,
+        ),
+        b"eglGetProcAddress" => (&EGL_GET_PROC_ADDRESS, eglGetProcAddress as *mut c_void),
+        b"glXSwapBuffers" => (&GLX_SWAP_BUFFERS, glXSwapBuffers as *mut c_void),
+        b"eglSwapBuffers" => (&EGL_SWAP_BUFFERS, eglSwapBuffers as *mut c_void),
+        b"XNextEvent" => (&X_NEXT_EVENT, XNextEvent as *mut c_void),
+        b"XPending" => (&X_PENDING, XPending as *mut c_void),
+        b"XIfEvent" => (&X_IF_EVENT, XIfEvent as *mut c_void),
+        b"XCheckIfEvent" => (&X_CHECK_IF_EVENT, XCheckIfEvent as *mut c_void),
+        b"XWindowEvent" => (&X_WINDOW_EVENT, XWindowEvent as *mut c_void),
+        b"XCheckWindowEvent" => (&X_CHECK_WINDOW_EVENT, XCheckWindowEvent as *mut c_void),
+        b"XMaskEvent" => (&X_MASK_EVENT, XMaskEvent as *mut c_void),
+        b"XCheckMaskEvent" => (&X_CHECK_MASK_EVENT, XCheckMaskEvent as *mut c_void),
+        b"XCheckTypedEvent" => (&X_CHECK_TYPED_EVENT, XCheckTypedEvent as *mut c_void),
+        b"XCheckTypedWindowEvent" => (
+            &X_CHECK_TYPED_WINDOW_EVENT,
+            XCheckTypedWindowEvent as *mut c_void,
+        ),
+        _ => return f,
+    };
+    // Global lookups find this library first.
+    if f != replacement {
+        hook.next.store(f, Ordering::Release);
+    }
+    replacement
+}
+
+/// `dlsym(RTLD_NEXT, ...)` from the program resolves relative to this library rather than the caller.
+#[no_mangle]
+pub unsafe extern "C" fn dlsym(handle: *mut c_void, name: *const c_char) -> *mut c_void {
+    replace(name, real_dlsym()(handle, name))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn glXGetProcAddress(name: *const c_char) -> *mut c_void {
+    replace(name, GLX_GET_PROC_ADDRESS.next::<FnGetProcAddress>()(name))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn glXGetProcAddressARB(name: *const c_char) -> *mut c_void {
+    replace(
+        name,
+        GLX_GET_PROC_ADDRESS_ARB.next::<FnGetProcAddress>()(name),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn eglGetProcAddress(name: *const c_char) -> *mut c_void {
+    replace(name, EGL_GET_PROC_ADDRESS.next::<FnGetProcAddress>()(name))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn glXSwapBuffers(display: *mut xlib::Display, drawable: GLXDrawable) {
+    let surface = GlxSurface { display, drawable };
+
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        println!("glXSwapBuffers successfully hooked.");
+
+        if let Err(err) = GLX_APP.init_default(surface, ui) {
+            println!("Failed to initialize egui: {err}");
+            return;
+        }
+        WINDOW.store(drawable, Ordering::Relaxed);
+    });
+
+    draw(&GLX_APP, surface);
+    GLX_SWAP_BUFFERS.next::<FnGlxSwapBuffers>()(display, drawable)
+}
+
+/// EGL surfaces do not tell which window they belong to, so the overlay takes input from every window.
+#[no_mangle]
+pub unsafe extern "C" fn eglSwapBuffers(display: *mut c_void, surface: *mut c_void) -> c_uint {
+    let egl_surface = EglSurface { display, surface };
+
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        println!("eglSwapBuffers successfully hooked.");
+
+        if let Err(err) = EGL_APP.init_default(egl_surface, ui) {
+            println!("Failed to initialize egui: {err}");
+        }
+    });
+
+    draw(&EGL_APP, egl_surface);
+    EGL_SWAP_BUFFERS.next::<FnEglSwapBuffers>()(display, surface)
+}
+
+fn draw<P: Platform>(app: &LinuxApp<P, i32>, surface: P::Surface) {
+    if !app.is_ready() {
+        return;
+    }
+
+    if EXITING.load(Ordering::Relaxed) {
+        if let Err(err) = app.destroy() {
+            println!("Failed to destroy egui: {err}");
+        }
+        return;
+    }
+
+    if let Err(err) = app.render(surface) {
+        println!("Skipped egui frame: {err}");
+    }
+}
+
+/// Gives an event to egui. Returns `true` if egui wants it and the program should not see it.
+unsafe fn feed(event: &xlib::XEvent) -> bool {
+    let window = WINDOW.load(Ordering::Relaxed);
+    if window != 0 && event.any.window != window {
+        return false;
+    }
+
+    if GLX_APP.is_ready() {
+        GLX_APP.x_event(event)
+    } else if EGL_APP.is_ready() {
+        EGL_APP.x_event(event)
+    } else {
+        false
+    }
+}
+
+/// The events egui has seen that are still queued on `display`, forgetting them.
+fn take_fed_events(display: *mut xlib::Display) -> Vec<[c_long; 24]> {
+    let mut fed = FED_EVENTS.lock().unwrap();
+    match fed.iter().position(|(d, _)| *d == display as usize) {
+        Some(i) => fed.swap_remove(i).1,
+        None => vec![],
+    }
+}
+
+/// Forgets `event` if egui has seen it already, and returns whether it had.
+fn take_fed_event(display: *mut xlib::Display, event: &xlib::XEvent) -> bool {
+    let mut fed = FED_EVENTS.lock().unwrap();
+    let Some(i) = fed.iter().position(|(d, _)| *d == display as usize) else {
+        return false;
+    };
+
+    let events = &mut fed[i].1;
+    let pad = unsafe { event.pad };
+    let Some(j) = events.iter().position(|fed| *fed == pad) else {
+        return false;
+    };
+    events.remove(j);
+    if events.is_empty() {
+        fed.swap_remove(i);
+    }
+    true
+}
+
+/// Takes events with `fetch` until one egui does not want, and returns what `fetch` did for it.
+/// `fetch` returns `None` when no event matches, which is returned as `False`.
+unsafe fn next_unwanted(
+    display: *mut xlib::Display,
+    event: *mut xlib::XEvent,
+    mut fetch: impl FnMut() -> Option<c_int>,
+) -> c_int {
+    loop {
+        let Some(result) = fetch() else {
+            return xlib::False;
+        };
+        if take_fed_event(display, &*event) || !feed(&*event) {
+            return result;
+        }
+    }
+}
+
+/// Blocks until an event egui does not want arrives.
+#[no_mangle]
+pub unsafe extern "C" fn XNextEvent(
+    display: *mut xlib::Display,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let next_event = X_NEXT_EVENT.next::<FnXNextEvent>();
+    next_unwanted(display, event, || Some(next_event(display, event)))
+}
+
+/// Programs usually only call `XNextEvent` while this says there are events, so egui gets every queued event here
+/// and the ones it does not want are put back. Otherwise an event egui took could leave `XNextEvent` blocking.
+#[no_mangle]
+pub unsafe extern "C" fn XPending(display: *mut xlib::Display) -> c_int {
+    let count = X_PENDING.next::<FnXPending>()(display);
+    if count <= 0 || !(GLX_APP.is_ready() || EGL_APP.is_ready()) {
+        return count;
+    }
+
+    let next_event = X_NEXT_EVENT.next::<FnXNextEvent>();
+    let mut fed = take_fed_events(display);
+    let mut kept = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut event: xlib::XEvent = std::mem::zeroed();
+        next_event(display, &mut event);
+        if let Some(i) = fed.iter().position(|fed| *fed == event.pad) {
+            fed.swap_remove(i);
+            kept.push(event);
+        } else if !feed(&event) {
+            kept.push(event);
+        }
+    }
+
+    // Putting back pushes to the front of the queue, so the last event goes first.
+    let put_back_event = X_PUT_BACK_EVENT.next::<FnXPutBackEvent>();
+    for event in kept.iter_mut().rev() {
+        put_back_event(display, event);
+    }
+    if !kept.is_empty() {
+        let kept = kept.iter().map(|event| event.pad).collect();
+        FED_EVENTS.lock().unwrap().push((display as usize, kept));
+    }
+
+    kept.len() as c_int
+}
+
+/// The functions taking specific events off the queue skip the ones egui wants too.
+#[no_mangle]
+pub unsafe extern "C" fn XIfEvent(
+    display: *mut xlib::Display,
+    event: *mut xlib::XEvent,
+    predicate: Predicate,
+    arg: *mut c_char,
+) -> c_int {
+    let if_event = X_IF_EVENT.next::<FnXIfEvent>();
+    next_unwanted(display, event, || {
+        Some(if_event(display, event, predicate, arg))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCheckIfEvent(
+    display: *mut xlib::Display,
+    event: *mut xlib::XEvent,
+    predicate: Predicate,
+    arg: *mut c_char,
+) -> c_int {
+    let check_if_event = X_CHECK_IF_EVENT.next::<FnXIfEvent>();
+    next_unwanted(display, event, || {
+        found(check_if_event(display, event, predicate, arg))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XWindowEvent(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    mask: c_long,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let window_event = X_WINDOW_EVENT.next::<FnXWindowEvent>();
+    next_unwanted(display, event, || {
+        Some(window_event(display, window, mask, event))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCheckWindowEvent(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    mask: c_long,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let check_window_event = X_CHECK_WINDOW_EVENT.next::<FnXWindowEvent>();
+    next_unwanted(display, event, || {
+        found(check_window_event(display, window, mask, event))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XMaskEvent(
+    display: *mut xlib::Display,
+    mask: c_long,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let mask_event = X_MASK_EVENT.next::<FnXMaskEvent>();
+    next_unwanted(display, event, || Some(mask_event(display, mask, event)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCheckMaskEvent(
+    display: *mut xlib::Display,
+    mask: c_long,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let check_mask_event = X_CHECK_MASK_EVENT.next::<FnXMaskEvent>();
+    next_unwanted(display, event, || {
+        found(check_mask_event(display, mask, event))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCheckTypedEvent(
+    display: *mut xlib::Display,
+    ty: c_int,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let check_typed_event = X_CHECK_TYPED_EVENT.next::<FnXCheckTypedEvent>();
+    next_unwanted(display, event, || {
+        found(check_typed_event(display, ty, event))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCheckTypedWindowEvent(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    ty: c_int,
+    event: *mut xlib::XEvent,
+) -> c_int {
+    let check_typed_window_event = X_CHECK_TYPED_WINDOW_EVENT.next::<FnXCheckTypedWindowEvent>();
+    next_unwanted(display, event, || {
+        found(check_typed_window_event(display, window, ty, event))
+    })
+}
+
+/// The result of an `XCheck*Event`, `None` if it found nothing.
+fn found(result: c_int) -> Option<c_int> {
+    (result != xlib::False).then_some(result)
+}
+
+fn ui(ctx: &Context, _: &mut i32) {
+    egui::containers::Window::new("Main menu").show(ctx, |ui| {
+        test_ui(ctx, ui);
+
+        ui.separator();
+        if ui.button("exit").clicked() {
+            EXITING.store(true, Ordering::Relaxed);
+        }
+    });
+}
+
+fn test_ui(ctx: &Context, ui: &mut egui::Ui) {
+    let state = STATE.as_ref();
+
+    // UI Elements
+    ui.label(RichText::new("Test").color(Color32::LIGHT_BLUE));
+    ui.label(RichText::new("Other").color(Color32::WHITE));
+    ui.separator();
+
+    let input = ctx.input(|input| input.pointer.clone());
+    ui.label(format!(
+        "X1: {} X2: {}",
+        input.button_down(egui::PointerButton::Extra1),
+        input.button_down(egui::PointerButton::Extra2)
+    ));
+
+    let mods = ui.input(|input| input.modifiers);
+    ui.label(format!(
+        "Ctrl: {} Shift: {} Alt: {}",
+        mods.ctrl, mods.shift, mods.alt
+    ));
+
+    if ui.input(|input| input.modifiers.matches_exact(Modifiers::CTRL) && input.key_pressed(Key::R))
+    {
+        println!("Pressed");
+    }
+
+    // Checkbox and Text Input
+    let mut binding = state.lock().unwrap();
+    let ui_state = binding.deref_mut();
+    if ui
+        .checkbox(&mut ui_state.ui_check, "Some checkbox")
+        .changed()
+    {
+        println!("Checkbox toggled to: {}", ui_state.ui_check);
+    }
+    if ui.text_edit_singleline(&mut ui_state.text).changed() {
+        println!("Set edit singleline to: {}", ui_state.text);
+    }
+
+    // Scroll Area
+    ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+        for i in 1..=100 {
+            ui.label(format!("Label: {}", i));
+        }
+    });
+
+    // Slider
+    if Slider::new(&mut ui_state.value, -1.0..=1.0)
+        .ui(ui)
+        .changed()
+    {
+        println!("Slider set value to: {}", ui_state.value);
+    }
+
+    // Color Picker
+    if ui.color_edit_button_rgb(&mut ui_state.color).changed() {
+        println!("Color edit button set color to: {:?}", ui_state.color);
+    }
+
+    // Display Pointer Info
+    ui.label(format!(
+        "{:?}",
+        ui.input(|input| input.pointer.button_down(egui::PointerButton::Primary))
+    ));
+}
@@ -82,7 +82,10 @@ fn hk_wgl_swap_buffers(hdc: HDC) -> HRESULT {
         INIT.call_once(|| {
             println!("wglSwapBuffers successfully hooked.");
 
-            APP.init_default(hdc, window, ui);
+            if let Err(err) = APP.init_default(hdc, window, ui) {
+                println!("Failed to initialize egui: {err}");
+                return;
+            }
 
             OLD_WND_PROC = Some(transmute(
                     SetWindowLongPtrW(
@@ -92,11 +95,17 @@ fn hk_wgl_swap_buffers(hdc: HDC) -> HRESULT {
             )));
         });
 
+        if !APP.is_ready() {
+            return WglSwapBuffersHook.call(hdc);
+        }
+
         if !APP.get_window().eq(&window) {
             SetWindowLongPtrW(window, GWLP_WNDPROC, hk_wnd_proc as usize as _);
         }
 
-        APP.render(hdc);
+        if let Err(err) = APP.render(hdc) {
+            println!("Skipped egui frame: {err}");
+        }
         WglSwapBuffersHook.call(hdc)
     }
 }